@@ -0,0 +1,140 @@
+use serde::{Deserialize, Serialize};
+use solana_sdk::hash::Hash;
+use std::fmt;
+use std::str::FromStr;
+
+const MAINNET_GENESIS_HASH: &str = "5eykt4UsFv8P8NJdTREpY1vzqKqZKvdpKuc147dw2N9d";
+const DEVNET_GENESIS_HASH: &str = "EtWTRABZaYq6iMfeYKouRu166VU2xqa1wcaWoxPkrZBG";
+const TESTNET_GENESIS_HASH: &str = "4uhcVJyU9pJkvQyS88uRDiswHXSCkY3zQawwpjk2NsNY";
+
+/// The Solana cluster keys, squads and tasks belong to
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Cluster {
+    /// A local test validator, whose genesis differs on every fresh ledger
+    Localnet,
+    Devnet,
+    Testnet,
+    Mainnet,
+}
+
+impl Cluster {
+    /// Matches the serialized name
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Cluster::Localnet => "localnet",
+            Cluster::Devnet => "devnet",
+            Cluster::Testnet => "testnet",
+            Cluster::Mainnet => "mainnet",
+        }
+    }
+
+    /// The fixed genesis hash of a public cluster
+    pub fn genesis_hash(&self) -> Option<Hash> {
+        let hash = match self {
+            Cluster::Localnet => return None,
+            Cluster::Devnet => DEVNET_GENESIS_HASH,
+            Cluster::Testnet => TESTNET_GENESIS_HASH,
+            Cluster::Mainnet => MAINNET_GENESIS_HASH,
+        };
+        Some(hash.parse().expect("valid genesis hash"))
+    }
+
+    /// The public cluster with this genesis hash
+    pub fn from_genesis_hash(genesis_hash: &Hash) -> Option<Cluster> {
+        [Cluster::Devnet, Cluster::Testnet, Cluster::Mainnet]
+            .into_iter()
+            .find(|cluster| cluster.genesis_hash().as_ref() == Some(genesis_hash))
+    }
+
+    /// Checks an RPC node's genesis hash belongs to this cluster. Localnet
+    /// accepts any hash except those of the public clusters.
+    pub fn verify_genesis_hash(&self, genesis_hash: &Hash) -> Result<(), String> {
+        let found = Cluster::from_genesis_hash(genesis_hash);
+        let matches = match self.genesis_hash() {
+            Some(expected) => expected == *genesis_hash,
+            None => found.is_none(),
+        };
+        if matches {
+            return Ok(());
+        }
+        let found = found.map_or_else(
+            || format!("an unknown cluster (genesis {})", genesis_hash),
+            |cluster| cluster.to_string(),
+        );
+        Err(format!("RPC node is on {}, not {}", found, self))
+    }
+}
+
+impl fmt::Display for Cluster {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Cluster {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "localnet" => Ok(Cluster::Localnet),
+            "devnet" => Ok(Cluster::Devnet),
+            "testnet" => Ok(Cluster::Testnet),
+            "mainnet" => Ok(Cluster::Mainnet),
+            _ => Err(format!("Unknown cluster '{}'", value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_public_clusters_round_trip_genesis_hash() {
+        for cluster in [Cluster::Devnet, Cluster::Testnet, Cluster::Mainnet] {
+            let genesis_hash = cluster.genesis_hash().unwrap();
+
+            assert_eq!(Cluster::from_genesis_hash(&genesis_hash), Some(cluster));
+            assert!(cluster.verify_genesis_hash(&genesis_hash).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_mismatched_genesis_hash_is_rejected() {
+        let mainnet = Cluster::Mainnet.genesis_hash().unwrap();
+
+        let error = Cluster::Devnet.verify_genesis_hash(&mainnet).unwrap_err();
+
+        assert_eq!(error, "RPC node is on mainnet, not devnet");
+        assert!(Cluster::Devnet
+            .verify_genesis_hash(&Hash::new_unique())
+            .unwrap_err()
+            .contains("unknown cluster"));
+    }
+
+    #[test]
+    fn test_localnet_rejects_public_clusters_only() {
+        let devnet = Cluster::Devnet.genesis_hash().unwrap();
+
+        assert!(Cluster::Localnet
+            .verify_genesis_hash(&Hash::new_unique())
+            .is_ok());
+        assert!(Cluster::Localnet.verify_genesis_hash(&devnet).is_err());
+    }
+
+    #[test]
+    fn test_parses_serialized_name() {
+        for cluster in [
+            Cluster::Localnet,
+            Cluster::Devnet,
+            Cluster::Testnet,
+            Cluster::Mainnet,
+        ] {
+            let json = serde_json::to_string(&cluster).unwrap();
+
+            assert_eq!(json, format!("\"{}\"", cluster.as_str()));
+            assert_eq!(cluster.as_str().parse::<Cluster>().unwrap(), cluster);
+        }
+    }
+}
@@ -1,5 +1,7 @@
+pub mod cluster;
 pub mod transaction;
 pub mod wallet;
 
-pub use transaction::TransactionBuilder;
-pub use wallet::WalletManager;
+pub use cluster::Cluster;
+pub use transaction::{FeeSettings, TransactionBuilder};
+pub use wallet::{Keystore, TopUpPlan, TopUpPolicy, WalletManager};
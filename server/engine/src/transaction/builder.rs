@@ -7,10 +7,23 @@ use solana_sdk::{
     transaction::Transaction,
 };
 use solana_system_interface::instruction;
+use tracing::debug;
+
+use super::FeeSettings;
+
+/// The most bytes a serialized transaction may take, so it fits one network packet
+pub const MAX_TRANSACTION_SIZE: usize = 1232;
 
 pub struct TransactionBuilder {
     instructions: Vec<Instruction>,
     recent_blockhash: Option<Hash>,
+    fees: FeeSettings,
+}
+
+impl Default for TransactionBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl TransactionBuilder {
@@ -18,6 +31,15 @@ impl TransactionBuilder {
         Self {
             instructions: Vec::new(),
             recent_blockhash: None,
+            fees: FeeSettings::default(),
+        }
+    }
+
+    /// A builder whose transactions request this compute budget
+    pub fn with_fees(fees: FeeSettings) -> Self {
+        Self {
+            fees,
+            ..Self::new()
         }
     }
 
@@ -28,6 +50,12 @@ impl TransactionBuilder {
         self
     }
 
+    pub fn set_fees(&mut self, fees: FeeSettings) -> &mut Self {
+        self.fees = fees;
+
+        self
+    }
+
     pub fn set_recent_blockhash(&mut self, blockhash: Hash) -> &mut Self {
         self.recent_blockhash = Some(blockhash);
 
@@ -40,6 +68,13 @@ impl TransactionBuilder {
             return Err("No signers provided".to_string());
         }
 
+        let _span = tracing::debug_span!(
+            "build_transaction",
+            payer = %signers[0].pubkey(),
+            instructions = self.instructions.len(),
+        )
+        .entered();
+
         // Check internal state
         if self.recent_blockhash.is_none() {
             return Err("Missing recent_blockhash".to_string());
@@ -48,12 +83,21 @@ impl TransactionBuilder {
             return Err("No instructions".to_string());
         }
 
-        // Devnet for now, later we can make this configurable
-        Ok(Transaction::new(
+        // The message is cluster-agnostic; the RPC client it is sent through picks the cluster
+        let mut instructions = self.fees.instructions();
+        instructions.extend(self.instructions.iter().cloned());
+        let transaction = Transaction::new(
             signers,
-            Message::new(&self.instructions, None),
+            Message::new(&instructions, None),
             self.recent_blockhash.unwrap(),
-        ))
+        );
+        // Only public keys and the signature are logged, never the signers themselves
+        debug!(
+            signers = signers.len(),
+            signature = %transaction.signatures[0],
+            "Signed transaction"
+        );
+        Ok(transaction)
     }
 
     // Common transaction types
@@ -62,12 +106,50 @@ impl TransactionBuilder {
         to: &Pubkey,
         lamports: u64,
         recent_blockhash: Hash,
+    ) -> Result<Transaction, String> {
+        Self::new().build_transfer(from, to, lamports, recent_blockhash)
+    }
+
+    pub fn create_account(
+        payer: &Keypair,
+        new_account: &Keypair,
+        owner: &Pubkey,
+        lamports: u64,
+        space: u64,
+        recent_blockhash: Hash,
+    ) -> Result<Transaction, String> {
+        Self::new().build_create_account(
+            payer,
+            new_account,
+            owner,
+            lamports,
+            space,
+            recent_blockhash,
+        )
+    }
+
+    // Batch operations
+    pub fn batch_transfer(
+        from: &Keypair,
+        transfers: &[(Pubkey, u64)],
+        recent_blockhash: Hash,
+    ) -> Result<Transaction, String> {
+        Self::new().build_batch_transfer(from, transfers, recent_blockhash)
+    }
+
+    /// `transfer`, requesting this builder's compute budget
+    pub fn build_transfer(
+        &self,
+        from: &Keypair,
+        to: &Pubkey,
+        lamports: u64,
+        recent_blockhash: Hash,
     ) -> Result<Transaction, String> {
         if lamports == 0 {
             return Err("Transfer amount must be greater than zero".to_string());
         }
 
-        let mut builder = TransactionBuilder::new();
+        let mut builder = TransactionBuilder::with_fees(self.fees);
         builder
             .add_instruction(instruction::transfer(&from.pubkey(), to, lamports))
             .set_recent_blockhash(recent_blockhash);
@@ -75,10 +157,12 @@ impl TransactionBuilder {
         let signers = vec![from];
 
         // No error mapping, no intervention for now
-        Ok(builder.build(&signers)?)
+        builder.build(&signers)
     }
 
-    pub fn create_account(
+    /// `create_account`, requesting this builder's compute budget
+    pub fn build_create_account(
+        &self,
         payer: &Keypair,
         new_account: &Keypair,
         owner: &Pubkey,
@@ -86,7 +170,7 @@ impl TransactionBuilder {
         space: u64,
         recent_blockhash: Hash,
     ) -> Result<Transaction, String> {
-        let mut builder = TransactionBuilder::new();
+        let mut builder = TransactionBuilder::with_fees(self.fees);
         let instruction = instruction::create_account(
             &payer.pubkey(),
             &new_account.pubkey(),
@@ -105,13 +189,14 @@ impl TransactionBuilder {
         Ok(result)
     }
 
-    // Batch operations
-    pub fn batch_transfer(
+    /// `batch_transfer`, requesting this builder's compute budget
+    pub fn build_batch_transfer(
+        &self,
         from: &Keypair,
-        transfers: &Vec<(Pubkey, u64)>,
+        transfers: &[(Pubkey, u64)],
         recent_blockhash: Hash,
     ) -> Result<Transaction, String> {
-        let mut builder = TransactionBuilder::new();
+        let mut builder = TransactionBuilder::with_fees(self.fees);
 
         for (to, lamports) in transfers {
             if *lamports == 0 {
@@ -124,7 +209,34 @@ impl TransactionBuilder {
 
         let signers = vec![from];
 
-        Ok(builder.build(&signers)?)
+        builder.build(&signers)
+    }
+
+    /// Splits transfers into runs that `batch_transfer` turns into transactions of at
+    /// most `MAX_TRANSACTION_SIZE` bytes, keeping their order
+    pub fn chunk_batch_transfers(
+        from: &Keypair,
+        transfers: &[(Pubkey, u64)],
+    ) -> Result<Vec<Vec<(Pubkey, u64)>>, String> {
+        let mut chunks: Vec<Vec<(Pubkey, u64)>> = Vec::new();
+        let mut current: Vec<(Pubkey, u64)> = Vec::new();
+        for transfer in transfers {
+            current.push(*transfer);
+            // The blockhash does not change the size, so any will do
+            let transaction = Self::batch_transfer(from, &current, Hash::default())?;
+            if transaction_size(&transaction)? <= MAX_TRANSACTION_SIZE {
+                continue;
+            }
+            current.pop();
+            if current.is_empty() {
+                return Err("A single transfer exceeds the transaction size limit".to_string());
+            }
+            chunks.push(std::mem::replace(&mut current, vec![*transfer]));
+        }
+        if !current.is_empty() {
+            chunks.push(current);
+        }
+        Ok(chunks)
     }
 
     // Transaction size management
@@ -138,6 +250,13 @@ impl TransactionBuilder {
     }
 }
 
+/// The transaction's size on the wire, in bytes
+pub fn transaction_size(transaction: &Transaction) -> Result<usize, String> {
+    bincode::serialized_size(transaction)
+        .map(|size| size as usize)
+        .map_err(|e| format!("Failed to size transaction: {}", e))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -217,7 +336,7 @@ mod tests {
         assert!(result.is_ok());
 
         let transaction = result.unwrap();
-        assert!(transaction.message.instructions.len() > 0);
+        assert!(!transaction.message.instructions.is_empty());
     }
 
     #[test]
@@ -247,6 +366,55 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_chunked_batch_transfers_fit_the_size_limit() {
+        let from = Keypair::new();
+        let transfers: Vec<(Pubkey, u64)> = (1..=60)
+            .map(|lamports| (Pubkey::new_unique(), lamports))
+            .collect();
+
+        let chunks = TransactionBuilder::chunk_batch_transfers(&from, &transfers).unwrap();
+
+        assert!(chunks.len() > 1);
+        assert_eq!(chunks.concat(), transfers);
+        for (i, chunk) in chunks.iter().enumerate() {
+            let size = |transfers: &Vec<(Pubkey, u64)>| {
+                let transaction =
+                    TransactionBuilder::batch_transfer(&from, transfers, Hash::default()).unwrap();
+                transaction_size(&transaction).unwrap()
+            };
+            assert!(size(chunk) <= MAX_TRANSACTION_SIZE);
+            // Each chunk but the last is as full as it can be
+            if let Some(next) = chunks.get(i + 1) {
+                let mut overfull = chunk.clone();
+                overfull.push(next[0]);
+                assert!(size(&overfull) > MAX_TRANSACTION_SIZE);
+            }
+        }
+    }
+
+    #[test]
+    fn test_common_transactions_request_the_builder_compute_budget() {
+        let from = Keypair::new();
+        let fees = FeeSettings::new()
+            .with_compute_unit_price(1_000)
+            .with_compute_unit_limit(10_000);
+        let builder = TransactionBuilder::with_fees(fees);
+
+        let transfer = builder
+            .build_transfer(&from, &Pubkey::new_unique(), 1_000, Hash::default())
+            .unwrap();
+        let batch = builder
+            .build_batch_transfer(&from, &[(Pubkey::new_unique(), 1_000)], Hash::default())
+            .unwrap();
+
+        assert_eq!(transfer.message.instructions.len(), 3);
+        assert_eq!(batch.message.instructions.len(), 3);
+        assert!(builder
+            .build_transfer(&from, &Pubkey::new_unique(), 0, Hash::default())
+            .is_err());
+    }
+
     #[test]
     fn test_build_without_recent_blockhash_fails() {
         let mut builder = TransactionBuilder::new();
@@ -274,6 +442,34 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_fees_prepend_compute_budget_instructions() {
+        let from = Keypair::new();
+        let fees = FeeSettings::new()
+            .with_compute_unit_limit(10_000)
+            .with_compute_unit_price(1_000);
+        let mut builder = TransactionBuilder::with_fees(fees);
+        builder
+            .add_instruction(instruction::transfer(
+                &from.pubkey(),
+                &Pubkey::new_unique(),
+                1000000,
+            ))
+            .set_recent_blockhash(Hash::default());
+
+        let transaction = builder.build(&vec![&from]).unwrap();
+
+        let instructions = &transaction.message.instructions;
+        assert_eq!(instructions.len(), 3);
+        let program = |index: usize| {
+            transaction.message.account_keys[instructions[index].program_id_index as usize]
+        };
+        assert_eq!(program(0), solana_compute_budget_interface::id());
+        assert_eq!(program(1), solana_compute_budget_interface::id());
+        assert_eq!(program(2), solana_system_interface::program::ID);
+        assert_eq!(builder.instruction_count(), 1);
+    }
+
     #[test]
     fn test_clear_builder() {
         let mut builder = TransactionBuilder::new();
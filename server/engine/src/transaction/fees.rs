@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+use solana_compute_budget_interface::ComputeBudgetInstruction;
+use solana_sdk::instruction::Instruction;
+
+/// Most compute units a single transaction may request
+pub const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+/// Compute units each instruction is given when no limit is requested
+pub const DEFAULT_INSTRUCTION_COMPUTE_UNITS: u32 = 200_000;
+/// Base fee charged per signature, whatever the compute budget
+pub const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+
+/// Compute budget requested by built transactions. Unset fields leave the
+/// runtime defaults in place and add no instruction.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeeSettings {
+    /// Priority fee, in micro-lamports per compute unit
+    pub compute_unit_price: Option<u64>,
+    pub compute_unit_limit: Option<u32>,
+}
+
+impl FeeSettings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_compute_unit_price(mut self, micro_lamports: u64) -> Self {
+        self.compute_unit_price = Some(micro_lamports);
+        self
+    }
+
+    pub fn with_compute_unit_limit(mut self, units: u32) -> Self {
+        self.compute_unit_limit = Some(units);
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        match self.compute_unit_limit {
+            Some(0) => Err("Compute unit limit must be greater than zero".to_string()),
+            Some(units) if units > MAX_COMPUTE_UNIT_LIMIT => Err(format!(
+                "Compute unit limit {} exceeds the maximum of {}",
+                units, MAX_COMPUTE_UNIT_LIMIT
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// The fee a transaction with this many signatures and instructions of its own
+    /// pays: the base fee per signature plus the priority fee on its compute limit
+    pub fn estimate_fee(&self, signatures: usize, instructions: usize) -> u64 {
+        let units = self.compute_unit_limit.map_or_else(
+            || {
+                (instructions as u64 * u64::from(DEFAULT_INSTRUCTION_COMPUTE_UNITS))
+                    .min(u64::from(MAX_COMPUTE_UNIT_LIMIT))
+            },
+            u64::from,
+        );
+        // The price is in micro-lamports, rounded up to whole lamports
+        let priority = self.compute_unit_price.map_or(0, |price| {
+            (u128::from(price) * u128::from(units)).div_ceil(1_000_000) as u64
+        });
+        signatures as u64 * LAMPORTS_PER_SIGNATURE + priority
+    }
+
+    /// Compute budget instructions to put ahead of a transaction's own
+    pub fn instructions(&self) -> Vec<Instruction> {
+        let mut instructions = Vec::new();
+        if let Some(units) = self.compute_unit_limit {
+            instructions.push(ComputeBudgetInstruction::set_compute_unit_limit(units));
+        }
+        if let Some(micro_lamports) = self.compute_unit_price {
+            instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+                micro_lamports,
+            ));
+        }
+        instructions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_adds_no_instructions() {
+        assert!(FeeSettings::new().instructions().is_empty());
+    }
+
+    #[test]
+    fn test_instructions_target_compute_budget_program() {
+        let fees = FeeSettings::new()
+            .with_compute_unit_limit(200_000)
+            .with_compute_unit_price(5_000);
+
+        let instructions = fees.instructions();
+
+        assert_eq!(instructions.len(), 2);
+        assert!(instructions
+            .iter()
+            .all(|ix| ix.program_id == solana_compute_budget_interface::id()));
+    }
+
+    #[test]
+    fn test_estimate_fee() {
+        assert_eq!(FeeSettings::new().estimate_fee(2, 3), 10_000);
+        // 3 instructions at the default 200k units each, at 5 micro-lamports per unit
+        assert_eq!(
+            FeeSettings::new()
+                .with_compute_unit_price(5)
+                .estimate_fee(1, 3),
+            5_000 + 3
+        );
+        assert_eq!(
+            FeeSettings::new()
+                .with_compute_unit_price(5_000)
+                .with_compute_unit_limit(200_000)
+                .estimate_fee(1, 10),
+            5_000 + 1_000
+        );
+    }
+
+    #[test]
+    fn test_validate_rejects_out_of_range_limit() {
+        assert!(FeeSettings::new()
+            .with_compute_unit_limit(0)
+            .validate()
+            .is_err());
+        assert!(FeeSettings::new()
+            .with_compute_unit_limit(MAX_COMPUTE_UNIT_LIMIT + 1)
+            .validate()
+            .is_err());
+        assert!(FeeSettings::new()
+            .with_compute_unit_limit(MAX_COMPUTE_UNIT_LIMIT)
+            .validate()
+            .is_ok());
+    }
+}
@@ -1,5 +1,12 @@
 pub mod builder;
+pub mod fees;
 pub mod params;
 
-pub use builder::TransactionBuilder;
-pub use params::{BatchTransferParams, CreateAccountParams, TransactionParams, TransferParams};
+pub use builder::{transaction_size, TransactionBuilder, MAX_TRANSACTION_SIZE};
+pub use fees::{
+    FeeSettings, DEFAULT_INSTRUCTION_COMPUTE_UNITS, LAMPORTS_PER_SIGNATURE, MAX_COMPUTE_UNIT_LIMIT,
+};
+pub use params::{
+    migrate_params, BatchTransferParams, CreateAccountParams, TransactionParams, TransferParams,
+    PARAMS_SCHEMA_VERSION,
+};
@@ -1,5 +1,25 @@
 use serde::{Deserialize, Serialize};
 use solana_sdk::pubkey::Pubkey;
+use solana_system_interface::program as system_program;
+
+/// Version of the serialized `TransactionParams` format. Bump it, and add a step to
+/// `migrate_params`, whenever a change would stop older JSON from deserializing.
+pub const PARAMS_SCHEMA_VERSION: u32 = 1;
+
+/// Upgrades params JSON written at `version` to the current format
+pub fn migrate_params(json: &str, version: u32) -> Result<String, String> {
+    if version > PARAMS_SCHEMA_VERSION {
+        return Err(format!(
+            "Params schema version {} is newer than supported version {}",
+            version, PARAMS_SCHEMA_VERSION
+        ));
+    }
+    match version {
+        // Version 1 is the original format and still current
+        1 => Ok(json.to_string()),
+        _ => Err(format!("Unknown params schema version {}", version)),
+    }
+}
 
 /// Represents different types of transaction parameters that can be serialized
 /// and stored in tasks for delayed transaction building
@@ -11,6 +31,35 @@ pub enum TransactionParams {
     BatchTransfer(BatchTransferParams),
 }
 
+impl TransactionParams {
+    /// The wallet that signs and pays fees for the resulting transaction
+    pub fn fee_payer(&self) -> Pubkey {
+        match self {
+            TransactionParams::Transfer(params) => params.from,
+            TransactionParams::CreateAccount(params) => params.payer,
+            TransactionParams::BatchTransfer(params) => params.from,
+        }
+    }
+
+    /// The programs the resulting transaction invokes
+    pub fn program_ids(&self) -> Vec<Pubkey> {
+        match self {
+            TransactionParams::Transfer(_)
+            | TransactionParams::CreateAccount(_)
+            | TransactionParams::BatchTransfer(_) => vec![system_program::ID],
+        }
+    }
+
+    /// The variant name, matching the serialized `type` tag
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            TransactionParams::Transfer(_) => "Transfer",
+            TransactionParams::CreateAccount(_) => "CreateAccount",
+            TransactionParams::BatchTransfer(_) => "BatchTransfer",
+        }
+    }
+}
+
 /// Parameters for a simple SOL transfer transaction
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct TransferParams {
@@ -99,4 +148,78 @@ mod tests {
         // Should contain "type": "Transfer" due to #[serde(tag = "type")]
         assert!(json.contains(r#""type":"Transfer"#));
     }
+
+    #[test]
+    fn test_fee_payer() {
+        let from = Pubkey::new_unique();
+        let payer = Pubkey::new_unique();
+
+        let transfer = TransactionParams::Transfer(TransferParams {
+            from,
+            to: Pubkey::new_unique(),
+            lamports: 1,
+        });
+        let create = TransactionParams::CreateAccount(CreateAccountParams {
+            payer,
+            new_account: Pubkey::new_unique(),
+            owner: Pubkey::new_unique(),
+            lamports: 1,
+            space: 0,
+        });
+        let batch = TransactionParams::BatchTransfer(BatchTransferParams {
+            from,
+            transfers: vec![(Pubkey::new_unique(), 1)],
+        });
+
+        assert_eq!(transfer.fee_payer(), from);
+        assert_eq!(create.fee_payer(), payer);
+        assert_eq!(batch.fee_payer(), from);
+    }
+
+    #[test]
+    fn test_system_params_invoke_system_program() {
+        let params = TransactionParams::Transfer(TransferParams {
+            from: Pubkey::new_unique(),
+            to: Pubkey::new_unique(),
+            lamports: 1,
+        });
+
+        assert_eq!(
+            params.program_ids(),
+            vec![Pubkey::from_str_const("11111111111111111111111111111111")]
+        );
+    }
+
+    #[test]
+    fn test_type_name_matches_serde_tag() {
+        let params = TransactionParams::BatchTransfer(BatchTransferParams {
+            from: Pubkey::new_unique(),
+            transfers: vec![(Pubkey::new_unique(), 1)],
+        });
+
+        let json = serde_json::to_string(&params).unwrap();
+        assert!(json.contains(&format!(r#""type":"{}""#, params.type_name())));
+    }
+
+    #[test]
+    fn test_v1_fixture_migrates_to_current() {
+        let fixture: Vec<serde_json::Value> =
+            serde_json::from_str(include_str!("../../fixtures/params/v1.json")).unwrap();
+
+        let migrated: Vec<TransactionParams> = fixture
+            .iter()
+            .map(|params| migrate_params(&params.to_string(), 1).unwrap())
+            .map(|json| serde_json::from_str(&json).unwrap())
+            .collect();
+
+        let types: Vec<&str> = migrated.iter().map(|params| params.type_name()).collect();
+        assert_eq!(types, vec!["Transfer", "CreateAccount", "BatchTransfer"]);
+        assert_eq!(migrated[0].fee_payer(), Pubkey::new_from_array([1; 32]));
+    }
+
+    #[test]
+    fn test_newer_params_version_is_rejected() {
+        assert!(migrate_params("{}", PARAMS_SCHEMA_VERSION + 1).is_err());
+        assert!(migrate_params("{}", 0).is_err());
+    }
 }
@@ -0,0 +1,319 @@
+use super::manager::WalletManager;
+use super::squad::Squad;
+use crate::cluster::Cluster;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use tracing::{debug, info};
+
+/// Version of the keystore file. Bump it when a change would break older files.
+/// Version 2 tagged the keystore and its squads with their cluster.
+/// Version 3 added the treasury wallet and squad top-up policies.
+pub const KEYSTORE_VERSION: u32 = 3;
+
+#[derive(Serialize, Deserialize)]
+struct KeystoreFile {
+    version: u32,
+    /// Absent from version 1 files, which are tagged when next saved
+    #[serde(default)]
+    cluster: Option<Cluster>,
+    /// Wallet name -> 64-byte keypair
+    wallets: BTreeMap<String, Vec<u8>>,
+    squads: Vec<Squad>,
+    /// Name of the wallet funding squad top-ups
+    #[serde(default)]
+    treasury: Option<String>,
+    /// Next number `batch_generate_auto_named` will use
+    next_auto_name: usize,
+}
+
+/// Keeps a `WalletManager`'s keypairs and squads in a JSON file. Keys are stored
+/// unencrypted, so on Unix the file is only readable by its owner.
+pub struct Keystore {
+    path: PathBuf,
+    cluster: Option<Cluster>,
+}
+
+impl Keystore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            cluster: None,
+        }
+    }
+
+    /// Refuses to load a keystore or squad tagged with any other cluster
+    pub fn with_cluster(mut self, cluster: Cluster) -> Self {
+        self.cluster = Some(cluster);
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Loads the stored wallets and squads. A missing file loads as empty.
+    pub fn load(&self) -> Result<WalletManager, String> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(self.empty_manager()),
+            Err(e) => return Err(format!("Failed to read keystore: {}", e)),
+        };
+        let file: KeystoreFile =
+            serde_json::from_str(&contents).map_err(|e| format!("Invalid keystore: {}", e))?;
+        if file.version > KEYSTORE_VERSION {
+            return Err(format!(
+                "Keystore version {} is newer than supported version {}",
+                file.version, KEYSTORE_VERSION
+            ));
+        }
+
+        let cluster = self.cluster.or(file.cluster);
+        check_cluster("Keystore", file.cluster, cluster)?;
+        let mut manager = match cluster {
+            Some(cluster) => WalletManager::new().with_cluster(cluster),
+            None => WalletManager::new(),
+        };
+        for (name, keypair) in file.wallets {
+            manager.import_wallet(name, &keypair)?;
+        }
+        for squad in file.squads {
+            check_cluster(&format!("Squad '{}'", squad.name), squad.cluster, cluster)?;
+            manager.create_squad(squad.name.clone(), squad.campaign)?;
+            manager.set_squad_wallets(&squad.name, squad.wallets)?;
+            manager.set_squad_top_up(&squad.name, squad.top_up)?;
+        }
+        if let Some(treasury) = &file.treasury {
+            manager.set_treasury(treasury)?;
+        }
+        manager.set_next_auto_name(file.next_auto_name);
+        info!(
+            path = %self.path.display(),
+            wallets = manager.wallet_count(),
+            "Loaded keystore"
+        );
+        Ok(manager)
+    }
+
+    /// Replaces the stored wallets and squads with the manager's
+    pub fn save(&self, manager: &WalletManager) -> Result<(), String> {
+        let mut wallets = BTreeMap::new();
+        for (name, _) in manager.list_wallets() {
+            let keypair = manager.get_wallet(&name)?;
+            wallets.insert(name, keypair.to_bytes().to_vec());
+        }
+        let file = KeystoreFile {
+            version: KEYSTORE_VERSION,
+            cluster: manager.cluster().or(self.cluster),
+            wallets,
+            squads: manager.list_squads().into_iter().cloned().collect(),
+            treasury: manager.treasury().map(str::to_string),
+            next_auto_name: manager.next_auto_name(),
+        };
+        let contents = serde_json::to_string_pretty(&file)
+            .map_err(|e| format!("Failed to serialize keystore: {}", e))?;
+
+        // Write beside the target and rename, so a crash never leaves a torn file
+        let staging = self.path.with_extension("tmp");
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        options
+            .open(&staging)
+            .and_then(|mut staged| staged.write_all(contents.as_bytes()))
+            .map_err(|e| format!("Failed to write keystore: {}", e))?;
+        fs::rename(&staging, &self.path).map_err(|e| format!("Failed to write keystore: {}", e))?;
+        debug!(
+            path = %self.path.display(),
+            wallets = manager.wallet_count(),
+            "Saved keystore"
+        );
+        Ok(())
+    }
+
+    fn empty_manager(&self) -> WalletManager {
+        match self.cluster {
+            Some(cluster) => WalletManager::new().with_cluster(cluster),
+            None => WalletManager::new(),
+        }
+    }
+}
+
+/// Untagged data fits any cluster
+fn check_cluster(
+    what: &str,
+    tagged: Option<Cluster>,
+    expected: Option<Cluster>,
+) -> Result<(), String> {
+    match (tagged, expected) {
+        (Some(tagged), Some(expected)) if tagged != expected => {
+            Err(format!("{} belongs to {}, not {}", what, tagged, expected))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wallet::TopUpPolicy;
+
+    fn temp_keystore(name: &str) -> Keystore {
+        let path =
+            std::env::temp_dir().join(format!("keystore-{}-{}.json", std::process::id(), name));
+        Keystore::new(path)
+    }
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let keystore = temp_keystore("round-trip");
+        let mut manager = WalletManager::new();
+        let alpha = manager.generate_wallet("alpha".to_string()).unwrap();
+        manager.generate_wallet("bravo".to_string()).unwrap();
+        manager
+            .create_squad("red".to_string(), Some("kamino".to_string()))
+            .unwrap();
+        manager
+            .assign_to_squad("red", &["bravo".to_string(), "alpha".to_string()])
+            .unwrap();
+
+        keystore.save(&manager).unwrap();
+        let loaded = keystore.load().unwrap();
+
+        assert_eq!(loaded.wallet_count(), 2);
+        assert_eq!(loaded.get_pubkey("alpha").unwrap(), alpha);
+        let squad = loaded.get_squad("red").unwrap();
+        assert_eq!(squad.campaign.as_deref(), Some("kamino"));
+        assert_eq!(squad.wallets, ["bravo", "alpha"]);
+        fs::remove_file(keystore.path()).unwrap();
+    }
+
+    #[test]
+    fn test_treasury_and_top_up_policies_round_trip() {
+        let keystore = temp_keystore("treasury");
+        let mut manager = WalletManager::new();
+        manager.generate_wallet("bank".to_string()).unwrap();
+        manager.set_treasury("bank").unwrap();
+        manager.create_squad("red".to_string(), None).unwrap();
+        let policy = TopUpPolicy::new(50_000_000, 10_000_000).unwrap();
+        manager.set_squad_top_up("red", Some(policy)).unwrap();
+
+        keystore.save(&manager).unwrap();
+        let loaded = keystore.load().unwrap();
+
+        assert_eq!(loaded.treasury(), Some("bank"));
+        assert_eq!(loaded.get_squad("red").unwrap().top_up, Some(policy));
+        fs::remove_file(keystore.path()).unwrap();
+    }
+
+    #[test]
+    fn test_missing_file_loads_empty() {
+        let loaded = temp_keystore("missing").load().unwrap();
+
+        assert_eq!(loaded.wallet_count(), 0);
+    }
+
+    #[test]
+    fn test_auto_names_continue_after_reload() {
+        let keystore = temp_keystore("auto-names");
+        let mut manager = WalletManager::new();
+        manager.batch_generate_auto_named(2).unwrap();
+        keystore.save(&manager).unwrap();
+
+        let mut loaded = keystore.load().unwrap();
+
+        assert_eq!(loaded.batch_generate_auto_named(1).unwrap(), ["wallet_2"]);
+        fs::remove_file(keystore.path()).unwrap();
+    }
+
+    #[test]
+    fn test_newer_version_is_rejected() {
+        let keystore = temp_keystore("newer");
+        let file = format!(
+            r#"{{"version": {}, "wallets": {{}}, "squads": [], "next_auto_name": 0}}"#,
+            KEYSTORE_VERSION + 1
+        );
+        fs::write(keystore.path(), file).unwrap();
+
+        let error = keystore.load().err().unwrap();
+
+        assert!(error.contains("newer than supported"));
+        fs::remove_file(keystore.path()).unwrap();
+    }
+
+    #[test]
+    fn test_keystore_of_another_cluster_is_refused() {
+        let path = temp_keystore("cluster").path().to_path_buf();
+        let mut manager = WalletManager::new().with_cluster(Cluster::Mainnet);
+        manager.generate_wallet("whale".to_string()).unwrap();
+        manager.create_squad("red".to_string(), None).unwrap();
+        Keystore::new(&path).save(&manager).unwrap();
+
+        let error = Keystore::new(&path)
+            .with_cluster(Cluster::Devnet)
+            .load()
+            .err()
+            .unwrap();
+        let loaded = Keystore::new(&path).load().unwrap();
+
+        assert_eq!(error, "Keystore belongs to mainnet, not devnet");
+        assert_eq!(loaded.cluster(), Some(Cluster::Mainnet));
+        assert_eq!(
+            loaded.get_squad("red").unwrap().cluster,
+            Some(Cluster::Mainnet)
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_version_1_file_is_tagged_on_save() {
+        let keystore = temp_keystore("v1").with_cluster(Cluster::Devnet);
+        let file = r#"{"version": 1, "wallets": {}, "squads": [
+            {"name": "red", "campaign": null, "wallets": []}
+        ], "next_auto_name": 0}"#;
+        fs::write(keystore.path(), file).unwrap();
+
+        let manager = keystore.load().unwrap();
+        keystore.save(&manager).unwrap();
+
+        let saved: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(keystore.path()).unwrap()).unwrap();
+        assert_eq!(saved["version"], KEYSTORE_VERSION);
+        assert_eq!(saved["cluster"], "devnet");
+        assert_eq!(saved["squads"][0]["cluster"], "devnet");
+        fs::remove_file(keystore.path()).unwrap();
+    }
+
+    #[test]
+    fn test_squad_of_another_cluster_is_refused() {
+        let keystore = temp_keystore("squad-cluster").with_cluster(Cluster::Devnet);
+        let file = r#"{"version": 2, "cluster": null, "wallets": {}, "squads": [
+            {"name": "red", "campaign": null, "wallets": [], "cluster": "mainnet"}
+        ], "next_auto_name": 0}"#;
+        fs::write(keystore.path(), file).unwrap();
+
+        let error = keystore.load().err().unwrap();
+
+        assert_eq!(error, "Squad 'red' belongs to mainnet, not devnet");
+        fs::remove_file(keystore.path()).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_file_is_private_to_owner() {
+        use std::os::unix::fs::PermissionsExt;
+        let keystore = temp_keystore("private");
+
+        keystore.save(&WalletManager::new()).unwrap();
+
+        let mode = fs::metadata(keystore.path()).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        fs::remove_file(keystore.path()).unwrap();
+    }
+}
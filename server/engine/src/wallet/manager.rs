@@ -1,20 +1,48 @@
+use super::squad::Squad;
+use super::treasury::{TopUpBatch, TopUpPlan, TopUpPolicy};
+use crate::cluster::Cluster;
+use crate::transaction::{BatchTransferParams, FeeSettings, TransactionBuilder};
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::signature::{Keypair, Signer};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use tracing::{debug, info};
 
 pub struct WalletManager {
     wallets: HashMap<String, Keypair>,
+    squads: HashMap<String, Squad>,
     counter: usize,
+    cluster: Option<Cluster>,
+    // Name of the wallet that funds squad top-ups
+    treasury: Option<String>,
+}
+
+impl Default for WalletManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl WalletManager {
     pub fn new() -> Self {
         Self {
             wallets: HashMap::new(),
+            squads: HashMap::new(),
             counter: 0,
+            cluster: None,
+            treasury: None,
         }
     }
 
+    /// Tags the keys and every squad created from now on with this cluster
+    pub fn with_cluster(mut self, cluster: Cluster) -> Self {
+        self.cluster = Some(cluster);
+        self
+    }
+
+    pub fn cluster(&self) -> Option<Cluster> {
+        self.cluster
+    }
+
     // Basic wallet
     pub fn generate_wallet(&mut self, name: String) -> Result<Pubkey, String> {
         if self.wallets.contains_key(&name) {
@@ -23,17 +51,29 @@ impl WalletManager {
 
         let keypair = Keypair::new();
         let pubkey = keypair.pubkey();
+        debug!(wallet = %name, %pubkey, "Generated wallet");
         self.wallets.insert(name, keypair);
 
         Ok(pubkey)
     }
 
+    /// Imports a wallet from its 64-byte keypair, as found in a Solana CLI keypair file
     pub fn import_wallet(
         &mut self,
-        _name: String,
-        _private_key_bytes: &[u8],
+        name: String,
+        private_key_bytes: &[u8],
     ) -> Result<Pubkey, String> {
-        unimplemented!("wallet import from private key")
+        if self.wallets.contains_key(&name) {
+            return Err(format!("Wallet '{}' already exists", name));
+        }
+
+        let keypair = Keypair::try_from(private_key_bytes)
+            .map_err(|e| format!("Invalid keypair for wallet '{}': {}", name, e))?;
+        let pubkey = keypair.pubkey();
+        debug!(wallet = %name, %pubkey, "Imported wallet");
+        self.wallets.insert(name, keypair);
+
+        Ok(pubkey)
     }
 
     pub fn get_wallet(&self, name: &str) -> Result<&Keypair, String> {
@@ -50,6 +90,14 @@ impl WalletManager {
         }
     }
 
+    /// The name of the wallet holding this key, if any
+    pub fn wallet_name(&self, pubkey: &Pubkey) -> Option<&str> {
+        self.wallets
+            .iter()
+            .find(|(_, keypair)| keypair.pubkey() == *pubkey)
+            .map(|(name, _)| name.as_str())
+    }
+
     pub fn list_wallets(&self) -> Vec<(String, Pubkey)> {
         self.wallets
             .iter()
@@ -57,9 +105,19 @@ impl WalletManager {
             .collect()
     }
 
+    /// Removes a wallet, along with its membership in any squad
     pub fn remove_wallet(&mut self, name: &str) -> Result<(), String> {
         match self.wallets.remove(name) {
-            Some(_) => Ok(()),
+            Some(keypair) => {
+                info!(wallet = name, pubkey = %keypair.pubkey(), "Removed wallet");
+                if self.treasury.as_deref() == Some(name) {
+                    self.treasury = None;
+                }
+                for squad in self.squads.values_mut() {
+                    squad.wallets.retain(|member| member != name);
+                }
+                Ok(())
+            }
             None => Err(format!("Wallet '{}' not found", name)),
         }
     }
@@ -98,6 +156,14 @@ impl WalletManager {
         Ok(generated_names)
     }
 
+    pub(super) fn next_auto_name(&self) -> usize {
+        self.counter
+    }
+
+    pub(super) fn set_next_auto_name(&mut self, next: usize) {
+        self.counter = next;
+    }
+
     pub fn batch_get_pubkeys(
         &self,
         wallet_names: &Vec<String>,
@@ -113,6 +179,215 @@ impl WalletManager {
 
         Ok(result)
     }
+
+    // Squads
+    pub fn create_squad(
+        &mut self,
+        name: String,
+        campaign: Option<String>,
+    ) -> Result<&Squad, String> {
+        if self.squads.contains_key(&name) {
+            return Err(format!("Squad '{}' already exists", name));
+        }
+        let mut squad = Squad::new(name.clone(), campaign);
+        squad.cluster = self.cluster;
+        Ok(self.squads.entry(name).or_insert(squad))
+    }
+
+    pub fn get_squad(&self, name: &str) -> Result<&Squad, String> {
+        self.squads
+            .get(name)
+            .ok_or_else(|| format!("Squad '{}' not found", name))
+    }
+
+    /// All squads, sorted by name
+    pub fn list_squads(&self) -> Vec<&Squad> {
+        let mut squads: Vec<&Squad> = self.squads.values().collect();
+        squads.sort_by(|a, b| a.name.cmp(&b.name));
+        squads
+    }
+
+    pub fn set_squad_campaign(
+        &mut self,
+        name: &str,
+        campaign: Option<String>,
+    ) -> Result<(), String> {
+        self.squad_mut(name)?.campaign = campaign;
+        Ok(())
+    }
+
+    /// Adds wallets to a squad, skipping any already in it. Fails without
+    /// changing anything if a wallet does not exist.
+    pub fn assign_to_squad(&mut self, name: &str, wallet_names: &[String]) -> Result<(), String> {
+        self.check_wallets_exist(wallet_names)?;
+        let squad = self.squad_mut(name)?;
+        for wallet in wallet_names {
+            if !squad.contains(wallet) {
+                squad.wallets.push(wallet.clone());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn unassign_from_squad(
+        &mut self,
+        name: &str,
+        wallet_names: &[String],
+    ) -> Result<(), String> {
+        self.squad_mut(name)?
+            .wallets
+            .retain(|member| !wallet_names.contains(member));
+        Ok(())
+    }
+
+    /// Replaces a squad's members with `wallet_names`
+    pub fn set_squad_wallets(
+        &mut self,
+        name: &str,
+        wallet_names: Vec<String>,
+    ) -> Result<(), String> {
+        self.check_wallets_exist(&wallet_names)?;
+        let squad = self.squad_mut(name)?;
+        squad.wallets.clear();
+        for wallet in wallet_names {
+            if !squad.contains(&wallet) {
+                squad.wallets.push(wallet);
+            }
+        }
+        Ok(())
+    }
+
+    /// Deletes a squad. Its wallets are kept.
+    pub fn remove_squad(&mut self, name: &str) -> Result<Squad, String> {
+        self.squads
+            .remove(name)
+            .ok_or_else(|| format!("Squad '{}' not found", name))
+    }
+
+    pub fn squad_pubkeys(&self, name: &str) -> Result<Vec<(String, Pubkey)>, String> {
+        let squad = self.get_squad(name)?;
+        self.batch_get_pubkeys(&squad.wallets)
+    }
+
+    /// Sets or clears the policy keeping a squad's wallets funded
+    pub fn set_squad_top_up(
+        &mut self,
+        name: &str,
+        policy: Option<TopUpPolicy>,
+    ) -> Result<(), String> {
+        if let Some(policy) = &policy {
+            policy.validate()?;
+        }
+        self.squad_mut(name)?.top_up = policy;
+        Ok(())
+    }
+
+    // Treasury
+    /// Designates the wallet that funds squad top-ups
+    pub fn set_treasury(&mut self, name: &str) -> Result<(), String> {
+        self.get_wallet(name)?;
+        self.treasury = Some(name.to_string());
+        Ok(())
+    }
+
+    pub fn clear_treasury(&mut self) {
+        self.treasury = None;
+    }
+
+    pub fn treasury(&self) -> Option<&str> {
+        self.treasury.as_deref()
+    }
+
+    /// Plans transfers from the treasury bringing every wallet of a squad with a
+    /// top-up policy back to target, given `balances` of the treasury and those
+    /// wallets. A wallet in several squads is topped up once, by the first squad
+    /// in name order. Each batch fits one transaction built by `batch_transfer`.
+    /// The treasury must cover the transfers and their `fees` while keeping
+    /// `rent_exempt_minimum` for itself.
+    pub fn plan_top_ups(
+        &self,
+        balances: &HashMap<Pubkey, u64>,
+        rent_exempt_minimum: u64,
+        fees: &FeeSettings,
+    ) -> Result<TopUpPlan, String> {
+        let treasury_name = self.treasury().ok_or("No treasury wallet set")?;
+        let treasury = self.get_wallet(treasury_name)?;
+        let balance_of = |name: &str, pubkey: &Pubkey| {
+            balances
+                .get(pubkey)
+                .copied()
+                .ok_or_else(|| format!("No balance known for wallet '{}'", name))
+        };
+        let available = balance_of(treasury_name, &treasury.pubkey())?;
+
+        let mut planned: HashSet<&str> = HashSet::from([treasury_name]);
+        let mut batches = Vec::new();
+        let mut total_lamports: u64 = 0;
+        for squad in self.list_squads() {
+            let Some(policy) = squad.top_up else {
+                continue;
+            };
+            policy
+                .check_rent_exempt(rent_exempt_minimum)
+                .map_err(|e| format!("Squad '{}': {}", squad.name, e))?;
+            let mut transfers = Vec::new();
+            for wallet in &squad.wallets {
+                if !planned.insert(wallet) {
+                    continue;
+                }
+                let pubkey = self.get_pubkey(wallet)?;
+                if let Some(lamports) = policy.shortfall(balance_of(wallet, &pubkey)?) {
+                    transfers.push((pubkey, lamports));
+                    total_lamports = total_lamports.saturating_add(lamports);
+                }
+            }
+            for transfers in TransactionBuilder::chunk_batch_transfers(treasury, &transfers)? {
+                batches.push(TopUpBatch {
+                    squad: squad.name.clone(),
+                    params: BatchTransferParams {
+                        from: treasury.pubkey(),
+                        transfers,
+                    },
+                });
+            }
+        }
+
+        let estimated_fees: u64 = batches
+            .iter()
+            .map(|batch| fees.estimate_fee(1, batch.params.transfers.len()))
+            .sum();
+        if total_lamports.saturating_add(estimated_fees)
+            > available.saturating_sub(rent_exempt_minimum)
+        {
+            return Err(format!(
+                "Treasury '{}' holds {} lamports, but top-ups need {} plus {} in fees, \
+                 and it must keep {} to stay rent-exempt",
+                treasury_name, available, total_lamports, estimated_fees, rent_exempt_minimum
+            ));
+        }
+        Ok(TopUpPlan {
+            treasury: treasury.pubkey(),
+            batches,
+            total_lamports,
+            estimated_fees,
+        })
+    }
+
+    fn squad_mut(&mut self, name: &str) -> Result<&mut Squad, String> {
+        self.squads
+            .get_mut(name)
+            .ok_or_else(|| format!("Squad '{}' not found", name))
+    }
+
+    fn check_wallets_exist(&self, wallet_names: &[String]) -> Result<(), String> {
+        match wallet_names
+            .iter()
+            .find(|name| !self.wallets.contains_key(name.as_str()))
+        {
+            Some(missing) => Err(format!("Wallet '{}' not found", missing)),
+            None => Ok(()),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -160,6 +435,15 @@ mod tests {
         assert_eq!(pubkey, retrieved_pubkey);
     }
 
+    #[test]
+    fn test_wallet_name_by_pubkey() {
+        let mut manager = WalletManager::new();
+        let pubkey = manager.generate_wallet("alpha".to_string()).unwrap();
+
+        assert_eq!(manager.wallet_name(&pubkey), Some("alpha"));
+        assert_eq!(manager.wallet_name(&Pubkey::new_unique()), None);
+    }
+
     #[test]
     fn test_get_nonexistent_wallet_fails() {
         let manager = WalletManager::new();
@@ -264,4 +548,265 @@ mod tests {
             assert_eq!(pubkey.to_bytes().len(), 32);
         }
     }
+
+    #[test]
+    fn test_import_wallet_round_trip() {
+        let mut manager = WalletManager::new();
+        let keypair = Keypair::new();
+
+        let pubkey = manager
+            .import_wallet("imported".to_string(), &keypair.to_bytes())
+            .unwrap();
+
+        assert_eq!(pubkey, keypair.pubkey());
+        assert_eq!(manager.get_pubkey("imported").unwrap(), keypair.pubkey());
+    }
+
+    #[test]
+    fn test_import_invalid_keypair_fails() {
+        let mut manager = WalletManager::new();
+
+        assert!(manager.import_wallet("bad".to_string(), &[1; 10]).is_err());
+        assert!(manager.import_wallet("bad".to_string(), &[1; 64]).is_err());
+        assert_eq!(manager.wallet_count(), 0);
+    }
+
+    #[test]
+    fn test_import_duplicate_name_fails() {
+        let mut manager = WalletManager::new();
+        manager.generate_wallet("taken".to_string()).unwrap();
+
+        let result = manager.import_wallet("taken".to_string(), &Keypair::new().to_bytes());
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_squad_lifecycle() {
+        let mut manager = WalletManager::new();
+        let wallets = manager.batch_generate("kamino", 3).unwrap();
+
+        manager
+            .create_squad("kamino".to_string(), Some("Kamino".to_string()))
+            .unwrap();
+        manager.assign_to_squad("kamino", &wallets).unwrap();
+        manager.assign_to_squad("kamino", &wallets[..1]).unwrap();
+        manager
+            .unassign_from_squad("kamino", &wallets[2..])
+            .unwrap();
+
+        let squad = manager.get_squad("kamino").unwrap();
+        assert_eq!(squad.campaign.as_deref(), Some("Kamino"));
+        assert_eq!(squad.wallets, wallets[..2]);
+        assert_eq!(manager.squad_pubkeys("kamino").unwrap().len(), 2);
+
+        manager.remove_squad("kamino").unwrap();
+        assert!(manager.get_squad("kamino").is_err());
+        assert_eq!(manager.wallet_count(), 3);
+    }
+
+    #[test]
+    fn test_duplicate_squad_fails() {
+        let mut manager = WalletManager::new();
+        manager.create_squad("kamino".to_string(), None).unwrap();
+
+        assert!(manager.create_squad("kamino".to_string(), None).is_err());
+    }
+
+    #[test]
+    fn test_assign_unknown_wallet_fails() {
+        let mut manager = WalletManager::new();
+        manager.generate_wallet("known".to_string()).unwrap();
+        manager.create_squad("kamino".to_string(), None).unwrap();
+
+        let result = manager.assign_to_squad("kamino", &["known".to_string(), "ghost".to_string()]);
+
+        assert!(result.is_err());
+        assert!(manager.get_squad("kamino").unwrap().wallets.is_empty());
+    }
+
+    #[test]
+    fn test_removed_wallet_leaves_squads() {
+        let mut manager = WalletManager::new();
+        let wallets = manager.batch_generate("w", 2).unwrap();
+        manager.create_squad("kamino".to_string(), None).unwrap();
+        manager.assign_to_squad("kamino", &wallets).unwrap();
+
+        manager.remove_wallet(&wallets[0]).unwrap();
+
+        assert_eq!(manager.get_squad("kamino").unwrap().wallets, wallets[1..]);
+    }
+
+    #[test]
+    fn test_list_squads_sorted() {
+        let mut manager = WalletManager::new();
+        manager.create_squad("marginfi".to_string(), None).unwrap();
+        manager.create_squad("kamino".to_string(), None).unwrap();
+
+        let names: Vec<&str> = manager
+            .list_squads()
+            .iter()
+            .map(|squad| squad.name.as_str())
+            .collect();
+
+        assert_eq!(names, vec!["kamino", "marginfi"]);
+    }
+
+    fn funded(manager: &WalletManager, lamports: &[(&str, u64)]) -> HashMap<Pubkey, u64> {
+        lamports
+            .iter()
+            .map(|(name, lamports)| (manager.get_pubkey(name).unwrap(), *lamports))
+            .collect()
+    }
+
+    fn treasury_with_squad(members: &[&str]) -> WalletManager {
+        let mut manager = WalletManager::new();
+        manager.generate_wallet("treasury".to_string()).unwrap();
+        manager.set_treasury("treasury").unwrap();
+        manager.create_squad("kamino".to_string(), None).unwrap();
+        for member in members {
+            manager.generate_wallet(member.to_string()).unwrap();
+        }
+        let members: Vec<String> = members.iter().map(|m| m.to_string()).collect();
+        manager.assign_to_squad("kamino", &members).unwrap();
+        manager
+            .set_squad_top_up(
+                "kamino",
+                Some(TopUpPolicy::new(10_000_000, 4_000_000).unwrap()),
+            )
+            .unwrap();
+        manager
+    }
+
+    #[test]
+    fn test_plan_tops_up_wallets_below_minimum_to_target() {
+        let manager = treasury_with_squad(&["low", "ok", "empty"]);
+        let balances = funded(
+            &manager,
+            &[
+                ("treasury", 100_000_000),
+                ("low", 3_000_000),
+                ("ok", 4_000_000),
+                ("empty", 0),
+            ],
+        );
+
+        let plan = manager
+            .plan_top_ups(&balances, 0, &FeeSettings::default())
+            .unwrap();
+
+        assert_eq!(plan.treasury, manager.get_pubkey("treasury").unwrap());
+        assert_eq!(plan.batches.len(), 1);
+        assert_eq!(plan.batches[0].squad, "kamino");
+        assert_eq!(
+            plan.batches[0].params.transfers,
+            vec![
+                (manager.get_pubkey("low").unwrap(), 7_000_000),
+                (manager.get_pubkey("empty").unwrap(), 10_000_000),
+            ]
+        );
+        assert_eq!(plan.total_lamports, 17_000_000);
+        assert_eq!(plan.estimated_fees, 5_000);
+    }
+
+    #[test]
+    fn test_plan_leaves_treasury_fees_and_rent() {
+        let manager = treasury_with_squad(&["empty"]);
+        let fees = FeeSettings::new()
+            .with_compute_unit_price(1_000_000)
+            .with_compute_unit_limit(1_000);
+        // 10_000_000 for the transfer, 5_000 + 1_000 in fees and 890_880 rent
+        let balances = funded(&manager, &[("treasury", 10_896_880), ("empty", 0)]);
+
+        let plan = manager.plan_top_ups(&balances, 890_880, &fees).unwrap();
+        assert_eq!(plan.estimated_fees, 6_000);
+
+        let short = funded(&manager, &[("treasury", 10_896_879), ("empty", 0)]);
+        let error = manager.plan_top_ups(&short, 890_880, &fees).unwrap_err();
+        assert!(
+            error.contains("need 10000000 plus 6000 in fees, and it must keep 890880"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_plan_skips_treasury_and_repeated_members() {
+        let mut manager = treasury_with_squad(&["shared"]);
+        manager.create_squad("marginfi".to_string(), None).unwrap();
+        let members = vec!["shared".to_string(), "treasury".to_string()];
+        manager.assign_to_squad("marginfi", &members).unwrap();
+        manager
+            .set_squad_top_up(
+                "marginfi",
+                Some(TopUpPolicy::new(50_000_000, 50_000_000).unwrap()),
+            )
+            .unwrap();
+        let balances = funded(&manager, &[("treasury", 100_000), ("shared", 0)]);
+
+        let result = manager.plan_top_ups(&balances, 0, &FeeSettings::default());
+
+        // Only kamino tops up the shared wallet, and the treasury never pays itself
+        let error = result.unwrap_err();
+        assert!(
+            error.contains("holds 100000 lamports, but top-ups need 10000000"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_plan_rejects_targets_below_rent_exemption() {
+        let manager = treasury_with_squad(&["empty"]);
+        let balances = funded(&manager, &[("treasury", u64::MAX), ("empty", 0)]);
+
+        let error = manager
+            .plan_top_ups(&balances, 20_000_000, &FeeSettings::default())
+            .unwrap_err();
+
+        assert_eq!(
+            error,
+            "Squad 'kamino': Top-up target 10000000 is below the rent-exempt minimum 20000000"
+        );
+    }
+
+    #[test]
+    fn test_plan_splits_large_squads_into_batches() {
+        let names: Vec<String> = (0..50).map(|i| format!("w{}", i)).collect();
+        let members: Vec<&str> = names.iter().map(String::as_str).collect();
+        let manager = treasury_with_squad(&members);
+        let mut balances = funded(&manager, &[("treasury", u64::MAX)]);
+        for name in &members {
+            balances.insert(manager.get_pubkey(name).unwrap(), 0);
+        }
+
+        let plan = manager
+            .plan_top_ups(&balances, 0, &FeeSettings::default())
+            .unwrap();
+
+        assert!(plan.batches.len() > 1);
+        assert_eq!(plan.transfer_count(), 50);
+        assert_eq!(plan.total_lamports, 500_000_000);
+    }
+
+    #[test]
+    fn test_plan_needs_treasury_and_balances() {
+        let mut manager = treasury_with_squad(&["alpha"]);
+        let balances = funded(&manager, &[("treasury", 100_000_000)]);
+
+        assert!(manager
+            .plan_top_ups(&balances, 0, &FeeSettings::default())
+            .unwrap_err()
+            .contains("'alpha'"));
+
+        manager.remove_wallet("treasury").unwrap();
+        assert_eq!(manager.treasury(), None);
+        assert_eq!(
+            manager
+                .plan_top_ups(&balances, 0, &FeeSettings::default())
+                .unwrap_err(),
+            "No treasury wallet set"
+        );
+        assert!(manager.set_treasury("ghost").is_err());
+    }
 }
@@ -1,3 +1,9 @@
+pub mod keystore;
 pub mod manager;
+pub mod squad;
+pub mod treasury;
 
+pub use keystore::{Keystore, KEYSTORE_VERSION};
 pub use manager::WalletManager;
+pub use squad::Squad;
+pub use treasury::{TopUpBatch, TopUpPlan, TopUpPolicy};
@@ -0,0 +1,35 @@
+use super::treasury::TopUpPolicy;
+use crate::cluster::Cluster;
+use serde::{Deserialize, Serialize};
+
+/// A named group of wallets farming one campaign together
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Squad {
+    pub name: String,
+    /// The protocol or airdrop campaign the squad is assigned to
+    pub campaign: Option<String>,
+    /// Names of member wallets, in the order they were assigned
+    pub wallets: Vec<String>,
+    /// The cluster the squad farms on; untagged squads predate cluster tagging
+    #[serde(default)]
+    pub cluster: Option<Cluster>,
+    /// Keeps members funded from the treasury when set
+    #[serde(default)]
+    pub top_up: Option<TopUpPolicy>,
+}
+
+impl Squad {
+    pub fn new(name: String, campaign: Option<String>) -> Self {
+        Self {
+            name,
+            campaign,
+            wallets: Vec::new(),
+            cluster: None,
+            top_up: None,
+        }
+    }
+
+    pub fn contains(&self, wallet: &str) -> bool {
+        self.wallets.iter().any(|member| member == wallet)
+    }
+}
@@ -0,0 +1,116 @@
+use crate::transaction::BatchTransferParams;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+
+/// How a squad's wallets are kept funded from the treasury
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct TopUpPolicy {
+    /// What a topped-up wallet is brought back up to
+    pub target_lamports: u64,
+    /// Wallets holding less than this are topped up
+    pub min_lamports: u64,
+}
+
+impl TopUpPolicy {
+    pub fn new(target_lamports: u64, min_lamports: u64) -> Result<Self, String> {
+        let policy = Self {
+            target_lamports,
+            min_lamports,
+        };
+        policy.validate()?;
+        Ok(policy)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.target_lamports == 0 {
+            return Err("Top-up target must be greater than zero".to_string());
+        }
+        if self.min_lamports > self.target_lamports {
+            return Err(format!(
+                "Top-up minimum {} is above the target {}",
+                self.min_lamports, self.target_lamports
+            ));
+        }
+        Ok(())
+    }
+
+    /// Rejects a target an empty wallet could not be topped up to, since the
+    /// runtime refuses transfers that leave a new account below rent exemption
+    pub fn check_rent_exempt(&self, rent_exempt_minimum: u64) -> Result<(), String> {
+        if self.target_lamports < rent_exempt_minimum {
+            return Err(format!(
+                "Top-up target {} is below the rent-exempt minimum {}",
+                self.target_lamports, rent_exempt_minimum
+            ));
+        }
+        Ok(())
+    }
+
+    /// How much to send a wallet holding `lamports`, if anything
+    pub fn shortfall(&self, lamports: u64) -> Option<u64> {
+        (lamports < self.min_lamports).then(|| self.target_lamports - lamports)
+    }
+}
+
+/// Top-ups for one squad that fit in a single transaction
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TopUpBatch {
+    pub squad: String,
+    pub params: BatchTransferParams,
+}
+
+/// Every transfer needed to bring under-funded squad wallets up to target
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TopUpPlan {
+    pub treasury: Pubkey,
+    pub batches: Vec<TopUpBatch>,
+    pub total_lamports: u64,
+    /// What sending every batch is expected to cost the treasury in fees
+    pub estimated_fees: u64,
+}
+
+impl TopUpPlan {
+    pub fn transfer_count(&self) -> usize {
+        self.batches
+            .iter()
+            .map(|batch| batch.params.transfers.len())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.batches.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_validation() {
+        assert!(TopUpPolicy::new(10_000_000, 10_000_000).is_ok());
+        assert!(TopUpPolicy::new(0, 0).is_err());
+        assert!(TopUpPolicy::new(10_000_000, 10_000_001).is_err());
+    }
+
+    #[test]
+    fn test_target_must_be_rent_exempt() {
+        let policy = TopUpPolicy::new(890_880, 0).unwrap();
+
+        assert!(policy.check_rent_exempt(890_880).is_ok());
+        assert_eq!(
+            policy.check_rent_exempt(890_881).unwrap_err(),
+            "Top-up target 890880 is below the rent-exempt minimum 890881"
+        );
+    }
+
+    #[test]
+    fn test_shortfall_only_below_minimum() {
+        let policy = TopUpPolicy::new(10_000_000, 4_000_000).unwrap();
+
+        assert_eq!(policy.shortfall(3_999_999), Some(6_000_001));
+        assert_eq!(policy.shortfall(4_000_000), None);
+        assert_eq!(policy.shortfall(0), Some(10_000_000));
+    }
+}
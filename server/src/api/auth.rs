@@ -0,0 +1,46 @@
+use super::error::ApiError;
+use crate::auth::{Role, TokenStore};
+use axum::{
+    extract::{Request, State},
+    http::header::AUTHORIZATION,
+    middleware::Next,
+    response::Response,
+};
+use std::sync::{Arc, RwLock};
+
+/// Middleware state: the role every route behind it requires
+#[derive(Clone)]
+pub(super) struct RequireRole {
+    pub tokens: Arc<RwLock<TokenStore>>,
+    pub role: Role,
+}
+
+/// Checks the bearer token and hands the matching `ApiToken` on in the request extensions
+pub(super) async fn require_role(
+    State(required): State<RequireRole>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let secret = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or_else(|| ApiError::unauthorized("Missing bearer token"))?;
+    let token = required
+        .tokens
+        .read()
+        .expect("token lock poisoned")
+        .authenticate(secret.trim())
+        .cloned()
+        .ok_or_else(|| ApiError::unauthorized("Invalid or revoked token"))?;
+    if !token.allows(required.role) {
+        return Err(ApiError::forbidden(format!(
+            "Requires the {} role",
+            required.role.as_str()
+        )));
+    }
+
+    request.extensions_mut().insert(token);
+    Ok(next.run(request).await)
+}
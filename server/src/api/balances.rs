@@ -0,0 +1,41 @@
+use crate::balances::CachedBalance;
+use crate::state::AppState;
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct BalanceQueryParams {
+    /// Only wallets holding fewer lamports than this
+    pub below: Option<u64>,
+}
+
+/// The balance monitor's last view of every wallet
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BalanceReport {
+    /// When the monitor last refreshed, in ms since the Unix epoch; `None` before its first refresh
+    pub refreshed_at: Option<u64>,
+    pub rent_exempt_minimum: Option<u64>,
+    /// Lowest first
+    pub balances: Vec<CachedBalance>,
+}
+
+pub async fn list(
+    State(state): State<AppState>,
+    Query(params): Query<BalanceQueryParams>,
+) -> Json<BalanceReport> {
+    let cache = state.balances.read().expect("balance cache lock poisoned");
+    let balances = cache
+        .lowest()
+        .into_iter()
+        .filter(|cached| params.below.is_none_or(|below| cached.lamports < below))
+        .cloned()
+        .collect();
+    Json(BalanceReport {
+        refreshed_at: cache.refreshed_at,
+        rent_exempt_minimum: cache.rent_exempt_minimum,
+        balances,
+    })
+}
@@ -0,0 +1,92 @@
+use crate::auth::TokenError;
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use task_scheduler::QUEUE_FULL;
+
+/// Body of every error response
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ErrorBody {
+    pub error: String,
+}
+
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, message)
+    }
+
+    /// No valid token was presented
+    pub fn unauthorized(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::UNAUTHORIZED, message)
+    }
+
+    /// The token is valid but its role is too low
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::FORBIDDEN, message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, message)
+    }
+
+    /// Maps a `WalletManager` error onto a status by its wording
+    pub fn from_wallets(message: String) -> Self {
+        let status = if message.contains("not found") {
+            StatusCode::NOT_FOUND
+        } else if message.contains("already exists") {
+            StatusCode::CONFLICT
+        } else {
+            StatusCode::BAD_REQUEST
+        };
+        Self::new(status, message)
+    }
+
+    pub fn from_scheduler(message: &'static str) -> Self {
+        let status = match message {
+            "Task not found" => StatusCode::NOT_FOUND,
+            "Task already scheduled" | "Task already finished" => StatusCode::CONFLICT,
+            "Scheduler is shutting down" => StatusCode::SERVICE_UNAVAILABLE,
+            QUEUE_FULL => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::BAD_REQUEST,
+        };
+        Self::new(status, message)
+    }
+
+    pub fn from_tokens(error: TokenError) -> Self {
+        let status = match error {
+            TokenError::NotFound(_) => StatusCode::NOT_FOUND,
+            TokenError::AlreadyRevoked(_) => StatusCode::CONFLICT,
+            TokenError::Persist(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        Self::new(status, error.to_string())
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = ErrorBody {
+            error: self.message,
+        };
+        (self.status, Json(body)).into_response()
+    }
+}
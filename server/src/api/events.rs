@@ -0,0 +1,85 @@
+use super::error::ApiError;
+use crate::live::{EventFilter, LiveEvent, LiveEventType};
+use crate::state::AppState;
+use axum::{
+    extract::{Query, State},
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures_util::stream::{self, Stream};
+use serde::de::{value::Error as ValueError, IntoDeserializer};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::convert::Infallible;
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+
+/// Query string for the event stream. Both take comma-separated lists.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventStreamParams {
+    pub squad: Option<String>,
+    /// Any of `task`, `balance`, `balance_alert` and `campaign_progress`
+    pub types: Option<String>,
+}
+
+impl EventStreamParams {
+    fn into_filter(self, state: &AppState) -> Result<EventFilter, ApiError> {
+        let mut filter = EventFilter::new();
+        for event_type in split(&self.types) {
+            let event_type = LiveEventType::deserialize(event_type.into_deserializer()).map_err(
+                |e: ValueError| ApiError::bad_request(format!("Invalid event type: {}", e)),
+            )?;
+            filter = filter.with_type(event_type);
+        }
+
+        let wallets = state.wallets.read().expect("wallet lock poisoned");
+        for squad in split(&self.squad) {
+            filter = filter
+                .with_squad(&wallets, squad)
+                .map_err(ApiError::from_wallets)?;
+        }
+        Ok(filter)
+    }
+}
+
+fn split(list: &Option<String>) -> impl Iterator<Item = &str> {
+    list.iter()
+        .flat_map(|list| list.split(','))
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+}
+
+/// Server-Sent Events named after each event's type, with the event as JSON data.
+/// A subscriber that falls too far behind gets a `lagged` event saying how many it missed.
+pub async fn stream(
+    State(state): State<AppState>,
+    Query(params): Query<EventStreamParams>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let filter = params.into_filter(&state)?;
+    let receiver = state.feed.subscribe();
+    let events = stream::unfold((receiver, filter), |(mut receiver, filter)| async move {
+        let event = next_event(&mut receiver, &filter).await?;
+        Some((Ok(event), (receiver, filter)))
+    });
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+async fn next_event(receiver: &mut Receiver<LiveEvent>, filter: &EventFilter) -> Option<Event> {
+    loop {
+        match receiver.recv().await {
+            Ok(event) if filter.matches(&event) => {
+                let name = event.event_type().as_str();
+                if let Ok(sse) = Event::default().event(name).json_data(&event) {
+                    return Some(sse);
+                }
+            }
+            Ok(_) => {}
+            Err(RecvError::Lagged(missed)) => {
+                return Some(
+                    Event::default()
+                        .event("lagged")
+                        .data(json!({ "missed": missed }).to_string()),
+                )
+            }
+            Err(RecvError::Closed) => return None,
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use crate::state::AppState;
+use axum::{extract::State, Json};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Health {
+    pub status: String,
+    pub wallets: usize,
+    /// Tasks waiting to run, including those blocked on dependencies
+    pub pending: usize,
+    pub in_flight: usize,
+    pub paused: bool,
+    pub shutting_down: bool,
+}
+
+pub async fn health(State(state): State<AppState>) -> Json<Health> {
+    let wallets = state
+        .wallets
+        .read()
+        .expect("wallet lock poisoned")
+        .wallet_count();
+    let scheduler = state.scheduler.lock().expect("scheduler lock poisoned");
+    let status = if scheduler.is_shutting_down() {
+        "shutting_down"
+    } else {
+        "ok"
+    };
+
+    Json(Health {
+        status: status.to_string(),
+        wallets,
+        pending: scheduler.get_pending_count(),
+        in_flight: scheduler.in_flight_count(),
+        paused: scheduler.is_paused(),
+        shutting_down: scheduler.is_shutting_down(),
+    })
+}
@@ -0,0 +1,19 @@
+use crate::metrics;
+use crate::state::AppState;
+use axum::{extract::State, http::header, response::IntoResponse};
+
+/// The Prometheus text exposition format
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Refreshes the gauges from current state, then renders every metric
+pub async fn render(State(state): State<AppState>) -> impl IntoResponse {
+    let handle = metrics::install();
+    metrics::record_wallets(&state.wallets.read().expect("wallet lock poisoned"));
+    state
+        .scheduler
+        .lock()
+        .expect("scheduler lock poisoned")
+        .record_metrics();
+    handle.run_upkeep();
+    ([(header::CONTENT_TYPE, CONTENT_TYPE)], handle.render())
+}
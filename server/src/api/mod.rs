@@ -0,0 +1,103 @@
+mod auth;
+mod balances;
+mod error;
+mod events;
+mod health;
+mod metrics;
+mod squads;
+mod tasks;
+mod tokens;
+mod treasury;
+mod wallets;
+
+pub use balances::{BalanceQueryParams, BalanceReport};
+pub use error::{ApiError, ErrorBody};
+pub use events::EventStreamParams;
+pub use health::Health;
+pub use squads::{CreateSquadRequest, UpdateSquadRequest};
+pub use tasks::{ScheduleTaskRequest, TaskQueryParams};
+pub use tokens::{IssueTokenRequest, IssuedToken};
+pub use treasury::{SetTreasuryRequest, TreasuryInfo};
+pub use wallets::{ExportedWallet, GenerateWalletRequest, ImportWalletRequest, WalletInfo};
+
+use crate::auth::Role;
+use crate::state::AppState;
+use auth::{require_role, RequireRole};
+use axum::{
+    middleware,
+    routing::{delete, get, post, put},
+    Router,
+};
+use std::future::Future;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+
+/// Builds the API. Everything but `/health` needs a bearer token with the route's role.
+pub fn router(state: AppState) -> Router {
+    let viewer = Router::new()
+        .route("/balances", get(balances::list))
+        .route("/events", get(events::stream))
+        .route("/metrics", get(metrics::render))
+        .route("/wallets", get(wallets::list))
+        .route("/squads", get(squads::list))
+        .route("/squads/{name}", get(squads::get))
+        .route("/tasks", get(tasks::query))
+        .route("/tasks/{id}", get(tasks::get))
+        .route("/treasury", get(treasury::get));
+    let operator = Router::new()
+        .route("/squads", post(squads::create))
+        .route("/squads/{name}", put(squads::update).delete(squads::remove))
+        .route("/tasks", post(tasks::schedule))
+        .route("/tasks/{id}/cancel", post(tasks::cancel))
+        .route(
+            "/squads/{name}/top-up",
+            put(treasury::set_policy).delete(treasury::remove_policy),
+        )
+        .route("/treasury/top-up", post(treasury::top_up));
+    let admin = Router::new()
+        .route("/wallets", post(wallets::generate))
+        .route("/wallets/import", post(wallets::import))
+        .route("/wallets/{name}", delete(wallets::remove))
+        .route("/wallets/{name}/export", post(wallets::export))
+        .route("/tokens", get(tokens::list).post(tokens::issue))
+        .route("/tokens/{id}/revoke", post(tokens::revoke))
+        .route("/treasury", put(treasury::set));
+
+    Router::new()
+        .route("/health", get(health::health))
+        .merge(restrict(viewer, &state, Role::Viewer))
+        .merge(restrict(operator, &state, Role::Operator))
+        .merge(restrict(admin, &state, Role::Admin))
+        .with_state(state)
+}
+
+fn restrict(routes: Router<AppState>, state: &AppState, role: Role) -> Router<AppState> {
+    let required = RequireRole {
+        tokens: Arc::clone(&state.tokens),
+        role,
+    };
+    routes.route_layer(middleware::from_fn_with_state(required, require_role))
+}
+
+/// Serves the API on `listener` until the process stops, forwarding scheduler
+/// events to the live feed meanwhile
+pub async fn serve(listener: TcpListener, state: AppState) -> std::io::Result<()> {
+    serve_until(listener, state, std::future::pending()).await
+}
+
+/// Like `serve`, but stops accepting connections once `shutdown` completes and
+/// returns when the open requests are done
+pub async fn serve_until(
+    listener: TcpListener,
+    state: AppState,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> std::io::Result<()> {
+    // Before anything is scheduled, so no early metrics are dropped
+    crate::metrics::install();
+    let forwarder = state.feed.forward_from(Arc::clone(&state.scheduler));
+    let served = axum::serve(listener, router(state))
+        .with_graceful_shutdown(shutdown)
+        .await;
+    forwarder.abort();
+    served
+}
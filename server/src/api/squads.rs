@@ -0,0 +1,91 @@
+use super::error::ApiError;
+use super::wallets::update_wallets;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use engine::wallet::Squad;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CreateSquadRequest {
+    pub name: String,
+    pub campaign: Option<String>,
+    #[serde(default)]
+    pub wallets: Vec<String>,
+}
+
+/// Replaces a squad's campaign and members
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct UpdateSquadRequest {
+    pub campaign: Option<String>,
+    pub wallets: Vec<String>,
+}
+
+pub async fn list(State(state): State<AppState>) -> Json<Vec<Squad>> {
+    let wallets = state.wallets.read().expect("wallet lock poisoned");
+    Json(wallets.list_squads().into_iter().cloned().collect())
+}
+
+pub async fn get(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<Squad>, ApiError> {
+    let wallets = state.wallets.read().expect("wallet lock poisoned");
+    let squad = wallets.get_squad(&name).map_err(ApiError::from_wallets)?;
+    Ok(Json(squad.clone()))
+}
+
+pub async fn create(
+    State(state): State<AppState>,
+    Json(request): Json<CreateSquadRequest>,
+) -> Result<(StatusCode, Json<Squad>), ApiError> {
+    let squad = update_wallets(&state, |wallets| {
+        wallets
+            .create_squad(request.name.clone(), request.campaign)
+            .map_err(ApiError::from_wallets)?;
+        // Don't leave a half-created squad behind if a member doesn't exist
+        if let Err(e) = wallets.assign_to_squad(&request.name, &request.wallets) {
+            let _ = wallets.remove_squad(&request.name);
+            return Err(ApiError::bad_request(e));
+        }
+        wallets
+            .get_squad(&request.name)
+            .cloned()
+            .map_err(ApiError::from_wallets)
+    })?;
+    Ok((StatusCode::CREATED, Json(squad)))
+}
+
+pub async fn update(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(request): Json<UpdateSquadRequest>,
+) -> Result<Json<Squad>, ApiError> {
+    let squad = update_wallets(&state, |wallets| {
+        wallets.get_squad(&name).map_err(ApiError::from_wallets)?;
+        wallets
+            .set_squad_wallets(&name, request.wallets)
+            .map_err(ApiError::bad_request)?;
+        wallets
+            .set_squad_campaign(&name, request.campaign)
+            .map_err(ApiError::from_wallets)?;
+        wallets
+            .get_squad(&name)
+            .cloned()
+            .map_err(ApiError::from_wallets)
+    })?;
+    Ok(Json(squad))
+}
+
+pub async fn remove(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    update_wallets(&state, |wallets| {
+        wallets.remove_squad(&name).map_err(ApiError::from_wallets)
+    })?;
+    Ok(StatusCode::NO_CONTENT)
+}
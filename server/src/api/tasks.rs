@@ -0,0 +1,155 @@
+use super::error::ApiError;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use engine::transaction::TransactionParams;
+use serde::de::{value::Error as ValueError, IntoDeserializer};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::time::Duration;
+use task_scheduler::{Task, TaskPage, TaskPriority, TaskQuery, TaskStatus, DEFAULT_PAGE_SIZE};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ScheduleTaskRequest {
+    pub params: TransactionParams,
+    pub priority: Option<TaskPriority>,
+    /// Squad or campaign the task belongs to
+    pub tag: Option<String>,
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+    pub idempotency_key: Option<String>,
+    pub timeout_ms: Option<u64>,
+}
+
+impl ScheduleTaskRequest {
+    pub fn new(params: TransactionParams) -> Self {
+        Self {
+            params,
+            priority: None,
+            tag: None,
+            dependencies: Vec::new(),
+            idempotency_key: None,
+            timeout_ms: None,
+        }
+    }
+
+    pub fn into_task(self) -> Result<Task, String> {
+        let params =
+            serde_json::to_string(&self.params).map_err(|e| format!("Invalid params: {}", e))?;
+        let mut task = Task::new(params).with_dependencies(self.dependencies);
+        if let Some(priority) = self.priority {
+            task = task.with_priority(priority);
+        }
+        if let Some(tag) = self.tag {
+            task = task.with_tag(tag);
+        }
+        if let Some(key) = self.idempotency_key {
+            task = task.with_idempotency_key(key);
+        }
+        if let Some(timeout_ms) = self.timeout_ms {
+            task = task.with_timeout(Duration::from_millis(timeout_ms));
+        }
+        Ok(task)
+    }
+}
+
+/// Query string for listing tasks. `status` takes a comma-separated list.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct TaskQueryParams {
+    pub status: Option<String>,
+    pub priority: Option<TaskPriority>,
+    /// Base58 fee-payer address
+    pub wallet: Option<String>,
+    pub tag: Option<String>,
+    pub created_from: Option<u64>,
+    pub created_until: Option<u64>,
+    pub params_type: Option<String>,
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+}
+
+impl TaskQueryParams {
+    fn into_query(self) -> Result<TaskQuery, ApiError> {
+        let mut query = TaskQuery::new().page(
+            self.offset.unwrap_or(0),
+            self.limit.unwrap_or(DEFAULT_PAGE_SIZE),
+        );
+        for status in self.status.iter().flat_map(|list| list.split(',')) {
+            let status = TaskStatus::deserialize(status.trim().into_deserializer())
+                .map_err(|e: ValueError| ApiError::bad_request(format!("Invalid status: {}", e)))?;
+            query = query.status(status);
+        }
+        if let Some(wallet) = self.wallet {
+            let wallet: Pubkey = wallet
+                .parse()
+                .map_err(|_| ApiError::bad_request(format!("Invalid wallet '{}'", wallet)))?;
+            query = query.wallet(wallet);
+        }
+        query.priority = self.priority;
+        query.tag = self.tag;
+        query.created_from = self.created_from;
+        query.created_until = self.created_until;
+        query.params_type = self.params_type;
+        Ok(query)
+    }
+}
+
+/// Responds 201 with the new task, or 200 with the existing one when the
+/// idempotency key matched an earlier task
+pub async fn schedule(
+    State(state): State<AppState>,
+    Json(request): Json<ScheduleTaskRequest>,
+) -> Result<(StatusCode, Json<Task>), ApiError> {
+    let task = request.into_task().map_err(ApiError::bad_request)?;
+    let id = task.id().to_string();
+    let scheduled = state
+        .scheduler
+        .lock()
+        .expect("scheduler lock poisoned")
+        .schedule_task(task)
+        .map_err(ApiError::from_scheduler)?;
+
+    let status = if scheduled.id() == id {
+        StatusCode::CREATED
+    } else {
+        StatusCode::OK
+    };
+    Ok((status, Json(scheduled)))
+}
+
+pub async fn query(
+    State(state): State<AppState>,
+    Query(params): Query<TaskQueryParams>,
+) -> Result<Json<TaskPage>, ApiError> {
+    let query = params.into_query()?;
+    let scheduler = state.scheduler.lock().expect("scheduler lock poisoned");
+    Ok(Json(scheduler.query(&query)))
+}
+
+pub async fn get(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Task>, ApiError> {
+    let scheduler = state.scheduler.lock().expect("scheduler lock poisoned");
+    let task = scheduler
+        .task(&id)
+        .ok_or_else(|| ApiError::not_found("Task not found"))?;
+    Ok(Json(task.clone()))
+}
+
+/// Cancels the task and everything downstream of it
+pub async fn cancel(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    state
+        .scheduler
+        .lock()
+        .expect("scheduler lock poisoned")
+        .cancel_task(&id)
+        .map_err(ApiError::from_scheduler)?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,53 @@
+use super::error::ApiError;
+use crate::auth::{ApiToken, Role};
+use crate::state::AppState;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct IssueTokenRequest {
+    pub name: String,
+    pub role: Role,
+}
+
+/// The only response that ever contains a token's secret
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct IssuedToken {
+    pub token: ApiToken,
+    pub secret: String,
+}
+
+pub async fn list(State(state): State<AppState>) -> Json<Vec<ApiToken>> {
+    let tokens = state.tokens.read().expect("token lock poisoned");
+    Json(tokens.list().into_iter().cloned().collect())
+}
+
+pub async fn issue(
+    State(state): State<AppState>,
+    Json(request): Json<IssueTokenRequest>,
+) -> Result<(StatusCode, Json<IssuedToken>), ApiError> {
+    let (token, secret) = state
+        .tokens
+        .write()
+        .expect("token lock poisoned")
+        .issue(request.name, request.role)
+        .map_err(ApiError::from_tokens)?;
+    Ok((StatusCode::CREATED, Json(IssuedToken { token, secret })))
+}
+
+pub async fn revoke(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    state
+        .tokens
+        .write()
+        .expect("token lock poisoned")
+        .revoke(&id)
+        .map_err(ApiError::from_tokens)?;
+    Ok(StatusCode::NO_CONTENT)
+}
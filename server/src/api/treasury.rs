@@ -0,0 +1,125 @@
+use super::error::ApiError;
+use super::wallets::update_wallets;
+use crate::state::AppState;
+use crate::treasury::{self, TopUpReport};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use engine::wallet::Squad;
+use engine::{TopUpPolicy, WalletManager};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::BTreeMap;
+
+/// The treasury wallet and every squad's top-up policy
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TreasuryInfo {
+    pub wallet: Option<String>,
+    pub pubkey: Option<Pubkey>,
+    /// By squad name; squads without a policy are left out
+    pub policies: BTreeMap<String, TopUpPolicy>,
+}
+
+impl TreasuryInfo {
+    pub fn of(wallets: &WalletManager) -> Self {
+        let wallet = wallets.treasury().map(str::to_string);
+        let pubkey = wallet
+            .as_deref()
+            .and_then(|name| wallets.get_pubkey(name).ok());
+        let policies = wallets
+            .list_squads()
+            .into_iter()
+            .filter_map(|squad| Some((squad.name.clone(), squad.top_up?)))
+            .collect();
+        Self {
+            wallet,
+            pubkey,
+            policies,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SetTreasuryRequest {
+    pub wallet: String,
+}
+
+pub async fn get(State(state): State<AppState>) -> Json<TreasuryInfo> {
+    let wallets = state.wallets.read().expect("wallet lock poisoned");
+    Json(TreasuryInfo::of(&wallets))
+}
+
+pub async fn set(
+    State(state): State<AppState>,
+    Json(request): Json<SetTreasuryRequest>,
+) -> Result<Json<TreasuryInfo>, ApiError> {
+    update_wallets(&state, |wallets| {
+        wallets
+            .set_treasury(&request.wallet)
+            .map_err(ApiError::from_wallets)
+    })?;
+    Ok(get(State(state)).await)
+}
+
+pub async fn set_policy(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(policy): Json<TopUpPolicy>,
+) -> Result<Json<Squad>, ApiError> {
+    let rent_exempt_minimum = state
+        .balances
+        .read()
+        .expect("balance cache lock poisoned")
+        .rent_exempt_minimum_or_default();
+    let squad = update_wallets(&state, |wallets| {
+        wallets.get_squad(&name).map_err(ApiError::from_wallets)?;
+        policy
+            .check_rent_exempt(rent_exempt_minimum)
+            .map_err(ApiError::bad_request)?;
+        wallets
+            .set_squad_top_up(&name, Some(policy))
+            .map_err(ApiError::bad_request)?;
+        wallets
+            .get_squad(&name)
+            .cloned()
+            .map_err(ApiError::from_wallets)
+    })?;
+    Ok(Json(squad))
+}
+
+pub async fn remove_policy(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    update_wallets(&state, |wallets| {
+        wallets
+            .set_squad_top_up(&name, None)
+            .map_err(ApiError::from_wallets)
+    })?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Plans top-ups from the balance monitor's cache and schedules them. Wallets
+/// with a top-up still in flight are not funded again, and batches already
+/// scheduled from the same balances are returned rather than repeated.
+pub async fn top_up(
+    State(state): State<AppState>,
+) -> Result<(StatusCode, Json<TopUpReport>), ApiError> {
+    let balances =
+        treasury::cached_balances(&state.balances.read().expect("balance cache lock poisoned"))
+            .map_err(|e| ApiError::new(StatusCode::SERVICE_UNAVAILABLE, e))?;
+    // Held from counting the transfers in flight until the new ones are scheduled,
+    // so two concurrent runs cannot both fund the same wallets
+    let mut scheduler = state.scheduler.lock().expect("scheduler lock poisoned");
+    let plan = {
+        let wallets = state.wallets.read().expect("wallet lock poisoned");
+        let in_flight = treasury::in_flight_top_ups(&scheduler, &wallets);
+        balances.plan(&wallets, &state.fees, &in_flight)
+    }
+    .map_err(ApiError::bad_request)?;
+    let report = treasury::schedule_top_ups(&mut scheduler, &plan, balances.taken_at)
+        .map_err(ApiError::from_scheduler)?;
+    Ok((StatusCode::CREATED, Json(report)))
+}
@@ -0,0 +1,129 @@
+use super::error::ApiError;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use engine::WalletManager;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WalletInfo {
+    pub name: String,
+    pub pubkey: Pubkey,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct GenerateWalletRequest {
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ImportWalletRequest {
+    pub name: String,
+    /// The 64-byte keypair, as stored in a Solana CLI keypair file
+    pub keypair: Vec<u8>,
+}
+
+/// A wallet's full keypair, for moving it to another tool
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ExportedWallet {
+    pub name: String,
+    pub pubkey: Pubkey,
+    /// The 64-byte keypair, as stored in a Solana CLI keypair file
+    pub keypair: Vec<u8>,
+}
+
+/// Applies `change` under the wallet lock and, if it succeeds, saves the wallets
+/// to the keystore before releasing it, so concurrent changes are saved in order
+pub(super) fn update_wallets<T>(
+    state: &AppState,
+    change: impl FnOnce(&mut WalletManager) -> Result<T, ApiError>,
+) -> Result<T, ApiError> {
+    let mut wallets = state.wallets.write().expect("wallet lock poisoned");
+    let changed = change(&mut wallets)?;
+    if let Some(keystore) = &state.keystore {
+        keystore
+            .save(&wallets)
+            .map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    }
+    Ok(changed)
+}
+
+/// All wallets, sorted by name
+pub async fn list(State(state): State<AppState>) -> Json<Vec<WalletInfo>> {
+    let mut wallets: Vec<WalletInfo> = state
+        .wallets
+        .read()
+        .expect("wallet lock poisoned")
+        .list_wallets()
+        .into_iter()
+        .map(|(name, pubkey)| WalletInfo { name, pubkey })
+        .collect();
+    wallets.sort_by(|a, b| a.name.cmp(&b.name));
+    Json(wallets)
+}
+
+pub async fn generate(
+    State(state): State<AppState>,
+    Json(request): Json<GenerateWalletRequest>,
+) -> Result<(StatusCode, Json<WalletInfo>), ApiError> {
+    let pubkey = update_wallets(&state, |wallets| {
+        wallets
+            .generate_wallet(request.name.clone())
+            .map_err(ApiError::from_wallets)
+    })?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(WalletInfo {
+            name: request.name,
+            pubkey,
+        }),
+    ))
+}
+
+pub async fn import(
+    State(state): State<AppState>,
+    Json(request): Json<ImportWalletRequest>,
+) -> Result<(StatusCode, Json<WalletInfo>), ApiError> {
+    let pubkey = update_wallets(&state, |wallets| {
+        wallets
+            .import_wallet(request.name.clone(), &request.keypair)
+            .map_err(ApiError::from_wallets)
+    })?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(WalletInfo {
+            name: request.name,
+            pubkey,
+        }),
+    ))
+}
+
+pub async fn remove(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    update_wallets(&state, |wallets| {
+        wallets.remove_wallet(&name).map_err(ApiError::from_wallets)
+    })?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn export(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<ExportedWallet>, ApiError> {
+    let wallets = state.wallets.read().expect("wallet lock poisoned");
+    let keypair = wallets.get_wallet(&name).map_err(ApiError::from_wallets)?;
+    Ok(Json(ExportedWallet {
+        pubkey: keypair.pubkey(),
+        keypair: keypair.to_bytes().to_vec(),
+        name,
+    }))
+}
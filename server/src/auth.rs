@@ -0,0 +1,280 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+/// What a token may do. Each role includes everything the roles below it may do.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Read-only access
+    Viewer,
+    /// Also schedules and cancels tasks and manages squads
+    Operator,
+    /// Also manages wallets, keys and tokens
+    Admin,
+}
+
+impl Role {
+    /// Matches the serialized name
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Operator => "operator",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+/// A token as stored. The secret itself is only ever returned when the token is issued.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ApiToken {
+    pub id: String,
+    /// Who or what the token was issued to
+    pub name: String,
+    pub role: Role,
+    /// In ms since the Unix epoch
+    pub created_at: u64,
+    pub revoked_at: Option<u64>,
+}
+
+impl ApiToken {
+    pub fn is_revoked(&self) -> bool {
+        self.revoked_at.is_some()
+    }
+
+    pub fn allows(&self, role: Role) -> bool {
+        !self.is_revoked() && self.role >= role
+    }
+}
+
+/// Why a token could not be issued or revoked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenError {
+    NotFound(String),
+    AlreadyRevoked(String),
+    /// The change could not be saved, and so was not made
+    Persist(String),
+}
+
+impl fmt::Display for TokenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenError::NotFound(id) => write!(f, "Token '{}' not found", id),
+            TokenError::AlreadyRevoked(id) => write!(f, "Token '{}' is already revoked", id),
+            TokenError::Persist(error) => f.write_str(error),
+        }
+    }
+}
+
+impl std::error::Error for TokenError {}
+
+/// API tokens keyed by the SHA-256 of their secret, optionally persisted as JSON
+#[derive(Debug, Default)]
+pub struct TokenStore {
+    tokens: HashMap<String, ApiToken>,
+    path: Option<PathBuf>,
+}
+
+impl TokenStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads tokens from `path`, creating no file until the first change
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, String> {
+        let path = path.into();
+        let tokens = match fs::read_to_string(&path) {
+            Ok(contents) => {
+                serde_json::from_str(&contents).map_err(|e| format!("Invalid token file: {}", e))?
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(format!("Failed to read tokens: {}", e)),
+        };
+        Ok(Self {
+            tokens,
+            path: Some(path),
+        })
+    }
+
+    /// Creates a token and returns it with its secret, which is not stored. The
+    /// token only works once it has been saved.
+    pub fn issue(
+        &mut self,
+        name: impl Into<String>,
+        role: Role,
+    ) -> Result<(ApiToken, String), TokenError> {
+        let secret = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        let token = ApiToken {
+            id: Uuid::new_v4().to_string(),
+            name: name.into(),
+            role,
+            created_at: now_millis(),
+            revoked_at: None,
+        };
+        let mut staged = self.tokens.clone();
+        staged.insert(hash_secret(&secret), token.clone());
+        self.persist(&staged)?;
+        self.tokens = staged;
+        Ok((token, secret))
+    }
+
+    /// Looks up the live token with this secret
+    pub fn authenticate(&self, secret: &str) -> Option<&ApiToken> {
+        self.tokens
+            .get(&hash_secret(secret))
+            .filter(|token| !token.is_revoked())
+    }
+
+    /// Revokes a token once the revocation has been saved, so it never comes back
+    pub fn revoke(&mut self, id: &str) -> Result<ApiToken, TokenError> {
+        let mut staged = self.tokens.clone();
+        let token = staged
+            .values_mut()
+            .find(|token| token.id == id)
+            .ok_or_else(|| TokenError::NotFound(id.to_string()))?;
+        if token.is_revoked() {
+            return Err(TokenError::AlreadyRevoked(id.to_string()));
+        }
+        token.revoked_at = Some(now_millis());
+        let token = token.clone();
+        self.persist(&staged)?;
+        self.tokens = staged;
+        Ok(token)
+    }
+
+    /// All tokens, revoked ones included, oldest first
+    pub fn list(&self) -> Vec<&ApiToken> {
+        let mut tokens: Vec<&ApiToken> = self.tokens.values().collect();
+        tokens.sort_by(|a, b| {
+            a.created_at
+                .cmp(&b.created_at)
+                .then_with(|| a.id.cmp(&b.id))
+        });
+        tokens
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    fn persist(&self, tokens: &HashMap<String, ApiToken>) -> Result<(), TokenError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let contents = serde_json::to_string_pretty(tokens)
+            .map_err(|e| TokenError::Persist(format!("Failed to serialize tokens: {}", e)))?;
+        let failed =
+            |e: std::io::Error| TokenError::Persist(format!("Failed to write tokens: {}", e));
+        // Write beside the target and rename, so a crash never leaves a torn file
+        let staging = path.with_extension("tmp");
+        fs::write(&staging, contents).map_err(failed)?;
+        fs::rename(&staging, path).map_err(failed)
+    }
+}
+
+fn hash_secret(secret: &str) -> String {
+    Sha256::digest(secret.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_issued_token_authenticates() {
+        let mut store = TokenStore::new();
+
+        let (token, secret) = store.issue("dashboard", Role::Viewer).unwrap();
+
+        assert_eq!(store.authenticate(&secret), Some(&token));
+        assert_eq!(store.authenticate("wrong"), None);
+    }
+
+    #[test]
+    fn test_secret_is_not_stored() {
+        let mut store = TokenStore::new();
+
+        let (_, secret) = store.issue("dashboard", Role::Viewer).unwrap();
+
+        assert!(!store.tokens.contains_key(&secret));
+        assert!(!format!("{:?}", store).contains(&secret));
+    }
+
+    #[test]
+    fn test_revoked_token_no_longer_authenticates() {
+        let mut store = TokenStore::new();
+        let (token, secret) = store.issue("bot", Role::Operator).unwrap();
+
+        let revoked = store.revoke(&token.id).unwrap();
+
+        assert!(revoked.is_revoked());
+        assert_eq!(store.authenticate(&secret), None);
+        assert_eq!(
+            store.revoke(&token.id).unwrap_err(),
+            TokenError::AlreadyRevoked(token.id.clone())
+        );
+        assert_eq!(
+            store.revoke("missing").unwrap_err(),
+            TokenError::NotFound("missing".to_string())
+        );
+    }
+
+    #[test]
+    fn test_changes_that_fail_to_save_are_not_made() {
+        let dir = std::env::temp_dir().join(format!("tokens-{}", Uuid::new_v4()));
+        fs::create_dir(&dir).unwrap();
+        let mut store = TokenStore::open(dir.join("tokens.json")).unwrap();
+        let (token, secret) = store.issue("admin", Role::Admin).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let issued = store.issue("viewer", Role::Viewer);
+        let revoked = store.revoke(&token.id);
+
+        assert!(matches!(issued, Err(TokenError::Persist(_))));
+        assert!(matches!(revoked, Err(TokenError::Persist(_))));
+        assert_eq!(store.list().len(), 1);
+        assert_eq!(store.authenticate(&secret), Some(&token));
+    }
+
+    #[test]
+    fn test_roles_include_lower_roles() {
+        let mut store = TokenStore::new();
+        let (operator, _) = store.issue("bot", Role::Operator).unwrap();
+
+        assert!(operator.allows(Role::Viewer));
+        assert!(operator.allows(Role::Operator));
+        assert!(!operator.allows(Role::Admin));
+    }
+
+    #[test]
+    fn test_tokens_persist_across_opens() {
+        let path = std::env::temp_dir().join(format!("tokens-{}.json", Uuid::new_v4()));
+        let mut store = TokenStore::open(&path).unwrap();
+        let (token, secret) = store.issue("admin", Role::Admin).unwrap();
+        store.issue("viewer", Role::Viewer).unwrap();
+
+        let reopened = TokenStore::open(&path).unwrap();
+
+        assert_eq!(reopened.authenticate(&secret), Some(&token));
+        assert_eq!(reopened.list().len(), 2);
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(!contents.contains(&secret));
+        fs::remove_file(&path).unwrap();
+    }
+}
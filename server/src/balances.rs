@@ -0,0 +1,489 @@
+//! Periodic balance checks for every managed wallet, cached with the time they
+//! were fetched, with alerts when a wallet runs low on SOL for fees or rent.
+
+use crate::live::{now_millis, BalanceAlert, BalanceAlertKind, BalanceChange, LiveEvent, LiveFeed};
+use crate::metrics::{rpc_call, BALANCE_ALERTS, LOW_BALANCE_WALLETS};
+use crate::state::AppState;
+use engine::WalletManager;
+use serde::{Deserialize, Serialize};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::rent::Rent;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+/// Most accounts `getMultipleAccounts` accepts per call
+pub const MAX_ACCOUNTS_PER_REQUEST: usize = 100;
+/// 0.01 SOL, enough for a few thousand signatures at the base fee
+pub const DEFAULT_LOW_BALANCE: u64 = 10_000_000;
+
+/// Fetches lamport balances
+pub trait BalanceSource: Send + Sync + 'static {
+    /// One balance per pubkey, in order; accounts that do not exist hold 0
+    fn balances(&self, pubkeys: &[Pubkey])
+        -> impl Future<Output = Result<Vec<u64>, String>> + Send;
+
+    /// The least a plain system account must hold to be rent-exempt
+    fn rent_exempt_minimum(&self) -> impl Future<Output = Result<u64, String>> + Send;
+}
+
+/// Fetches balances with one `fetch` per [`MAX_ACCOUNTS_PER_REQUEST`] pubkeys,
+/// in order
+async fn fetch_in_chunks<F, Fut>(pubkeys: &[Pubkey], mut fetch: F) -> Result<Vec<u64>, String>
+where
+    F: FnMut(Vec<Pubkey>) -> Fut,
+    Fut: Future<Output = Result<Vec<u64>, String>>,
+{
+    let mut balances = Vec::with_capacity(pubkeys.len());
+    for chunk in pubkeys.chunks(MAX_ACCOUNTS_PER_REQUEST) {
+        balances.extend(fetch(chunk.to_vec()).await?);
+    }
+    Ok(balances)
+}
+
+impl BalanceSource for RpcClient {
+    async fn balances(&self, pubkeys: &[Pubkey]) -> Result<Vec<u64>, String> {
+        fetch_in_chunks(pubkeys, |chunk| async move {
+            let accounts = rpc_call("getMultipleAccounts", self.get_multiple_accounts(&chunk))
+                .await
+                .map_err(|e| format!("Failed to fetch balances: {}", e))?;
+            Ok(accounts
+                .into_iter()
+                .map(|account| account.map_or(0, |account| account.lamports))
+                .collect())
+        })
+        .await
+    }
+
+    async fn rent_exempt_minimum(&self) -> Result<u64, String> {
+        rpc_call(
+            "getMinimumBalanceForRentExemption",
+            self.get_minimum_balance_for_rent_exemption(0),
+        )
+        .await
+        .map_err(|e| format!("Failed to fetch rent-exempt minimum: {}", e))
+    }
+}
+
+/// What a plain system account must hold to be rent-exempt under the default
+/// rent parameters, which every public cluster uses
+pub fn default_rent_exempt_minimum() -> u64 {
+    Rent::default().minimum_balance(0)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CachedBalance {
+    pub wallet: String,
+    pub pubkey: Pubkey,
+    pub lamports: u64,
+    /// When the balance was fetched, in ms since the Unix epoch
+    pub fetched_at: u64,
+}
+
+/// The last balances the monitor fetched
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct BalanceCache {
+    /// When the last refresh finished, in ms since the Unix epoch
+    pub refreshed_at: Option<u64>,
+    pub rent_exempt_minimum: Option<u64>,
+    /// Keyed by wallet name
+    pub balances: BTreeMap<String, CachedBalance>,
+}
+
+impl BalanceCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The rent-exempt minimum last fetched, or the runtime default until then
+    pub fn rent_exempt_minimum_or_default(&self) -> u64 {
+        self.rent_exempt_minimum
+            .unwrap_or_else(default_rent_exempt_minimum)
+    }
+
+    pub fn get(&self, wallet: &str) -> Option<&CachedBalance> {
+        self.balances.get(wallet)
+    }
+
+    /// Cached balances, lowest first
+    pub fn lowest(&self) -> Vec<&CachedBalance> {
+        let mut balances: Vec<&CachedBalance> = self.balances.values().collect();
+        balances.sort_by(|a, b| (a.lamports, &a.wallet).cmp(&(b.lamports, &b.wallet)));
+        balances
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BalanceMonitorConfig {
+    /// How often every wallet's balance is fetched
+    pub interval: Duration,
+    /// Wallets below this many lamports are alerted on
+    pub low_balance: u64,
+}
+
+impl Default for BalanceMonitorConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(60),
+            low_balance: DEFAULT_LOW_BALANCE,
+        }
+    }
+}
+
+/// Keeps the shared balance cache current and publishes balance changes and
+/// alerts to the live feed
+pub struct BalanceMonitor<S: BalanceSource> {
+    wallets: Arc<RwLock<WalletManager>>,
+    cache: Arc<RwLock<BalanceCache>>,
+    feed: LiveFeed,
+    source: Arc<S>,
+    config: BalanceMonitorConfig,
+}
+
+pub struct BalanceMonitorHandle {
+    stop: watch::Sender<bool>,
+    task: JoinHandle<()>,
+}
+
+impl<S: BalanceSource> BalanceMonitor<S> {
+    /// Watches the state's wallets, filling its balance cache and feed
+    pub fn new(state: &AppState, source: S, config: BalanceMonitorConfig) -> Self {
+        Self {
+            wallets: Arc::clone(&state.wallets),
+            cache: Arc::clone(&state.balances),
+            feed: state.feed.clone(),
+            source: Arc::new(source),
+            config,
+        }
+    }
+
+    /// Fetches every wallet's balance and returns the alerts raised. A wallet is
+    /// alerted on when it first drops below a threshold, or is first seen below it.
+    pub async fn refresh(&self) -> Result<Vec<BalanceAlert>, String> {
+        let wallets = self
+            .wallets
+            .read()
+            .expect("wallet lock poisoned")
+            .list_wallets();
+        let rent_exempt_minimum = match self.cached_rent_exempt_minimum() {
+            Some(minimum) => minimum,
+            None => self.source.rent_exempt_minimum().await?,
+        };
+        let pubkeys: Vec<Pubkey> = wallets.iter().map(|(_, pubkey)| *pubkey).collect();
+        let lamports = self.source.balances(&pubkeys).await?;
+        if lamports.len() != wallets.len() {
+            return Err("Balance source returned the wrong number of balances".to_string());
+        }
+
+        let fetched_at = now_millis();
+        let mut alerts = Vec::new();
+        let mut cache = self.cache.write().expect("balance cache lock poisoned");
+        let mut balances = BTreeMap::new();
+        for ((wallet, pubkey), lamports) in wallets.into_iter().zip(lamports) {
+            // A wallet re-created under the same name starts over
+            let previous = cache
+                .balances
+                .get(&wallet)
+                .filter(|cached| cached.pubkey == pubkey)
+                .map(|cached| cached.lamports);
+            if previous != Some(lamports) {
+                self.feed.publish(LiveEvent::Balance(BalanceChange::new(
+                    &wallet, pubkey, previous, lamports,
+                )));
+            }
+            for (kind, threshold) in [
+                (BalanceAlertKind::LowBalance, self.config.low_balance),
+                (BalanceAlertKind::BelowRentExempt, rent_exempt_minimum),
+            ] {
+                let crossed = lamports < threshold && previous.is_none_or(|prev| prev >= threshold);
+                if crossed {
+                    alerts.push(BalanceAlert::new(
+                        &wallet, pubkey, kind, lamports, threshold,
+                    ));
+                }
+            }
+            let cached = CachedBalance {
+                wallet: wallet.clone(),
+                pubkey,
+                lamports,
+                fetched_at,
+            };
+            balances.insert(wallet, cached);
+        }
+        // Wallets removed since the last refresh drop out here
+        cache.balances = balances;
+        cache.rent_exempt_minimum = Some(rent_exempt_minimum);
+        cache.refreshed_at = Some(fetched_at);
+
+        let low = cache
+            .balances
+            .values()
+            .filter(|cached| cached.lamports < self.config.low_balance)
+            .count();
+        metrics::gauge!(LOW_BALANCE_WALLETS).set(low as f64);
+        for alert in &alerts {
+            warn!(
+                wallet = %alert.wallet,
+                pubkey = %alert.pubkey,
+                kind = ?alert.kind,
+                lamports = alert.lamports,
+                threshold = alert.threshold,
+                "Wallet balance is low"
+            );
+            let kind = match alert.kind {
+                BalanceAlertKind::LowBalance => "low_balance",
+                BalanceAlertKind::BelowRentExempt => "below_rent_exempt",
+            };
+            metrics::counter!(BALANCE_ALERTS, "kind" => kind).increment(1);
+            self.feed.publish(LiveEvent::BalanceAlert(alert.clone()));
+        }
+        Ok(alerts)
+    }
+
+    /// Refreshes every `interval` until shut down. Failed refreshes are logged
+    /// and leave the cache as it was.
+    pub fn spawn(self) -> BalanceMonitorHandle {
+        let (stop, mut stopped) = watch::channel(false);
+        let task = tokio::spawn(async move {
+            while !*stopped.borrow() {
+                if let Err(error) = self.refresh().await {
+                    warn!(%error, "Balance refresh failed");
+                }
+                tokio::select! {
+                    _ = tokio::time::sleep(self.config.interval) => {}
+                    _ = stopped.changed() => {}
+                }
+            }
+        });
+        BalanceMonitorHandle { stop, task }
+    }
+
+    fn cached_rent_exempt_minimum(&self) -> Option<u64> {
+        self.cache
+            .read()
+            .expect("balance cache lock poisoned")
+            .rent_exempt_minimum
+    }
+}
+
+impl BalanceMonitorHandle {
+    pub async fn shutdown(self) {
+        let _ = self.stop.send(true);
+        let _ = self.task.await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    const RENT_EXEMPT: u64 = 890_880;
+
+    /// Serves balances set by the test, counting how often it is asked
+    #[derive(Default)]
+    struct FixedBalances {
+        lamports: Mutex<HashMap<Pubkey, u64>>,
+        requests: Mutex<Vec<usize>>,
+    }
+
+    impl BalanceSource for Arc<FixedBalances> {
+        async fn balances(&self, pubkeys: &[Pubkey]) -> Result<Vec<u64>, String> {
+            self.requests.lock().unwrap().push(pubkeys.len());
+            let lamports = self.lamports.lock().unwrap();
+            Ok(pubkeys
+                .iter()
+                .map(|pubkey| lamports.get(pubkey).copied().unwrap_or(0))
+                .collect())
+        }
+
+        async fn rent_exempt_minimum(&self) -> Result<u64, String> {
+            Ok(RENT_EXEMPT)
+        }
+    }
+
+    fn monitor() -> (
+        BalanceMonitor<Arc<FixedBalances>>,
+        AppState,
+        Arc<FixedBalances>,
+    ) {
+        let state = AppState::default();
+        let source = Arc::new(FixedBalances::default());
+        let config = BalanceMonitorConfig {
+            low_balance: 5_000_000,
+            ..BalanceMonitorConfig::default()
+        };
+        let monitor = BalanceMonitor::new(&state, Arc::clone(&source), config);
+        (monitor, state, source)
+    }
+
+    fn fund(state: &AppState, source: &FixedBalances, wallet: &str, lamports: u64) {
+        let mut wallets = state.wallets.write().unwrap();
+        let pubkey = match wallets.get_pubkey(wallet) {
+            Ok(pubkey) => pubkey,
+            Err(_) => wallets.generate_wallet(wallet.to_string()).unwrap(),
+        };
+        source.lamports.lock().unwrap().insert(pubkey, lamports);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_caches_balances_with_timestamp() {
+        let (monitor, state, source) = monitor();
+        fund(&state, &source, "alpha", 7_000_000);
+        fund(&state, &source, "bravo", 6_000_000);
+
+        let alerts = monitor.refresh().await.unwrap();
+
+        assert!(alerts.is_empty());
+        let cache = state.balances.read().unwrap();
+        assert_eq!(cache.get("alpha").unwrap().lamports, 7_000_000);
+        assert_eq!(cache.rent_exempt_minimum, Some(RENT_EXEMPT));
+        let refreshed_at = cache.refreshed_at.unwrap();
+        assert!(refreshed_at > 0);
+        assert_eq!(cache.get("bravo").unwrap().fetched_at, refreshed_at);
+        let lowest: Vec<&str> = cache.lowest().iter().map(|b| b.wallet.as_str()).collect();
+        assert_eq!(lowest, ["bravo", "alpha"]);
+    }
+
+    #[tokio::test]
+    async fn test_balances_are_requested_in_chunks() {
+        let pubkeys: Vec<Pubkey> = (0..=MAX_ACCOUNTS_PER_REQUEST)
+            .map(|_| Pubkey::new_unique())
+            .collect();
+        let mut requests = Vec::new();
+
+        let balances = fetch_in_chunks(&pubkeys, |chunk| {
+            requests.push(chunk.len());
+            let offset = (requests.len() as u64 - 1) * MAX_ACCOUNTS_PER_REQUEST as u64;
+            async move { Ok((0..chunk.len() as u64).map(|i| offset + i).collect()) }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(requests, [MAX_ACCOUNTS_PER_REQUEST, 1]);
+        let expected: Vec<u64> = (0..=MAX_ACCOUNTS_PER_REQUEST as u64).collect();
+        assert_eq!(balances, expected);
+    }
+
+    #[tokio::test]
+    async fn test_chunk_failures_fail_the_fetch() {
+        let pubkeys: Vec<Pubkey> = (0..=MAX_ACCOUNTS_PER_REQUEST)
+            .map(|_| Pubkey::new_unique())
+            .collect();
+
+        let error = fetch_in_chunks(&pubkeys, |chunk| async move {
+            if chunk.len() < MAX_ACCOUNTS_PER_REQUEST {
+                Err("Failed to fetch balances: timed out".to_string())
+            } else {
+                Ok(vec![0; chunk.len()])
+            }
+        })
+        .await
+        .unwrap_err();
+
+        assert!(error.contains("timed out"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_hands_every_wallet_to_the_source() {
+        let (monitor, state, source) = monitor();
+        state
+            .wallets
+            .write()
+            .unwrap()
+            .batch_generate("w", MAX_ACCOUNTS_PER_REQUEST + 1)
+            .unwrap();
+
+        monitor.refresh().await.unwrap();
+
+        // Sources get the whole list; see fetch_in_chunks for the RPC chunking
+        assert_eq!(
+            *source.requests.lock().unwrap(),
+            [MAX_ACCOUNTS_PER_REQUEST + 1]
+        );
+        assert_eq!(
+            state.balances.read().unwrap().balances.len(),
+            MAX_ACCOUNTS_PER_REQUEST + 1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_alerts_once_per_crossing() {
+        let (monitor, state, source) = monitor();
+        let mut live = state.feed.subscribe();
+        fund(&state, &source, "alpha", 7_000_000);
+        monitor.refresh().await.unwrap();
+
+        fund(&state, &source, "alpha", 4_000_000);
+        let alerts = monitor.refresh().await.unwrap();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].kind, BalanceAlertKind::LowBalance);
+        assert_eq!(alerts[0].threshold, 5_000_000);
+
+        // Still low, so no new alert until it recovers and drops again
+        fund(&state, &source, "alpha", 3_000_000);
+        assert!(monitor.refresh().await.unwrap().is_empty());
+        fund(&state, &source, "alpha", 9_000_000);
+        assert!(monitor.refresh().await.unwrap().is_empty());
+        fund(&state, &source, "alpha", 1_000_000);
+        assert_eq!(monitor.refresh().await.unwrap().len(), 1);
+
+        let mut kinds = Vec::new();
+        while let Ok(event) = live.try_recv() {
+            kinds.push(event.event_type().as_str());
+        }
+        assert_eq!(
+            kinds,
+            [
+                "balance",
+                "balance",
+                "balance_alert",
+                "balance",
+                "balance",
+                "balance",
+                "balance_alert"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unfunded_wallet_is_below_rent_exempt() {
+        let (monitor, state, source) = monitor();
+        fund(&state, &source, "empty", 0);
+
+        let alerts = monitor.refresh().await.unwrap();
+
+        let kinds: Vec<BalanceAlertKind> = alerts.iter().map(|alert| alert.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                BalanceAlertKind::LowBalance,
+                BalanceAlertKind::BelowRentExempt
+            ]
+        );
+        assert_eq!(alerts[1].threshold, RENT_EXEMPT);
+    }
+
+    #[tokio::test]
+    async fn test_removed_wallets_leave_the_cache() {
+        let (monitor, state, source) = monitor();
+        fund(&state, &source, "alpha", 7_000_000);
+        monitor.refresh().await.unwrap();
+
+        state
+            .wallets
+            .write()
+            .unwrap()
+            .remove_wallet("alpha")
+            .unwrap();
+        monitor.refresh().await.unwrap();
+
+        assert!(state.balances.read().unwrap().balances.is_empty());
+    }
+}
@@ -0,0 +1,18 @@
+use clap::Parser;
+use server::cli::{self, Cli};
+use std::process::ExitCode;
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match cli::run(&cli).await {
+        Ok(output) => {
+            println!("{}", output.render(cli.json));
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
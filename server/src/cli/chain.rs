@@ -0,0 +1,103 @@
+use super::{resolve_address, Output, Stores};
+use crate::balances::BalanceSource;
+use crate::metrics::rpc_call;
+use engine::TransactionBuilder;
+use serde::{Deserialize, Serialize};
+use solana_sdk::native_token::{sol_str_to_lamports, LAMPORTS_PER_SOL};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+use solana_system_interface::instruction;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WalletBalance {
+    pub name: String,
+    pub pubkey: Pubkey,
+    pub lamports: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TransferReceipt {
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub lamports: u64,
+    pub signature: String,
+}
+
+pub(super) async fn balance(
+    stores: &Stores<'_>,
+    names: &[String],
+    squad: Option<&str>,
+) -> Result<Output, String> {
+    stores.profile.verify_cluster().await?;
+    let wallets = stores.wallets()?;
+    let mut selected = match squad {
+        Some(squad) => wallets.squad_pubkeys(squad)?,
+        None if names.is_empty() => wallets.list_wallets(),
+        None => wallets.batch_get_pubkeys(&names.to_vec())?,
+    };
+    selected.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let pubkeys: Vec<Pubkey> = selected.iter().map(|(_, pubkey)| *pubkey).collect();
+    let lamports = stores.profile.rpc_client().balances(&pubkeys).await?;
+    let balances: Vec<WalletBalance> = selected
+        .into_iter()
+        .zip(lamports)
+        .map(|((name, pubkey), lamports)| WalletBalance {
+            name,
+            pubkey,
+            lamports,
+        })
+        .collect();
+
+    let text = balances
+        .iter()
+        .map(|balance| {
+            format!(
+                "{}\t{}\t{} SOL",
+                balance.name,
+                balance.pubkey,
+                balance.lamports as f64 / LAMPORTS_PER_SOL as f64
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    Output::new(&balances, text)
+}
+
+pub(super) async fn transfer(
+    stores: &Stores<'_>,
+    from: &str,
+    to: &str,
+    sol: &str,
+) -> Result<Output, String> {
+    let lamports =
+        sol_str_to_lamports(sol).ok_or_else(|| format!("Invalid SOL amount '{}'", sol))?;
+    let wallets = stores.wallets()?;
+    let payer = wallets.get_wallet(from)?;
+    let recipient = resolve_address(&wallets, to)?;
+
+    stores.profile.verify_cluster().await?;
+    let client = stores.profile.rpc_client();
+    let blockhash = rpc_call("getLatestBlockhash", client.get_latest_blockhash())
+        .await
+        .map_err(|e| format!("Failed to fetch blockhash: {}", e))?;
+    let transaction = TransactionBuilder::with_fees(stores.profile.fees)
+        .add_instruction(instruction::transfer(&payer.pubkey(), &recipient, lamports))
+        .set_recent_blockhash(blockhash)
+        .build(&vec![payer])?;
+    let signature = rpc_call(
+        "sendAndConfirmTransaction",
+        client.send_and_confirm_transaction(&transaction),
+    )
+    .await
+    .map_err(|e| format!("Transfer failed: {}", e))?;
+
+    let receipt = TransferReceipt {
+        from: payer.pubkey(),
+        to: recipient,
+        lamports,
+        signature: signature.to_string(),
+    };
+    let text = format!("Sent {} SOL to {}: {}", sol, recipient, signature);
+    Output::new(&receipt, text)
+}
@@ -0,0 +1,201 @@
+mod chain;
+mod squad;
+mod task;
+mod treasury;
+mod wallet;
+
+pub use squad::SquadCommand;
+pub use task::TaskCommand;
+pub use treasury::TreasuryCommand;
+pub use wallet::WalletCommand;
+
+use crate::config::{Config, Profile};
+use crate::lock::lock_store;
+use clap::{Parser, Subcommand};
+use engine::{Keystore, WalletManager};
+use serde::Serialize;
+use serde_json::Value;
+use solana_sdk::pubkey::Pubkey;
+use std::path::PathBuf;
+use std::time::Duration;
+use task_scheduler::{FileTaskStore, TaskScheduler, TaskStore};
+
+/// Operate wallets, squads and tasks without the dashboard
+#[derive(Parser, Debug)]
+#[command(name = "sols", version)]
+pub struct Cli {
+    /// TOML config file [default: $SOLS_CONFIG, else sols.toml if present]
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+    /// Config profile, e.g. localnet, devnet or mainnet
+    #[arg(long, global = true)]
+    pub profile: Option<String>,
+    /// Wallet and squad keystore [default: the profile's]
+    #[arg(long, global = true)]
+    pub keystore: Option<PathBuf>,
+    /// Task store, one JSON task per line [default: the profile's]
+    #[arg(long, global = true)]
+    pub tasks: Option<PathBuf>,
+    /// [default: the profile's]
+    #[arg(long, global = true)]
+    pub rpc_url: Option<String>,
+    /// Print JSON instead of text, for scripting
+    #[arg(long, global = true)]
+    pub json: bool,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Manage keystore wallets
+    #[command(subcommand)]
+    Wallet(WalletCommand),
+    /// Group wallets into squads
+    #[command(subcommand)]
+    Squad(SquadCommand),
+    /// Schedule and inspect tasks in the task store
+    #[command(subcommand)]
+    Task(TaskCommand),
+    /// Fund squads from a treasury wallet
+    #[command(subcommand)]
+    Treasury(TreasuryCommand),
+    /// Show SOL balances, of every wallet unless some are named
+    Balance {
+        wallets: Vec<String>,
+        /// Only the squad's wallets
+        #[arg(long, conflicts_with = "wallets")]
+        squad: Option<String>,
+    },
+    /// Send SOL from a wallet right away and wait for confirmation
+    Transfer {
+        from: String,
+        /// Wallet name or address
+        to: String,
+        /// Amount in SOL, e.g. 0.25
+        sol: String,
+    },
+}
+
+/// What a command printed: JSON for `--json`, text otherwise
+#[derive(Debug, Clone, PartialEq)]
+pub struct Output {
+    pub json: Value,
+    pub text: String,
+}
+
+impl Output {
+    pub fn new(value: &impl Serialize, text: impl Into<String>) -> Result<Self, String> {
+        let json = serde_json::to_value(value)
+            .map_err(|e| format!("Failed to serialize output: {}", e))?;
+        Ok(Self {
+            json,
+            text: text.into(),
+        })
+    }
+
+    pub fn render(&self, json: bool) -> String {
+        if json {
+            serde_json::to_string_pretty(&self.json).unwrap_or_default()
+        } else {
+            self.text.clone()
+        }
+    }
+}
+
+impl Cli {
+    /// The config with the environment and then these flags applied, validated
+    pub fn config(&self) -> Result<Config, String> {
+        let mut config = Config::from_env(self.config.as_deref())?;
+        if let Some(profile) = &self.profile {
+            config.select(profile)?;
+        }
+        let profile = config.active_mut()?;
+        if let Some(keystore) = &self.keystore {
+            profile.keystore = keystore.clone();
+        }
+        if let Some(tasks) = &self.tasks {
+            profile.tasks = tasks.clone();
+        }
+        if let Some(rpc_url) = &self.rpc_url {
+            profile.rpc_url = rpc_url.clone();
+        }
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+pub async fn run(cli: &Cli) -> Result<Output, String> {
+    let config = cli.config()?;
+    let profile = config.active()?;
+    let stores = Stores {
+        keystore: profile.keystore(),
+        tasks: profile.task_store(),
+        profile,
+    };
+    match &cli.command {
+        Command::Wallet(command) => wallet::run(command, &stores),
+        Command::Squad(command) => squad::run(command, &stores),
+        Command::Task(command) => task::run(command, &stores),
+        Command::Treasury(command) => treasury::run(command, &stores).await,
+        Command::Balance { wallets, squad } => {
+            chain::balance(&stores, wallets, squad.as_deref()).await
+        }
+        Command::Transfer { from, to, sol } => chain::transfer(&stores, from, to, sol).await,
+    }
+}
+
+/// How long a mutating command waits for another to release a store. A running
+/// server never does, so commands give up rather than hang.
+const STORE_LOCK_WAIT: Duration = Duration::from_secs(2);
+
+/// The persistent stores every command reads, and mutating commands write back
+struct Stores<'a> {
+    keystore: Keystore,
+    tasks: FileTaskStore,
+    /// Cluster settings, including the retry policy of scheduled tasks
+    profile: &'a Profile,
+}
+
+impl Stores<'_> {
+    fn wallets(&self) -> Result<WalletManager, String> {
+        self.keystore.load()
+    }
+
+    fn update_wallets<T>(
+        &self,
+        change: impl FnOnce(&mut WalletManager) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let _lock = lock_store(self.keystore.path(), STORE_LOCK_WAIT)?;
+        let mut wallets = self.keystore.load()?;
+        let changed = change(&mut wallets)?;
+        self.keystore.save(&wallets)?;
+        Ok(changed)
+    }
+
+    fn scheduler(&self) -> Result<TaskScheduler, String> {
+        let mut scheduler = self.profile.task_scheduler();
+        scheduler.load(self.tasks.load()?)?;
+        Ok(scheduler)
+    }
+
+    fn update_scheduler<T>(
+        &self,
+        change: impl FnOnce(&mut TaskScheduler) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let _lock = lock_store(self.tasks.path(), STORE_LOCK_WAIT)?;
+        let mut scheduler = self.scheduler()?;
+        let changed = change(&mut scheduler)?;
+        self.tasks.save(&scheduler.snapshot())?;
+        Ok(changed)
+    }
+}
+
+/// A wallet name from the keystore, or else a base58 address
+fn resolve_address(wallets: &WalletManager, wallet_or_address: &str) -> Result<Pubkey, String> {
+    wallets.get_pubkey(wallet_or_address).or_else(|_| {
+        wallet_or_address
+            .parse()
+            .map_err(|_| format!("'{}' is neither a wallet nor an address", wallet_or_address))
+    })
+}
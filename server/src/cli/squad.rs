@@ -0,0 +1,62 @@
+use super::{Output, Stores};
+use clap::Subcommand;
+use engine::wallet::Squad;
+
+#[derive(Subcommand, Debug)]
+pub enum SquadCommand {
+    Create {
+        name: String,
+        /// The protocol or airdrop campaign the squad farms
+        #[arg(long)]
+        campaign: Option<String>,
+    },
+    /// Add wallets to a squad
+    Add {
+        name: String,
+        #[arg(required = true)]
+        wallets: Vec<String>,
+    },
+    List,
+}
+
+pub(super) fn run(command: &SquadCommand, stores: &Stores<'_>) -> Result<Output, String> {
+    match command {
+        SquadCommand::Create { name, campaign } => {
+            let squad = stores.update_wallets(|wallets| {
+                wallets
+                    .create_squad(name.clone(), campaign.clone())
+                    .cloned()
+            })?;
+            Output::new(&squad, format!("Created squad '{}'", name))
+        }
+        SquadCommand::Add { name, wallets } => {
+            let squad = stores.update_wallets(|manager| {
+                manager.assign_to_squad(name, wallets)?;
+                manager.get_squad(name).cloned()
+            })?;
+            let text = format!("Squad '{}' now has {} wallets", name, squad.wallets.len());
+            Output::new(&squad, text)
+        }
+        SquadCommand::List => {
+            let squads: Vec<Squad> = stores
+                .wallets()?
+                .list_squads()
+                .into_iter()
+                .cloned()
+                .collect();
+            let text = squads
+                .iter()
+                .map(|squad| {
+                    format!(
+                        "{}\t{}\t{} wallets",
+                        squad.name,
+                        squad.campaign.as_deref().unwrap_or("-"),
+                        squad.wallets.len()
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            Output::new(&squads, text)
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use super::{resolve_address, Output, Stores};
+use crate::api::ScheduleTaskRequest;
+use clap::Subcommand;
+use serde::de::{value::Error as ValueError, DeserializeOwned, IntoDeserializer};
+use task_scheduler::{Task, TaskPriority, TaskQuery, TaskStatus, DEFAULT_PAGE_SIZE};
+
+#[derive(Subcommand, Debug)]
+pub enum TaskCommand {
+    Schedule {
+        /// `TransactionParams` as JSON, e.g. {"type":"Transfer",...}
+        #[arg(long)]
+        params: String,
+        #[arg(long, value_parser = parse_variant::<TaskPriority>)]
+        priority: Option<TaskPriority>,
+        /// Squad or campaign the task belongs to
+        #[arg(long)]
+        tag: Option<String>,
+        /// ID of a task that must complete first; repeatable
+        #[arg(long = "depends-on")]
+        dependencies: Vec<String>,
+        #[arg(long)]
+        idempotency_key: Option<String>,
+        #[arg(long)]
+        timeout_ms: Option<u64>,
+    },
+    List {
+        /// Any of these statuses, comma-separated
+        #[arg(long, value_delimiter = ',', value_parser = parse_variant::<TaskStatus>)]
+        status: Vec<TaskStatus>,
+        #[arg(long)]
+        tag: Option<String>,
+        /// Fee payer, as a wallet name or address
+        #[arg(long)]
+        wallet: Option<String>,
+        #[arg(long, default_value_t = 0)]
+        offset: usize,
+        #[arg(long, default_value_t = DEFAULT_PAGE_SIZE)]
+        limit: usize,
+    },
+    /// Cancel a task and everything waiting on it
+    Cancel { id: String },
+    /// Replay a dead-lettered task
+    Retry { id: String },
+}
+
+pub(super) fn run(command: &TaskCommand, stores: &Stores<'_>) -> Result<Output, String> {
+    match command {
+        TaskCommand::Schedule {
+            params,
+            priority,
+            tag,
+            dependencies,
+            idempotency_key,
+            timeout_ms,
+        } => {
+            let request = ScheduleTaskRequest {
+                params: serde_json::from_str(params)
+                    .map_err(|e| format!("Invalid params: {}", e))?,
+                priority: *priority,
+                tag: tag.clone(),
+                dependencies: dependencies.clone(),
+                idempotency_key: idempotency_key.clone(),
+                timeout_ms: *timeout_ms,
+            };
+            let task = request.into_task()?;
+            let task = stores.update_scheduler(|scheduler| Ok(scheduler.schedule_task(task)?))?;
+            Output::new(&task, format!("Scheduled {}", task.id()))
+        }
+        TaskCommand::List {
+            status,
+            tag,
+            wallet,
+            offset,
+            limit,
+        } => {
+            let mut query = TaskQuery::new().page(*offset, *limit);
+            query.statuses = status.clone();
+            query.tag = tag.clone();
+            if let Some(wallet) = wallet {
+                query = query.wallet(resolve_address(&stores.wallets()?, wallet)?);
+            }
+            let page = stores.scheduler()?.query(&query);
+            let text = page
+                .tasks
+                .iter()
+                .map(describe)
+                .chain(std::iter::once(format!(
+                    "{} of {} tasks",
+                    page.tasks.len(),
+                    page.total
+                )))
+                .collect::<Vec<_>>()
+                .join("\n");
+            Output::new(&page, text)
+        }
+        TaskCommand::Cancel { id } => {
+            stores.update_scheduler(|scheduler| Ok(scheduler.cancel_task(id)?))?;
+            Output::new(&id, format!("Cancelled {}", id))
+        }
+        TaskCommand::Retry { id } => {
+            stores.update_scheduler(|scheduler| Ok(scheduler.replay_dead_letter(id)?))?;
+            Output::new(&id, format!("Requeued {}", id))
+        }
+    }
+}
+
+fn describe(task: &Task) -> String {
+    let params_type = task
+        .params()
+        .map(|params| params.type_name())
+        .unwrap_or("?");
+    format!(
+        "{}\t{:?}\t{:?}\t{}\t{}",
+        task.id(),
+        task.status(),
+        task.priority(),
+        params_type,
+        task.tag().unwrap_or("-")
+    )
+}
+
+/// Parses a unit enum variant by its serialized name, e.g. "Pending"
+fn parse_variant<T: DeserializeOwned>(value: &str) -> Result<T, String> {
+    T::deserialize(value.into_deserializer()).map_err(|e: ValueError| e.to_string())
+}
@@ -0,0 +1,103 @@
+use super::{Output, Stores};
+use crate::api::TreasuryInfo;
+use crate::balances::default_rent_exempt_minimum;
+use crate::treasury;
+use clap::Subcommand;
+use engine::TopUpPolicy;
+use solana_sdk::native_token::{sol_str_to_lamports, LAMPORTS_PER_SOL};
+
+#[derive(Subcommand, Debug)]
+pub enum TreasuryCommand {
+    /// Fund top-ups from this wallet
+    Set { wallet: String },
+    /// Show the treasury wallet and every squad's top-up policy
+    Show,
+    /// Keep a squad's wallets funded: top up to the target whenever one falls below the minimum
+    Policy {
+        squad: String,
+        /// Balance to top up to, in SOL
+        #[arg(long)]
+        target_sol: String,
+        /// Balance below which a wallet is topped up, in SOL
+        #[arg(long)]
+        min_sol: String,
+    },
+    /// Stop topping up a squad's wallets
+    ClearPolicy { squad: String },
+    /// Schedule transfers from the treasury to every under-funded squad wallet
+    TopUp,
+}
+
+pub(super) async fn run(command: &TreasuryCommand, stores: &Stores<'_>) -> Result<Output, String> {
+    match command {
+        TreasuryCommand::Set { wallet } => {
+            stores.update_wallets(|wallets| wallets.set_treasury(wallet))?;
+            Output::new(&wallet, format!("Treasury is now '{}'", wallet))
+        }
+        TreasuryCommand::Show => {
+            let summary = TreasuryInfo::of(&stores.wallets()?);
+            let mut lines = vec![format!(
+                "Treasury: {}",
+                summary.wallet.as_deref().unwrap_or("-")
+            )];
+            lines.extend(summary.policies.iter().map(|(squad, policy)| {
+                format!(
+                    "{}\tmin {} SOL\ttarget {} SOL",
+                    squad,
+                    policy.min_lamports as f64 / LAMPORTS_PER_SOL as f64,
+                    policy.target_lamports as f64 / LAMPORTS_PER_SOL as f64
+                )
+            }));
+            Output::new(&summary, lines.join("\n"))
+        }
+        TreasuryCommand::Policy {
+            squad,
+            target_sol,
+            min_sol,
+        } => {
+            let policy = TopUpPolicy::new(parse_sol(target_sol)?, parse_sol(min_sol)?)?;
+            policy.check_rent_exempt(default_rent_exempt_minimum())?;
+            let squad = stores.update_wallets(|wallets| {
+                wallets.set_squad_top_up(squad, Some(policy))?;
+                wallets.get_squad(squad).cloned()
+            })?;
+            let text = format!(
+                "Squad '{}' wallets below {} SOL will be topped up to {} SOL",
+                squad.name, min_sol, target_sol
+            );
+            Output::new(&squad, text)
+        }
+        TreasuryCommand::ClearPolicy { squad } => {
+            let squad = stores.update_wallets(|wallets| {
+                wallets.set_squad_top_up(squad, None)?;
+                wallets.get_squad(squad).cloned()
+            })?;
+            Output::new(
+                &squad,
+                format!("Squad '{}' is no longer topped up", squad.name),
+            )
+        }
+        TreasuryCommand::TopUp => {
+            let wallets = stores.wallets()?;
+            stores.profile.verify_cluster().await?;
+            let balances = treasury::fetch_balances(&wallets, &stores.profile.rpc_client()).await?;
+            let report = stores.update_scheduler(|scheduler| {
+                let in_flight = treasury::in_flight_top_ups(scheduler, &wallets);
+                let plan = balances.plan(&wallets, &stores.profile.fees, &in_flight)?;
+                treasury::schedule_top_ups(scheduler, &plan, balances.taken_at)
+                    .map_err(str::to_string)
+            })?;
+            let text = format!(
+                "Scheduled {} transfers of {} SOL in {} tasks",
+                report.transfers,
+                report.total_lamports as f64 / LAMPORTS_PER_SOL as f64,
+                report.tasks.len()
+            );
+            Output::new(&report, text)
+        }
+    }
+}
+
+fn parse_sol(sol: &str) -> Result<u64, String> {
+    sol_str_to_lamports(sol).ok_or_else(|| format!("Invalid SOL amount '{}'", sol))
+}
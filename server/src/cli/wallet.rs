@@ -0,0 +1,108 @@
+use super::{Output, Stores};
+use crate::api::{ExportedWallet, WalletInfo};
+use clap::Subcommand;
+use solana_sdk::signature::Signer;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Subcommand, Debug)]
+pub enum WalletCommand {
+    Generate {
+        name: String,
+    },
+    /// Import a Solana CLI keypair file
+    Import {
+        name: String,
+        keypair_file: PathBuf,
+    },
+    List,
+    /// Print the keypair in Solana CLI format, or write it to a file
+    Export {
+        name: String,
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    Remove {
+        name: String,
+    },
+}
+
+pub(super) fn run(command: &WalletCommand, stores: &Stores<'_>) -> Result<Output, String> {
+    match command {
+        WalletCommand::Generate { name } => {
+            let pubkey = stores.update_wallets(|wallets| wallets.generate_wallet(name.clone()))?;
+            let wallet = WalletInfo {
+                name: name.clone(),
+                pubkey,
+            };
+            Output::new(&wallet, format!("Generated '{}': {}", name, pubkey))
+        }
+        WalletCommand::Import { name, keypair_file } => {
+            let contents = fs::read_to_string(keypair_file)
+                .map_err(|e| format!("Failed to read {}: {}", keypair_file.display(), e))?;
+            let bytes: Vec<u8> = serde_json::from_str(&contents)
+                .map_err(|e| format!("Invalid keypair file {}: {}", keypair_file.display(), e))?;
+            let pubkey =
+                stores.update_wallets(|wallets| wallets.import_wallet(name.clone(), &bytes))?;
+            let wallet = WalletInfo {
+                name: name.clone(),
+                pubkey,
+            };
+            Output::new(&wallet, format!("Imported '{}': {}", name, pubkey))
+        }
+        WalletCommand::List => {
+            let mut wallets: Vec<WalletInfo> = stores
+                .wallets()?
+                .list_wallets()
+                .into_iter()
+                .map(|(name, pubkey)| WalletInfo { name, pubkey })
+                .collect();
+            wallets.sort_by(|a, b| a.name.cmp(&b.name));
+            let text = wallets
+                .iter()
+                .map(|wallet| format!("{}\t{}", wallet.name, wallet.pubkey))
+                .collect::<Vec<_>>()
+                .join("\n");
+            Output::new(&wallets, text)
+        }
+        WalletCommand::Export { name, out } => {
+            let wallets = stores.wallets()?;
+            let keypair = wallets.get_wallet(name)?;
+            let exported = ExportedWallet {
+                name: name.clone(),
+                pubkey: keypair.pubkey(),
+                keypair: keypair.to_bytes().to_vec(),
+            };
+            let keypair_json = serde_json::to_string(&exported.keypair)
+                .map_err(|e| format!("Failed to serialize keypair: {}", e))?;
+            match out {
+                Some(path) => {
+                    write_private(path, &keypair_json)?;
+                    let text = format!("Wrote keypair for '{}' to {}", name, path.display());
+                    Output::new(&exported, text)
+                }
+                None => Output::new(&exported, keypair_json),
+            }
+        }
+        WalletCommand::Remove { name } => {
+            stores.update_wallets(|wallets| wallets.remove_wallet(name))?;
+            Output::new(&name, format!("Removed '{}'", name))
+        }
+    }
+}
+
+/// Keypair files hold secrets, so on Unix only the owner may read them
+fn write_private(path: &Path, contents: &str) -> Result<(), String> {
+    use std::io::Write;
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+        .open(path)
+        .and_then(|mut file| file.write_all(contents.as_bytes()))
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
@@ -0,0 +1,803 @@
+use crate::balances::BalanceMonitorConfig;
+use crate::metrics::rpc_call;
+use engine::{Cluster, FeeSettings, Keystore};
+use serde::{Deserialize, Serialize};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_commitment_config::CommitmentConfig;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fs;
+use std::io::ErrorKind;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use task_scheduler::{
+    FileTaskStore, TaskScheduler, WorkerPoolConfig, DEFAULT_EXECUTION_TIMEOUT, DEFAULT_RETENTION,
+};
+use tokio::sync::Semaphore;
+
+/// Read when it exists and no other path is given
+pub const DEFAULT_CONFIG_PATH: &str = "sols.toml";
+pub const DEFAULT_PROFILE: &str = "devnet";
+pub const DEFAULT_KEYSTORE_PATH: &str = "keystore.json";
+/// Shared by the server and the CLI, one JSON task per line
+pub const DEFAULT_TASKS_PATH: &str = "tasks.jsonl";
+pub const DEFAULT_LISTEN_ADDR: &str = "127.0.0.1:8080";
+pub const DEFAULT_TOKENS_PATH: &str = "tokens.json";
+/// Environment variables starting with this override the config file,
+/// e.g. `SOLS_PROFILE=mainnet` or `SOLS_RPC_URL=...`
+pub const ENV_PREFIX: &str = "SOLS_";
+
+/// How settled a block must be before RPC reads and confirmations count it
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Commitment {
+    Processed,
+    #[default]
+    Confirmed,
+    Finalized,
+}
+
+impl Commitment {
+    pub fn config(&self) -> CommitmentConfig {
+        match self {
+            Commitment::Processed => CommitmentConfig::processed(),
+            Commitment::Confirmed => CommitmentConfig::confirmed(),
+            Commitment::Finalized => CommitmentConfig::finalized(),
+        }
+    }
+}
+
+impl FromStr for Commitment {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "processed" => Ok(Commitment::Processed),
+            "confirmed" => Ok(Commitment::Confirmed),
+            "finalized" => Ok(Commitment::Finalized),
+            _ => Err(format!("Unknown commitment '{}'", value)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct SchedulerSettings {
+    /// Workers pulling from the queue
+    pub concurrency: usize,
+    /// Executions talking to the RPC endpoint at once
+    pub rpc_concurrency: usize,
+    /// How long an attempt may run when its task does not set its own timeout
+    pub execution_timeout_secs: u64,
+    /// How long finished tasks stay queryable before they are forgotten
+    pub retention_secs: u64,
+}
+
+impl Default for SchedulerSettings {
+    fn default() -> Self {
+        let pool = WorkerPoolConfig::default();
+        Self {
+            concurrency: pool.concurrency,
+            rpc_concurrency: pool.rpc_limit.available_permits(),
+            execution_timeout_secs: DEFAULT_EXECUTION_TIMEOUT.as_secs(),
+            retention_secs: DEFAULT_RETENTION.as_secs(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt before a task is dead-lettered
+    pub max_retries: u8,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self { max_retries: 3 }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct BalanceSettings {
+    /// How often the balance monitor fetches every wallet's balance
+    pub interval_secs: u64,
+    /// Wallets below this are alerted on; 0.01 SOL by default
+    pub low_balance_lamports: u64,
+}
+
+impl Default for BalanceSettings {
+    fn default() -> Self {
+        let config = BalanceMonitorConfig::default();
+        Self {
+            interval_secs: config.interval.as_secs(),
+            low_balance_lamports: config.low_balance,
+        }
+    }
+}
+
+/// How the server writes its logs
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// Human-readable, multi-line
+    #[default]
+    Pretty,
+    /// One JSON object per line, for log collectors
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "pretty" => Ok(LogFormat::Pretty),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("Unknown log format '{}'", value)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogSettings {
+    pub format: LogFormat,
+    /// Which spans and events to keep, in `RUST_LOG` syntax, e.g. `info,task_scheduler=debug`
+    pub filter: String,
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self {
+            format: LogFormat::default(),
+            filter: "info".to_string(),
+        }
+    }
+}
+
+/// Where the HTTP API listens and keeps its access tokens
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerSettings {
+    /// An `ip:port` to bind, e.g. `0.0.0.0:8080`
+    pub listen_addr: String,
+    pub tokens: PathBuf,
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        Self {
+            listen_addr: DEFAULT_LISTEN_ADDR.to_string(),
+            tokens: PathBuf::from(DEFAULT_TOKENS_PATH),
+        }
+    }
+}
+
+impl ServerSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        self.listen_addr
+            .parse::<SocketAddr>()
+            .map_err(|e| format!("listen_addr '{}': {}", self.listen_addr, e))?;
+        if self.tokens.as_os_str().is_empty() {
+            return Err("tokens path must not be empty".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Everything that differs between clusters
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    /// Checked against the RPC node's genesis hash, and stamped on keystores,
+    /// squads and tasks so they are never used against another cluster
+    pub cluster: Cluster,
+    pub rpc_url: String,
+    pub ws_url: String,
+    #[serde(default)]
+    pub commitment: Commitment,
+    #[serde(default)]
+    pub fees: FeeSettings,
+    #[serde(default)]
+    pub scheduler: SchedulerSettings,
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    #[serde(default)]
+    pub balances: BalanceSettings,
+    #[serde(default = "default_keystore")]
+    pub keystore: PathBuf,
+    #[serde(default = "default_tasks")]
+    pub tasks: PathBuf,
+}
+
+fn default_keystore() -> PathBuf {
+    PathBuf::from(DEFAULT_KEYSTORE_PATH)
+}
+
+fn default_tasks() -> PathBuf {
+    PathBuf::from(DEFAULT_TASKS_PATH)
+}
+
+impl Profile {
+    pub fn new(cluster: Cluster, rpc_url: impl Into<String>, ws_url: impl Into<String>) -> Self {
+        Self {
+            cluster,
+            rpc_url: rpc_url.into(),
+            ws_url: ws_url.into(),
+            commitment: Commitment::default(),
+            fees: FeeSettings::default(),
+            scheduler: SchedulerSettings::default(),
+            retry: RetryPolicy::default(),
+            balances: BalanceSettings::default(),
+            keystore: default_keystore(),
+            tasks: default_tasks(),
+        }
+    }
+
+    /// A local `solana-test-validator`
+    pub fn localnet() -> Self {
+        Self::new(
+            Cluster::Localnet,
+            "http://127.0.0.1:8899",
+            "ws://127.0.0.1:8900",
+        )
+    }
+
+    pub fn devnet() -> Self {
+        Self::new(
+            Cluster::Devnet,
+            "https://api.devnet.solana.com",
+            "wss://api.devnet.solana.com",
+        )
+    }
+
+    pub fn mainnet() -> Self {
+        let mut profile = Self::new(
+            Cluster::Mainnet,
+            "https://api.mainnet-beta.solana.com",
+            "wss://api.mainnet-beta.solana.com",
+        );
+        profile.commitment = Commitment::Finalized;
+        profile
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !has_scheme(&self.rpc_url, &["http://", "https://"]) {
+            return Err(format!("rpc_url '{}' must be an http(s) URL", self.rpc_url));
+        }
+        if !has_scheme(&self.ws_url, &["ws://", "wss://"]) {
+            return Err(format!("ws_url '{}' must be a ws(s) URL", self.ws_url));
+        }
+        if self.scheduler.concurrency == 0 {
+            return Err("scheduler.concurrency must be at least 1".to_string());
+        }
+        if self.scheduler.rpc_concurrency == 0 {
+            return Err("scheduler.rpc_concurrency must be at least 1".to_string());
+        }
+        if self.scheduler.execution_timeout_secs == 0 {
+            return Err("scheduler.execution_timeout_secs must be at least 1".to_string());
+        }
+        if self.balances.interval_secs == 0 {
+            return Err("balances.interval_secs must be at least 1".to_string());
+        }
+        if self.keystore.as_os_str().is_empty() {
+            return Err("keystore path must not be empty".to_string());
+        }
+        if self.tasks.as_os_str().is_empty() {
+            return Err("tasks path must not be empty".to_string());
+        }
+        self.fees.validate()
+    }
+
+    pub fn rpc_client(&self) -> RpcClient {
+        RpcClient::new_with_commitment(self.rpc_url.clone(), self.commitment.config())
+    }
+
+    /// Asks the RPC node for its genesis hash and checks it belongs to this profile's cluster
+    pub async fn verify_cluster(&self) -> Result<(), String> {
+        let client = self.rpc_client();
+        let genesis_hash = rpc_call("getGenesisHash", client.get_genesis_hash())
+            .await
+            .map_err(|e| format!("Failed to fetch genesis hash from {}: {}", self.rpc_url, e))?;
+        self.cluster
+            .verify_genesis_hash(&genesis_hash)
+            .map_err(|e| format!("{} ({})", e, self.rpc_url))
+    }
+
+    /// The keystore, refusing keys and squads of other clusters
+    pub fn keystore(&self) -> Keystore {
+        Keystore::new(&self.keystore).with_cluster(self.cluster)
+    }
+
+    /// Where unfinished tasks are kept between runs
+    pub fn task_store(&self) -> FileTaskStore {
+        FileTaskStore::new(&self.tasks)
+    }
+
+    /// A scheduler applying this profile's cluster, timeout and retry policy
+    pub fn task_scheduler(&self) -> TaskScheduler {
+        TaskScheduler::new()
+            .with_cluster(self.cluster)
+            .with_default_timeout(Duration::from_secs(self.scheduler.execution_timeout_secs))
+            .with_max_retries(self.retry.max_retries)
+            .with_retention(Duration::from_secs(self.scheduler.retention_secs))
+    }
+
+    pub fn balance_monitor_config(&self) -> BalanceMonitorConfig {
+        BalanceMonitorConfig {
+            interval: Duration::from_secs(self.balances.interval_secs),
+            low_balance: self.balances.low_balance_lamports,
+        }
+    }
+
+    pub fn worker_pool_config(&self) -> WorkerPoolConfig {
+        WorkerPoolConfig {
+            concurrency: self.scheduler.concurrency,
+            rpc_limit: Arc::new(Semaphore::new(self.scheduler.rpc_concurrency)),
+            endpoint: Some(self.rpc_url.clone()),
+            ..WorkerPoolConfig::default()
+        }
+    }
+}
+
+fn has_scheme(url: &str, schemes: &[&str]) -> bool {
+    schemes.iter().any(|scheme| {
+        url.strip_prefix(scheme)
+            .is_some_and(|rest| !rest.is_empty())
+    })
+}
+
+/// Named profiles and which one is active. The localnet, devnet and mainnet
+/// profiles are built in; a config file may replace them or add others.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Config {
+    pub profile: String,
+    pub profiles: BTreeMap<String, Profile>,
+    /// Shared by every profile
+    pub logging: LogSettings,
+    pub server: ServerSettings,
+}
+
+/// The config file as written; everything in it is optional
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    profile: Option<String>,
+    profiles: BTreeMap<String, Profile>,
+    logging: LogSettings,
+    server: ServerSettings,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Config {
+    /// The built-in profiles, with devnet active
+    pub fn new() -> Self {
+        let profiles = [
+            ("localnet", Profile::localnet()),
+            ("devnet", Profile::devnet()),
+            ("mainnet", Profile::mainnet()),
+        ]
+        .into_iter()
+        .map(|(name, profile)| (name.to_string(), profile))
+        .collect();
+        Self {
+            profile: DEFAULT_PROFILE.to_string(),
+            profiles,
+            logging: LogSettings::default(),
+            server: ServerSettings::default(),
+        }
+    }
+
+    /// Layers a TOML config over the built-in profiles
+    pub fn from_toml(contents: &str) -> Result<Self, String> {
+        let file: ConfigFile =
+            toml::from_str(contents).map_err(|e| format!("Invalid config: {}", e))?;
+        let mut config = Self::new();
+        config.profiles.extend(file.profiles);
+        config.logging = file.logging;
+        config.server = file.server;
+        if let Some(profile) = file.profile {
+            config.profile = profile;
+        }
+        Ok(config)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read config {}: {}", path.display(), e))?;
+        Self::from_toml(&contents)
+    }
+
+    /// Loads `path`, else `SOLS_CONFIG`, else `sols.toml` if present, else the
+    /// built-in profiles, then applies `SOLS_*` overrides. Call `validate` before use.
+    pub fn from_env(path: Option<&Path>) -> Result<Self, String> {
+        let explicit = path
+            .map(Path::to_path_buf)
+            .or_else(|| std::env::var_os(format!("{}CONFIG", ENV_PREFIX)).map(PathBuf::from));
+        let config = match explicit {
+            Some(path) => Self::load(path)?,
+            None => match fs::read_to_string(DEFAULT_CONFIG_PATH) {
+                Ok(contents) => Self::from_toml(&contents)?,
+                Err(e) if e.kind() == ErrorKind::NotFound => Self::new(),
+                Err(e) => return Err(format!("Failed to read config: {}", e)),
+            },
+        };
+        config.with_env(std::env::vars())
+    }
+
+    /// Applies `SOLS_*` overrides: `SOLS_PROFILE` first, then the shared logging and
+    /// server settings, the rest to the profile `SOLS_PROFILE` selects
+    pub fn with_env(
+        mut self,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Self, String> {
+        let vars: BTreeMap<String, String> = vars
+            .into_iter()
+            .filter_map(|(key, value)| Some((key.strip_prefix(ENV_PREFIX)?.to_string(), value)))
+            .collect();
+        if let Some(profile) = vars.get("PROFILE") {
+            self.profile = profile.clone();
+        }
+        if let Some(format) = vars.get("LOG_FORMAT") {
+            self.logging.format = parse_var("LOG_FORMAT", format)?;
+        }
+        if let Some(filter) = vars.get("LOG") {
+            self.logging.filter = filter.clone();
+        }
+        if let Some(addr) = vars.get("LISTEN_ADDR") {
+            self.server.listen_addr = addr.clone();
+        }
+        if let Some(tokens) = vars.get("TOKENS") {
+            self.server.tokens = PathBuf::from(tokens);
+        }
+        let profile = self.active_mut()?;
+        for (key, value) in &vars {
+            match key.as_str() {
+                "CLUSTER" => profile.cluster = parse_var(key, value)?,
+                "RPC_URL" => profile.rpc_url = value.clone(),
+                "WS_URL" => profile.ws_url = value.clone(),
+                "COMMITMENT" => profile.commitment = parse_var(key, value)?,
+                "COMPUTE_UNIT_PRICE" => {
+                    profile.fees.compute_unit_price = Some(parse_var(key, value)?)
+                }
+                "COMPUTE_UNIT_LIMIT" => {
+                    profile.fees.compute_unit_limit = Some(parse_var(key, value)?)
+                }
+                "CONCURRENCY" => profile.scheduler.concurrency = parse_var(key, value)?,
+                "RPC_CONCURRENCY" => profile.scheduler.rpc_concurrency = parse_var(key, value)?,
+                "EXECUTION_TIMEOUT_SECS" => {
+                    profile.scheduler.execution_timeout_secs = parse_var(key, value)?
+                }
+                "RETENTION_SECS" => profile.scheduler.retention_secs = parse_var(key, value)?,
+                "MAX_RETRIES" => profile.retry.max_retries = parse_var(key, value)?,
+                "BALANCE_INTERVAL_SECS" => profile.balances.interval_secs = parse_var(key, value)?,
+                "LOW_BALANCE_LAMPORTS" => {
+                    profile.balances.low_balance_lamports = parse_var(key, value)?
+                }
+                "KEYSTORE" => profile.keystore = PathBuf::from(value),
+                "TASKS" => profile.tasks = PathBuf::from(value),
+                // SOLS_CONFIG, SOLS_PROFILE and the shared settings are handled above
+                _ => {}
+            }
+        }
+        Ok(self)
+    }
+
+    /// Switches to another named profile
+    pub fn select(&mut self, profile: &str) -> Result<(), String> {
+        if !self.profiles.contains_key(profile) {
+            return Err(self.unknown_profile(profile));
+        }
+        self.profile = profile.to_string();
+        Ok(())
+    }
+
+    pub fn active(&self) -> Result<&Profile, String> {
+        self.profiles
+            .get(&self.profile)
+            .ok_or_else(|| self.unknown_profile(&self.profile))
+    }
+
+    pub fn active_mut(&mut self) -> Result<&mut Profile, String> {
+        let error = self.unknown_profile(&self.profile);
+        self.profiles.get_mut(&self.profile).ok_or(error)
+    }
+
+    /// Checks the server settings and the active profile; the other profiles are
+    /// only checked once selected
+    pub fn validate(&self) -> Result<(), String> {
+        self.server
+            .validate()
+            .map_err(|e| format!("Server: {}", e))?;
+        self.active()?
+            .validate()
+            .map_err(|e| format!("Profile '{}': {}", self.profile, e))
+    }
+
+    fn unknown_profile(&self, profile: &str) -> String {
+        let known: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+        format!(
+            "Unknown profile '{}', expected one of: {}",
+            profile,
+            known.join(", ")
+        )
+    }
+}
+
+fn parse_var<T: FromStr>(key: &str, value: &str) -> Result<T, String>
+where
+    T::Err: Display,
+{
+    value
+        .parse()
+        .map_err(|e| format!("Invalid {}{} '{}': {}", ENV_PREFIX, key, value, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_builtin_profiles_are_valid() {
+        let mut config = Config::new();
+
+        for name in ["localnet", "devnet", "mainnet"] {
+            config.select(name).unwrap();
+            config.validate().unwrap();
+        }
+        assert_eq!(Config::new().profile, "devnet");
+    }
+
+    #[test]
+    fn test_file_overrides_and_adds_profiles() {
+        let config = Config::from_toml(
+            r#"
+            profile = "staging"
+
+            [profiles.staging]
+            cluster = "mainnet"
+            rpc_url = "https://rpc.example.com"
+            ws_url = "wss://rpc.example.com"
+            commitment = "finalized"
+            keystore = "/secrets/staging.json"
+            tasks = "/var/sols/staging.jsonl"
+
+            [profiles.staging.fees]
+            compute_unit_price = 5000
+
+            [profiles.staging.scheduler]
+            concurrency = 2
+
+            [profiles.staging.retry]
+            max_retries = 5
+            "#,
+        )
+        .unwrap();
+
+        let profile = config.active().unwrap();
+        assert_eq!(profile.cluster, Cluster::Mainnet);
+        assert_eq!(profile.rpc_url, "https://rpc.example.com");
+        assert_eq!(profile.commitment, Commitment::Finalized);
+        assert_eq!(profile.fees.compute_unit_price, Some(5000));
+        assert_eq!(profile.scheduler.concurrency, 2);
+        assert_eq!(
+            profile.scheduler.rpc_concurrency,
+            SchedulerSettings::default().rpc_concurrency
+        );
+        assert_eq!(profile.retry.max_retries, 5);
+        assert_eq!(profile.keystore, PathBuf::from("/secrets/staging.json"));
+        assert_eq!(profile.tasks, PathBuf::from("/var/sols/staging.jsonl"));
+        // Built-in profiles survive alongside
+        assert_eq!(config.profiles["mainnet"], Profile::mainnet());
+    }
+
+    #[test]
+    fn test_unknown_keys_are_rejected() {
+        let result = Config::from_toml(
+            r#"
+            [profiles.devnet]
+            cluster = "devnet"
+            rpc_url = "https://api.devnet.solana.com"
+            ws_url = "wss://api.devnet.solana.com"
+            concurency = 4
+            "#,
+        );
+
+        assert!(result.unwrap_err().contains("concurency"));
+    }
+
+    #[test]
+    fn test_env_selects_profile_then_overrides_it() {
+        let config = Config::new()
+            .with_env(vars(&[
+                ("SOLS_PROFILE", "localnet"),
+                ("SOLS_RPC_URL", "http://10.0.0.2:8899"),
+                ("SOLS_MAX_RETRIES", "0"),
+                ("SOLS_COMPUTE_UNIT_LIMIT", "200000"),
+                ("SOLS_TASKS", "/var/sols/tasks.jsonl"),
+                ("HOME", "/root"),
+            ]))
+            .unwrap();
+
+        assert_eq!(config.profile, "localnet");
+        let profile = config.active().unwrap();
+        assert_eq!(profile.rpc_url, "http://10.0.0.2:8899");
+        assert_eq!(profile.ws_url, Profile::localnet().ws_url);
+        assert_eq!(profile.retry.max_retries, 0);
+        assert_eq!(profile.fees.compute_unit_limit, Some(200_000));
+        assert_eq!(profile.tasks, PathBuf::from("/var/sols/tasks.jsonl"));
+        assert_eq!(config.profiles["devnet"], Profile::devnet());
+    }
+
+    #[test]
+    fn test_logging_from_file_then_env() {
+        let config = Config::from_toml(
+            r#"
+            [logging]
+            format = "json"
+            filter = "warn"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.logging.format, LogFormat::Json);
+        assert_eq!(config.logging.filter, "warn");
+
+        let config = config
+            .with_env(vars(&[
+                ("SOLS_LOG_FORMAT", "pretty"),
+                ("SOLS_LOG", "task_scheduler=debug"),
+            ]))
+            .unwrap();
+
+        assert_eq!(config.logging.format, LogFormat::Pretty);
+        assert_eq!(config.logging.filter, "task_scheduler=debug");
+        assert!(Config::new()
+            .with_env(vars(&[("SOLS_LOG_FORMAT", "xml")]))
+            .unwrap_err()
+            .contains("SOLS_LOG_FORMAT"));
+    }
+
+    #[test]
+    fn test_server_settings_from_file_then_env() {
+        let config = Config::from_toml(
+            r#"
+            [server]
+            listen_addr = "0.0.0.0:9000"
+            tokens = "/secrets/tokens.json"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.server.listen_addr, "0.0.0.0:9000");
+        assert_eq!(config.server.tokens, PathBuf::from("/secrets/tokens.json"));
+        assert_eq!(Config::new().server, ServerSettings::default());
+
+        let config = config
+            .with_env(vars(&[
+                ("SOLS_LISTEN_ADDR", "127.0.0.1:9001"),
+                ("SOLS_TOKENS", "tokens-dev.json"),
+            ]))
+            .unwrap();
+
+        assert_eq!(config.server.listen_addr, "127.0.0.1:9001");
+        assert_eq!(config.server.tokens, PathBuf::from("tokens-dev.json"));
+        config.validate().unwrap();
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_server_settings() {
+        let mut config = Config::new();
+        config.server.listen_addr = "localhost".to_string();
+        assert!(config
+            .validate()
+            .unwrap_err()
+            .starts_with("Server: listen_addr"));
+
+        let mut config = Config::new();
+        config.server.tokens = PathBuf::new();
+        assert!(config.validate().unwrap_err().contains("tokens path"));
+    }
+
+    #[test]
+    fn test_profile_requires_cluster() {
+        let result = Config::from_toml(
+            r#"
+            [profiles.staging]
+            rpc_url = "https://rpc.example.com"
+            ws_url = "wss://rpc.example.com"
+            "#,
+        );
+
+        assert!(result.unwrap_err().contains("missing field `cluster`"));
+    }
+
+    #[test]
+    fn test_malformed_env_value_is_rejected() {
+        let error = Config::new()
+            .with_env(vars(&[("SOLS_CONCURRENCY", "lots")]))
+            .unwrap_err();
+
+        assert!(error.starts_with("Invalid SOLS_CONCURRENCY"));
+    }
+
+    #[test]
+    fn test_unknown_profile_is_rejected() {
+        let error = Config::new()
+            .with_env(vars(&[("SOLS_PROFILE", "testnet")]))
+            .unwrap_err();
+
+        assert!(error.contains("Unknown profile 'testnet'"));
+        assert!(Config::new().select("testnet").is_err());
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_settings() {
+        type Breakage = fn(&mut Profile);
+        let cases: Vec<(Breakage, &str)> = vec![
+            (
+                |p| p.rpc_url = "api.devnet.solana.com".to_string(),
+                "rpc_url",
+            ),
+            (
+                |p| p.ws_url = "https://api.devnet.solana.com".to_string(),
+                "ws_url",
+            ),
+            (|p| p.scheduler.concurrency = 0, "concurrency"),
+            (|p| p.scheduler.rpc_concurrency = 0, "rpc_concurrency"),
+            (|p| p.balances.interval_secs = 0, "balances.interval_secs"),
+            (|p| p.tasks = PathBuf::new(), "tasks path"),
+            (
+                |p| p.fees.compute_unit_limit = Some(0),
+                "Compute unit limit",
+            ),
+        ];
+
+        for (break_profile, expected) in cases {
+            let mut config = Config::new();
+            break_profile(config.active_mut().unwrap());
+
+            let error = config.validate().unwrap_err();
+
+            assert!(error.starts_with("Profile 'devnet'"), "{}", error);
+            assert!(error.contains(expected), "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_profile_scheduler_is_pinned_to_cluster() {
+        assert_eq!(
+            Profile::mainnet().task_scheduler().cluster(),
+            Some(Cluster::Mainnet)
+        );
+    }
+
+    #[test]
+    fn test_profile_configures_scheduler_and_pool() {
+        let mut profile = Profile::localnet();
+        profile.scheduler.concurrency = 3;
+        profile.scheduler.rpc_concurrency = 5;
+
+        let pool = profile.worker_pool_config();
+
+        assert_eq!(pool.concurrency, 3);
+        assert_eq!(pool.rpc_limit.available_permits(), 5);
+        assert_eq!(pool.endpoint.as_deref(), Some("http://127.0.0.1:8899"));
+    }
+}
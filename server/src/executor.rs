@@ -0,0 +1,567 @@
+//! Runs tasks on chain: builds and signs each task's transaction with the
+//! keystore's wallets, sends it and waits for it to land or expire.
+
+use crate::metrics::rpc_call;
+use engine::transaction::TransactionParams;
+use engine::{FeeSettings, TransactionBuilder, WalletManager};
+use solana_client::client_error::{ClientError, ClientErrorKind};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_request::{RpcError, RpcResponseErrorData};
+use solana_sdk::hash::Hash;
+use solana_sdk::signature::{Keypair, Signature};
+use solana_sdk::transaction::{Transaction, TransactionError};
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use task_scheduler::{
+    AttemptHandle, AttemptReport, LandingStatus, SignatureChecker, Task, TaskExecutor,
+};
+use tracing::{debug, Instrument};
+
+/// How often a sent transaction's status is checked by default
+pub const DEFAULT_CONFIRM_POLL: Duration = Duration::from_millis(500);
+
+/// A failed RPC request, with what the node said about it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChainError {
+    pub message: String,
+    /// The endpoint answered 429
+    pub throttled: bool,
+    /// Program logs from a failed preflight simulation
+    pub logs: Vec<String>,
+}
+
+impl ChainError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            ..Self::default()
+        }
+    }
+
+    fn report(self, context: &str) -> AttemptReport {
+        let error = format!("{}: {}", context, self.message);
+        let report = if self.throttled {
+            AttemptReport::throttled(error)
+        } else {
+            AttemptReport::failure(error)
+        };
+        report.with_simulation_logs(self.logs)
+    }
+}
+
+impl From<ClientError> for ChainError {
+    fn from(error: ClientError) -> Self {
+        let throttled = matches!(
+            error.kind(),
+            ClientErrorKind::Reqwest(e) if e.status().is_some_and(|status| status.as_u16() == 429)
+        );
+        let logs = match error.kind() {
+            ClientErrorKind::RpcError(RpcError::RpcResponseError {
+                data: RpcResponseErrorData::SendTransactionPreflightFailure(result),
+                ..
+            }) => result.logs.clone().unwrap_or_default(),
+            _ => Vec::new(),
+        };
+        Self {
+            message: error.to_string(),
+            throttled,
+            logs,
+        }
+    }
+}
+
+/// Whether a signature landed: `Some(Ok)` if it succeeded, `Some(Err)` with the
+/// transaction's error if it failed, `None` if it was not found
+pub type SignatureStatus = Option<Result<(), String>>;
+
+/// The RPC calls sending a transaction takes
+pub trait Chain: Send + Sync + 'static {
+    fn latest_blockhash(&self) -> impl Future<Output = Result<Hash, ChainError>> + Send;
+
+    fn send(
+        &self,
+        transaction: &Transaction,
+    ) -> impl Future<Output = Result<Signature, ChainError>> + Send;
+
+    /// The signature's status at the client's commitment, from recent slots only
+    fn status(
+        &self,
+        signature: &Signature,
+    ) -> impl Future<Output = Result<SignatureStatus, ChainError>> + Send;
+
+    /// Like `status`, but also searching the ledger's history
+    fn historical_status(
+        &self,
+        signature: &Signature,
+    ) -> impl Future<Output = Result<SignatureStatus, ChainError>> + Send;
+
+    fn is_blockhash_valid(
+        &self,
+        blockhash: &Hash,
+    ) -> impl Future<Output = Result<bool, ChainError>> + Send;
+}
+
+impl Chain for RpcClient {
+    async fn latest_blockhash(&self) -> Result<Hash, ChainError> {
+        Ok(rpc_call("getLatestBlockhash", self.get_latest_blockhash()).await?)
+    }
+
+    async fn send(&self, transaction: &Transaction) -> Result<Signature, ChainError> {
+        Ok(rpc_call("sendTransaction", self.send_transaction(transaction)).await?)
+    }
+
+    async fn status(&self, signature: &Signature) -> Result<SignatureStatus, ChainError> {
+        let statuses = rpc_call(
+            "getSignatureStatuses",
+            self.get_signature_statuses(&[*signature]),
+        )
+        .await?;
+        // Only counted once it reaches the client's commitment
+        let status = statuses.value.into_iter().next().flatten();
+        Ok(status
+            .filter(|status| status.satisfies_commitment(self.commitment()))
+            .map(|status| outcome(status.err)))
+    }
+
+    async fn historical_status(
+        &self,
+        signature: &Signature,
+    ) -> Result<SignatureStatus, ChainError> {
+        let statuses = rpc_call(
+            "getSignatureStatuses",
+            self.get_signature_statuses_with_history(&[*signature]),
+        )
+        .await?;
+        // Only counted once it reaches the client's commitment
+        let status = statuses.value.into_iter().next().flatten();
+        Ok(status
+            .filter(|status| status.satisfies_commitment(self.commitment()))
+            .map(|status| outcome(status.err)))
+    }
+
+    async fn is_blockhash_valid(&self, blockhash: &Hash) -> Result<bool, ChainError> {
+        Ok(rpc_call(
+            "isBlockhashValid",
+            self.is_blockhash_valid(blockhash, self.commitment()),
+        )
+        .await?)
+    }
+}
+
+/// How a transaction that landed ended
+fn outcome(error: Option<TransactionError>) -> Result<(), String> {
+    match error {
+        Some(error) => Err(error.to_string()),
+        None => Ok(()),
+    }
+}
+
+/// Sends each task's transaction, signed by the keystore wallet it names, and
+/// polls until it lands or its blockhash expires
+pub struct ChainExecutor<C: Chain> {
+    chain: Arc<C>,
+    wallets: Arc<RwLock<WalletManager>>,
+    fees: FeeSettings,
+    poll: Duration,
+}
+
+impl<C: Chain> ChainExecutor<C> {
+    pub fn new(chain: Arc<C>, wallets: Arc<RwLock<WalletManager>>, fees: FeeSettings) -> Self {
+        Self {
+            chain,
+            wallets,
+            fees,
+            poll: DEFAULT_CONFIRM_POLL,
+        }
+    }
+
+    /// How often a sent transaction's status is checked
+    pub fn with_poll_interval(mut self, poll: Duration) -> Self {
+        self.poll = poll;
+        self
+    }
+
+    /// Builds and signs the task's transaction. The wallet lock is not held
+    /// across any await, so keys are only borrowed while signing.
+    fn build(&self, params: &TransactionParams, blockhash: Hash) -> Result<Transaction, String> {
+        let wallets = self.wallets.read().expect("wallet lock poisoned");
+        let keypair = |pubkey| -> Result<&Keypair, String> {
+            let name = wallets
+                .wallet_name(pubkey)
+                .ok_or_else(|| format!("No wallet holds the key {}", pubkey))?;
+            wallets.get_wallet(name)
+        };
+
+        let builder = TransactionBuilder::with_fees(self.fees);
+        match params {
+            TransactionParams::Transfer(params) => builder.build_transfer(
+                keypair(&params.from)?,
+                &params.to,
+                params.lamports,
+                blockhash,
+            ),
+            TransactionParams::BatchTransfer(params) => {
+                builder.build_batch_transfer(keypair(&params.from)?, &params.transfers, blockhash)
+            }
+            TransactionParams::CreateAccount(params) => builder.build_create_account(
+                keypair(&params.payer)?,
+                keypair(&params.new_account)?,
+                &params.owner,
+                params.lamports,
+                params.space,
+                blockhash,
+            ),
+        }
+    }
+
+    /// Polls until the signature lands, or is not found once its blockhash expired
+    async fn confirm(&self, signature: Signature, blockhash: Hash, fee: u64) -> AttemptReport {
+        let landed = |status: Result<(), String>| match status {
+            Ok(()) => AttemptReport::success(signature.to_string()).with_fee(fee),
+            Err(error) => AttemptReport::failure(error)
+                .with_signature(signature.to_string())
+                .with_fee(fee),
+        };
+        loop {
+            // Errors are retried until the attempt's timeout; the reaper has the signature
+            if let Ok(Some(status)) = self.chain.status(&signature).await {
+                return landed(status);
+            }
+            if let Ok(false) = self.chain.is_blockhash_valid(&blockhash).await {
+                // It may have landed just before the blockhash expired
+                if let Ok(Some(status)) = self.chain.status(&signature).await {
+                    return landed(status);
+                }
+                return AttemptReport::failure("Blockhash expired before the transaction landed")
+                    .with_signature(signature.to_string());
+            }
+            tokio::time::sleep(self.poll).await;
+        }
+    }
+}
+
+impl<C: Chain> TaskExecutor for ChainExecutor<C> {
+    async fn execute(&self, task: Task, attempt: AttemptHandle) -> AttemptReport {
+        let params = match task.params() {
+            Ok(params) => params,
+            Err(e) => return AttemptReport::failure(format!("Invalid transaction params: {}", e)),
+        };
+        let blockhash = match self.chain.latest_blockhash().await {
+            Ok(blockhash) => blockhash,
+            Err(e) => return e.report("Failed to fetch blockhash"),
+        };
+        let transaction = match self.build(&params, blockhash) {
+            Ok(transaction) => transaction,
+            Err(e) => return AttemptReport::failure(e).with_blockhash(blockhash.to_string()),
+        };
+        let own_instructions =
+            transaction.message.instructions.len() - self.fees.instructions().len();
+        let fee = self
+            .fees
+            .estimate_fee(transaction.signatures.len(), own_instructions);
+
+        let sent = self
+            .chain
+            .send(&transaction)
+            .instrument(tracing::info_span!("send", %blockhash, fee));
+        let signature = match sent.await {
+            Ok(signature) => signature,
+            Err(e) => {
+                return e
+                    .report("Send failed")
+                    .with_blockhash(blockhash.to_string())
+            }
+        };
+        debug!(%signature, "Transaction sent");
+        attempt.sent(signature.to_string(), Some(blockhash.to_string()));
+        self.confirm(signature, blockhash, fee)
+            .instrument(tracing::info_span!("confirm", %signature))
+            .await
+            .with_blockhash(blockhash.to_string())
+    }
+}
+
+/// Checks signatures for the reaper, searching the ledger's history so that
+/// transactions sent long ago, e.g. before a restart, are still found
+pub struct LandingChecker<C: Chain>(pub Arc<C>);
+
+impl<C: Chain> SignatureChecker for LandingChecker<C> {
+    async fn check(&self, signature: &str) -> Result<LandingStatus, String> {
+        let signature: Signature = signature
+            .parse()
+            .map_err(|e| format!("Invalid signature '{}': {}", signature, e))?;
+        let status = self
+            .0
+            .historical_status(&signature)
+            .await
+            .map_err(|e| e.message)?;
+        Ok(match status {
+            Some(Ok(())) => LandingStatus::Landed,
+            Some(Err(error)) => LandingStatus::FailedOnChain(error),
+            None => LandingStatus::NotLanded,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine::transaction::{CreateAccountParams, TransferParams};
+    use solana_sdk::pubkey::Pubkey;
+    use solana_sdk::signature::Signer;
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+    use task_scheduler::{TaskScheduler, TaskStatus, WorkerPool, WorkerPoolConfig};
+
+    /// Answers every status check from a script, then with `None`
+    #[derive(Default)]
+    struct FakeChain {
+        send_error: Option<ChainError>,
+        statuses: Mutex<VecDeque<SignatureStatus>>,
+        historical: SignatureStatus,
+        blockhash_expired: bool,
+        sent: Mutex<Vec<Transaction>>,
+    }
+
+    impl FakeChain {
+        fn with_statuses(statuses: Vec<SignatureStatus>) -> Self {
+            Self {
+                statuses: Mutex::new(statuses.into()),
+                ..Self::default()
+            }
+        }
+    }
+
+    impl Chain for FakeChain {
+        async fn latest_blockhash(&self) -> Result<Hash, ChainError> {
+            Ok(Hash::new_unique())
+        }
+
+        async fn send(&self, transaction: &Transaction) -> Result<Signature, ChainError> {
+            if let Some(error) = &self.send_error {
+                return Err(error.clone());
+            }
+            self.sent.lock().unwrap().push(transaction.clone());
+            Ok(transaction.signatures[0])
+        }
+
+        async fn status(&self, _signature: &Signature) -> Result<SignatureStatus, ChainError> {
+            Ok(self.statuses.lock().unwrap().pop_front().flatten())
+        }
+
+        async fn historical_status(
+            &self,
+            _signature: &Signature,
+        ) -> Result<SignatureStatus, ChainError> {
+            Ok(self.historical.clone())
+        }
+
+        async fn is_blockhash_valid(&self, _blockhash: &Hash) -> Result<bool, ChainError> {
+            Ok(!self.blockhash_expired)
+        }
+    }
+
+    fn wallets(names: &[&str]) -> (Arc<RwLock<WalletManager>>, Vec<Pubkey>) {
+        let mut wallets = WalletManager::new();
+        let pubkeys = names
+            .iter()
+            .map(|name| wallets.generate_wallet(name.to_string()).unwrap())
+            .collect();
+        (Arc::new(RwLock::new(wallets)), pubkeys)
+    }
+
+    fn transfer_from(from: Pubkey) -> Task {
+        let params = TransactionParams::Transfer(TransferParams {
+            from,
+            to: Pubkey::new_unique(),
+            lamports: 1_000,
+        });
+        Task::new(serde_json::to_string(&params).unwrap())
+    }
+
+    /// Runs the task's first attempt through a worker pool and returns the task after it
+    async fn run_once(
+        chain: &Arc<FakeChain>,
+        wallets: Arc<RwLock<WalletManager>>,
+        task: Task,
+    ) -> Task {
+        let scheduler = Arc::new(Mutex::new(TaskScheduler::new().with_max_retries(0)));
+        let id = scheduler
+            .lock()
+            .unwrap()
+            .schedule_task(task)
+            .unwrap()
+            .id()
+            .to_string();
+        let executor = ChainExecutor::new(Arc::clone(chain), wallets, FeeSettings::default())
+            .with_poll_interval(Duration::from_millis(1));
+        let config = WorkerPoolConfig {
+            idle_poll: Duration::from_millis(1),
+            ..WorkerPoolConfig::default()
+        };
+        let pool = WorkerPool::new(Arc::clone(&scheduler), executor, config).spawn();
+
+        let finished = |scheduler: &TaskScheduler| {
+            let task = scheduler.task(&id).unwrap();
+            task.attempts()
+                .first()
+                .is_some_and(|attempt| attempt.finished_at.is_some())
+        };
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !finished(&scheduler.lock().unwrap()) {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        })
+        .await
+        .expect("attempt never finished");
+        pool.shutdown().await;
+        let task = scheduler.lock().unwrap().task(&id).unwrap().clone();
+        task
+    }
+
+    #[tokio::test]
+    async fn test_transfer_is_signed_sent_and_confirmed() {
+        let (wallets, pubkeys) = wallets(&["alpha"]);
+        let chain = Arc::new(FakeChain::with_statuses(vec![None, Some(Ok(()))]));
+
+        let task = run_once(&chain, wallets, transfer_from(pubkeys[0])).await;
+
+        assert_eq!(task.status(), TaskStatus::Completed);
+        let sent = chain.sent.lock().unwrap();
+        assert_eq!(sent.len(), 1);
+        sent[0].verify().unwrap();
+        let attempt = &task.attempts()[0];
+        assert_eq!(attempt.signature, Some(sent[0].signatures[0].to_string()));
+        assert_eq!(
+            attempt.blockhash,
+            Some(sent[0].message.recent_blockhash.to_string())
+        );
+        assert_eq!(attempt.fee_lamports, Some(5_000));
+    }
+
+    #[tokio::test]
+    async fn test_failed_transaction_keeps_its_signature() {
+        let (wallets, pubkeys) = wallets(&["alpha"]);
+        let chain = Arc::new(FakeChain::with_statuses(vec![Some(Err(
+            "insufficient funds".to_string(),
+        ))]));
+
+        let task = run_once(&chain, wallets, transfer_from(pubkeys[0])).await;
+
+        assert_eq!(task.status(), TaskStatus::Failed);
+        let attempt = &task.attempts()[0];
+        assert_eq!(attempt.error.as_deref(), Some("insufficient funds"));
+        assert!(attempt.signature.is_some());
+        assert_eq!(attempt.fee_lamports, Some(5_000));
+    }
+
+    #[tokio::test]
+    async fn test_expired_blockhash_fails_the_attempt() {
+        let (wallets, pubkeys) = wallets(&["alpha"]);
+        let chain = Arc::new(FakeChain {
+            blockhash_expired: true,
+            ..FakeChain::default()
+        });
+
+        let task = run_once(&chain, wallets, transfer_from(pubkeys[0])).await;
+
+        let attempt = &task.attempts()[0];
+        assert_eq!(
+            attempt.error.as_deref(),
+            Some("Blockhash expired before the transaction landed")
+        );
+        assert!(attempt.signature.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_throttled_send_does_not_use_a_retry() {
+        let (wallets, pubkeys) = wallets(&["alpha"]);
+        let chain = Arc::new(FakeChain {
+            send_error: Some(ChainError {
+                throttled: true,
+                ..ChainError::new("HTTP status client error (429 Too Many Requests)")
+            }),
+            ..FakeChain::default()
+        });
+
+        let task = run_once(&chain, wallets, transfer_from(pubkeys[0])).await;
+
+        assert_eq!(task.status(), TaskStatus::Pending);
+        assert_eq!(task.retry_count(), 0);
+        assert!(task.attempts()[0]
+            .error
+            .as_deref()
+            .unwrap()
+            .starts_with("Send failed"));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_wallet_fails_without_sending() {
+        let (wallets, _) = wallets(&[]);
+        let chain = Arc::new(FakeChain::default());
+
+        let task = run_once(&chain, wallets, transfer_from(Pubkey::new_unique())).await;
+
+        assert_eq!(task.status(), TaskStatus::Failed);
+        assert!(task.attempts()[0]
+            .error
+            .as_deref()
+            .unwrap()
+            .starts_with("No wallet holds the key"));
+        assert!(chain.sent.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_create_account_is_signed_by_payer_and_new_account() {
+        let (wallets, pubkeys) = wallets(&["payer", "vault"]);
+        let chain = Arc::new(FakeChain::with_statuses(vec![Some(Ok(()))]));
+        let params = TransactionParams::CreateAccount(CreateAccountParams {
+            payer: pubkeys[0],
+            new_account: pubkeys[1],
+            owner: Pubkey::new_unique(),
+            lamports: 1_000_000,
+            space: 0,
+        });
+
+        let task = run_once(
+            &chain,
+            wallets,
+            Task::new(serde_json::to_string(&params).unwrap()),
+        )
+        .await;
+
+        assert_eq!(task.status(), TaskStatus::Completed);
+        let sent = chain.sent.lock().unwrap();
+        assert_eq!(sent[0].signatures.len(), 2);
+        sent[0].verify().unwrap();
+        assert_eq!(task.attempts()[0].fee_lamports, Some(10_000));
+    }
+
+    #[tokio::test]
+    async fn test_landing_checker_searches_history() {
+        let signature = Keypair::new().sign_message(b"sent").to_string();
+        let checker = |historical| {
+            LandingChecker(Arc::new(FakeChain {
+                historical,
+                ..FakeChain::default()
+            }))
+        };
+
+        assert_eq!(
+            checker(Some(Ok(()))).check(&signature).await,
+            Ok(LandingStatus::Landed)
+        );
+        assert_eq!(
+            checker(Some(Err("failed".to_string())))
+                .check(&signature)
+                .await,
+            Ok(LandingStatus::FailedOnChain("failed".to_string()))
+        );
+        assert_eq!(
+            checker(None).check(&signature).await,
+            Ok(LandingStatus::NotLanded)
+        );
+        assert!(checker(None).check("not a signature").await.is_err());
+    }
+}
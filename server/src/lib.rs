@@ -0,0 +1,23 @@
+pub mod api;
+pub mod auth;
+pub mod balances;
+pub mod cli;
+pub mod config;
+pub mod executor;
+pub mod live;
+pub mod lock;
+pub mod metrics;
+pub mod state;
+pub mod telemetry;
+pub mod treasury;
+
+pub use api::{router, serve, serve_until};
+pub use auth::{ApiToken, Role, TokenError, TokenStore};
+pub use balances::{BalanceCache, BalanceMonitor, BalanceMonitorConfig, BalanceSource};
+pub use config::{Commitment, Config, LogFormat, LogSettings, Profile, ServerSettings};
+pub use executor::{Chain, ChainError, ChainExecutor, LandingChecker};
+pub use live::{
+    BalanceAlert, BalanceAlertKind, BalanceChange, EventFilter, LiveEvent, LiveEventType, LiveFeed,
+};
+pub use state::AppState;
+pub use treasury::TopUpReport;
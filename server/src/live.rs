@@ -0,0 +1,325 @@
+use engine::WalletManager;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use task_scheduler::{TagProgress, TaskEvent, TaskScheduler, DEFAULT_EVENT_CAPACITY};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::JoinHandle;
+
+/// A wallet's balance moved since it was last observed
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BalanceChange {
+    pub wallet: String,
+    pub pubkey: Pubkey,
+    /// `None` the first time the wallet is observed
+    pub previous: Option<u64>,
+    pub lamports: u64,
+    /// When the balance was observed, in ms since the Unix epoch
+    pub timestamp: u64,
+}
+
+impl BalanceChange {
+    pub fn new(
+        wallet: impl Into<String>,
+        pubkey: Pubkey,
+        previous: Option<u64>,
+        lamports: u64,
+    ) -> Self {
+        Self {
+            wallet: wallet.into(),
+            pubkey,
+            previous,
+            lamports,
+            timestamp: now_millis(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum BalanceAlertKind {
+    /// Below the configured low-balance threshold
+    LowBalance,
+    /// Below the rent-exempt minimum of a plain system account
+    BelowRentExempt,
+}
+
+/// A wallet's balance dropped below a threshold. Sent once per crossing,
+/// not on every observation below it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BalanceAlert {
+    pub wallet: String,
+    pub pubkey: Pubkey,
+    pub kind: BalanceAlertKind,
+    pub lamports: u64,
+    pub threshold: u64,
+    /// When the balance was observed, in ms since the Unix epoch
+    pub timestamp: u64,
+}
+
+impl BalanceAlert {
+    pub fn new(
+        wallet: impl Into<String>,
+        pubkey: Pubkey,
+        kind: BalanceAlertKind,
+        lamports: u64,
+        threshold: u64,
+    ) -> Self {
+        Self {
+            wallet: wallet.into(),
+            pubkey,
+            kind,
+            lamports,
+            threshold,
+            timestamp: now_millis(),
+        }
+    }
+}
+
+pub(crate) fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Everything the dashboard can watch live
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LiveEvent {
+    Task(TaskEvent),
+    Balance(BalanceChange),
+    BalanceAlert(BalanceAlert),
+    /// Task counts for a squad or campaign tag, sent whenever one of its tasks changes
+    CampaignProgress(TagProgress),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum LiveEventType {
+    Task,
+    Balance,
+    BalanceAlert,
+    CampaignProgress,
+}
+
+impl LiveEventType {
+    /// Matches the serialized `type` tag
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LiveEventType::Task => "task",
+            LiveEventType::Balance => "balance",
+            LiveEventType::BalanceAlert => "balance_alert",
+            LiveEventType::CampaignProgress => "campaign_progress",
+        }
+    }
+}
+
+impl LiveEvent {
+    pub fn event_type(&self) -> LiveEventType {
+        match self {
+            LiveEvent::Task(_) => LiveEventType::Task,
+            LiveEvent::Balance(_) => LiveEventType::Balance,
+            LiveEvent::BalanceAlert(_) => LiveEventType::BalanceAlert,
+            LiveEvent::CampaignProgress(_) => LiveEventType::CampaignProgress,
+        }
+    }
+}
+
+/// Fans live events out to every connected stream
+#[derive(Clone)]
+pub struct LiveFeed {
+    sender: broadcast::Sender<LiveEvent>,
+}
+
+impl Default for LiveFeed {
+    fn default() -> Self {
+        Self::new(DEFAULT_EVENT_CAPACITY)
+    }
+}
+
+impl LiveFeed {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<LiveEvent> {
+        self.sender.subscribe()
+    }
+
+    pub fn publish(&self, event: LiveEvent) {
+        // No subscribers is not an error
+        let _ = self.sender.send(event);
+    }
+
+    /// Republishes the scheduler's task events, each tagged one followed by its tag's progress
+    pub fn forward_from(&self, scheduler: Arc<Mutex<TaskScheduler>>) -> JoinHandle<()> {
+        let mut events = scheduler
+            .lock()
+            .expect("scheduler lock poisoned")
+            .subscribe();
+        let feed = self.clone();
+        tokio::spawn(async move {
+            loop {
+                let event = match events.recv().await {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                };
+                let progress = event.tag.as_deref().map(|tag| {
+                    scheduler
+                        .lock()
+                        .expect("scheduler lock poisoned")
+                        .progress(tag)
+                });
+                feed.publish(LiveEvent::Task(event));
+                if let Some(progress) = progress {
+                    feed.publish(LiveEvent::CampaignProgress(progress));
+                }
+            }
+        })
+    }
+}
+
+/// Which live events a subscriber wants. Unset filters match everything.
+#[derive(Debug, Clone, Default)]
+pub struct EventFilter {
+    types: HashSet<LiveEventType>,
+    squads: Option<SquadScope>,
+}
+
+/// Tags and member wallets of the squads a subscriber follows
+#[derive(Debug, Clone, Default)]
+struct SquadScope {
+    tags: HashSet<String>,
+    wallets: HashSet<Pubkey>,
+}
+
+impl EventFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_type(mut self, event_type: LiveEventType) -> Self {
+        self.types.insert(event_type);
+        self
+    }
+
+    /// Follows a squad: tasks tagged with its name or campaign or paid by a member,
+    /// and balances of its members. Membership is read once, when called.
+    pub fn with_squad(mut self, wallets: &WalletManager, name: &str) -> Result<Self, String> {
+        let squad = wallets.get_squad(name)?;
+        let scope = self.squads.get_or_insert_with(SquadScope::default);
+        scope.tags.insert(squad.name.clone());
+        scope.tags.extend(squad.campaign.clone());
+        scope.wallets.extend(
+            wallets
+                .squad_pubkeys(name)?
+                .into_iter()
+                .map(|(_, pubkey)| pubkey),
+        );
+        Ok(self)
+    }
+
+    pub fn matches(&self, event: &LiveEvent) -> bool {
+        if !self.types.is_empty() && !self.types.contains(&event.event_type()) {
+            return false;
+        }
+        let Some(scope) = &self.squads else {
+            return true;
+        };
+        match event {
+            LiveEvent::Task(event) => {
+                event
+                    .tag
+                    .as_ref()
+                    .is_some_and(|tag| scope.tags.contains(tag))
+                    || event
+                        .wallet
+                        .is_some_and(|wallet| scope.wallets.contains(&wallet))
+            }
+            LiveEvent::Balance(change) => scope.wallets.contains(&change.pubkey),
+            LiveEvent::BalanceAlert(alert) => scope.wallets.contains(&alert.pubkey),
+            LiveEvent::CampaignProgress(progress) => scope.tags.contains(&progress.tag),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use task_scheduler::{Task, TaskEventKind};
+
+    fn task_event(tag: Option<&str>) -> LiveEvent {
+        let mut task = Task::new("{}".to_string());
+        if let Some(tag) = tag {
+            task = task.with_tag(tag);
+        }
+        LiveEvent::Task(TaskEvent::new(&task, TaskEventKind::Scheduled))
+    }
+
+    #[test]
+    fn test_events_serialize_with_type_tag() {
+        let event = LiveEvent::Balance(BalanceChange::new(
+            "alpha",
+            Pubkey::new_unique(),
+            Some(1),
+            2,
+        ));
+
+        let value = serde_json::to_value(&event).unwrap();
+
+        assert_eq!(value["type"], "balance");
+        assert_eq!(value["wallet"], "alpha");
+        assert_eq!(serde_json::from_value::<LiveEvent>(value).unwrap(), event);
+    }
+
+    #[test]
+    fn test_empty_filter_matches_everything() {
+        let filter = EventFilter::new();
+
+        assert!(filter.matches(&task_event(None)));
+        assert!(filter.matches(&LiveEvent::CampaignProgress(TagProgress::default())));
+    }
+
+    #[test]
+    fn test_squad_filter_matches_campaign_tag() {
+        let mut wallets = WalletManager::new();
+        wallets.generate_wallet("alpha".to_string()).unwrap();
+        wallets
+            .create_squad("red".to_string(), Some("kamino".to_string()))
+            .unwrap();
+        wallets
+            .assign_to_squad("red", &["alpha".to_string()])
+            .unwrap();
+        let member = wallets.get_pubkey("alpha").unwrap();
+
+        let filter = EventFilter::new()
+            .with_type(LiveEventType::Task)
+            .with_type(LiveEventType::Balance)
+            .with_squad(&wallets, "red")
+            .unwrap();
+
+        assert!(filter.matches(&task_event(Some("kamino"))));
+        assert!(filter.matches(&task_event(Some("red"))));
+        assert!(!filter.matches(&task_event(Some("marginfi"))));
+        assert!(!filter.matches(&task_event(None)));
+        assert!(filter.matches(&LiveEvent::Balance(BalanceChange::new(
+            "alpha", member, None, 1
+        ))));
+        assert!(!filter.matches(&LiveEvent::CampaignProgress(TagProgress {
+            tag: "kamino".to_string(),
+            ..TagProgress::default()
+        })));
+    }
+
+    #[test]
+    fn test_unknown_squad_is_rejected() {
+        assert!(EventFilter::new()
+            .with_squad(&WalletManager::new(), "ghost")
+            .is_err());
+    }
+}
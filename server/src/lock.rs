@@ -0,0 +1,55 @@
+//! Advisory locks that keep two processes from writing the same store
+
+use std::fs::{File, TryLockError};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// How often a held lock is tried again while waiting for it
+const RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Locks `<path>.lock` until the returned file is dropped, waiting up to `wait`
+/// for whoever holds it. The server holds the locks of its keystore and task
+/// store for as long as it runs, so CLI commands never write underneath it.
+pub fn lock_store(path: &Path, wait: Duration) -> Result<File, String> {
+    let mut lock_path = path.as_os_str().to_owned();
+    lock_path.push(".lock");
+    let file = File::create(&lock_path)
+        .map_err(|e| format!("Failed to open {}: {}", Path::new(&lock_path).display(), e))?;
+    let started = Instant::now();
+    loop {
+        match file.try_lock() {
+            Ok(()) => return Ok(file),
+            Err(TryLockError::WouldBlock) if started.elapsed() < wait => {
+                std::thread::sleep(RETRY_INTERVAL)
+            }
+            Err(TryLockError::WouldBlock) => {
+                return Err(format!(
+                    "{} is locked by another process, e.g. a running server",
+                    path.display()
+                ))
+            }
+            Err(TryLockError::Error(e)) => {
+                return Err(format!("Failed to lock {}: {}", path.display(), e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_second_holder_is_refused_until_the_first_lets_go() {
+        let path = std::env::temp_dir().join(format!("sols-lock-{}", Uuid::new_v4()));
+
+        let held = lock_store(&path, Duration::ZERO).unwrap();
+        let error = lock_store(&path, Duration::from_millis(100)).unwrap_err();
+        assert!(error.contains("locked by another process"), "{}", error);
+
+        drop(held);
+        assert!(lock_store(&path, Duration::ZERO).is_ok());
+        let _ = std::fs::remove_file(format!("{}.lock", path.display()));
+    }
+}
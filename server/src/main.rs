@@ -1,10 +1,147 @@
-fn main() {
-    println!("Hello, world!");
+use server::lock::lock_store;
+use server::{AppState, BalanceMonitor, ChainExecutor, Config, LandingChecker, Role, TokenStore};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use task_scheduler::{Reaper, ReaperConfig, TaskStore, WorkerPool};
+use tokio::net::TcpListener;
+#[cfg(unix)]
+use tokio::signal::unix::{signal, SignalKind};
+use tracing::{info, warn};
+
+/// How long running attempts get to finish at shutdown before they are
+/// abandoned and their tasks saved for the next run
+const SHUTDOWN_DEADLINE: Duration = Duration::from_secs(30);
+/// How long startup waits for a CLI command to release the stores
+const STORE_LOCK_WAIT: Duration = Duration::from_secs(10);
+
+#[tokio::main]
+async fn main() -> std::io::Result<()> {
+    // Refuse to start on a bad config rather than fail on first use
+    let config = Config::from_env(None)
+        .and_then(|config| config.validate().map(|_| config))
+        .map_err(std::io::Error::other)?;
+    server::telemetry::init(&config.logging).map_err(std::io::Error::other)?;
+    let profile = config.active().map_err(std::io::Error::other)?;
+    info!(
+        profile = %config.profile,
+        cluster = %profile.cluster,
+        rpc_url = %profile.rpc_url,
+        "Using profile"
+    );
+    // Never run against a node of another cluster than the profile's keys and tasks
+    profile
+        .verify_cluster()
+        .await
+        .map_err(std::io::Error::other)?;
+
+    let mut tokens = TokenStore::open(&config.server.tokens).map_err(std::io::Error::other)?;
+    // Without any token nobody could call the API, so the first start issues an admin one
+    if tokens.is_empty() {
+        let (_, secret) = tokens
+            .issue("bootstrap", Role::Admin)
+            .map_err(std::io::Error::other)?;
+        // Printed rather than logged, so the secret never reaches a log collector
+        println!("Issued bootstrap admin token (shown once): {}", secret);
+    }
+
+    let listener = TcpListener::bind(&config.server.listen_addr).await?;
+    info!(addr = %listener.local_addr()?, "Listening");
+    let keystore = profile.keystore();
+    // Held until the tasks are saved at exit, so CLI commands never write
+    // the stores underneath the server
+    let _locks = [keystore.path(), profile.task_store().path()]
+        .map(|path| lock_store(path, STORE_LOCK_WAIT))
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(std::io::Error::other)?;
+    let wallets = keystore.load().map_err(std::io::Error::other)?;
+    let mut state = AppState::new(wallets, Arc::default())
+        .with_keystore(keystore)
+        .with_tokens(tokens)
+        .with_fees(profile.fees);
+    // Pick up tasks left by the last run or scheduled from the CLI
+    let mut scheduler = profile
+        .task_scheduler()
+        .with_wallet_names(Arc::clone(&state.wallets));
+    let restored = profile
+        .task_store()
+        .load()
+        .and_then(|tasks| scheduler.load(tasks).map_err(str::to_string))
+        .map_err(std::io::Error::other)?;
+    info!(tasks = restored.len(), "Restored unfinished tasks");
+    state.scheduler = Arc::new(Mutex::new(scheduler));
+    let monitor = BalanceMonitor::new(
+        &state,
+        profile.rpc_client(),
+        profile.balance_monitor_config(),
+    )
+    .spawn();
+
+    // Workers send scheduled tasks through the profile's RPC node, and the
+    // reaper settles attempts that hang after sending
+    let rpc = Arc::new(profile.rpc_client());
+    let executor = ChainExecutor::new(Arc::clone(&rpc), Arc::clone(&state.wallets), profile.fees);
+    let pool = WorkerPool::new(
+        Arc::clone(&state.scheduler),
+        executor,
+        profile.worker_pool_config(),
+    )
+    .spawn();
+    let reaper = Reaper::new(
+        Arc::clone(&state.scheduler),
+        LandingChecker(rpc),
+        ReaperConfig::default(),
+    )
+    .spawn();
+
+    let served = server::serve_until(listener, state, shutdown_signal()).await;
+    info!("Shutting down");
+    // Stopped first, so nothing changes the tasks after the pool saves them
+    reaper.shutdown().await;
+    monitor.shutdown().await;
+    let report = pool
+        .shutdown_gracefully(SHUTDOWN_DEADLINE, &profile.task_store())
+        .await
+        .map_err(std::io::Error::other)?;
+    info!(
+        interrupted = report.interrupted,
+        persisted = report.persisted,
+        "Saved tasks"
+    );
+    served
+}
+
+/// Resolves on ctrl-c, or SIGTERM where there is one
+async fn shutdown_signal() {
+    let interrupt = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!(error = %e, "Could not listen for ctrl-c");
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(e) => {
+                warn!(error = %e, "Could not listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        _ = interrupt => {}
+        _ = terminate => {}
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use solana_client::nonblocking::rpc_client::RpcClient;
+    use server::Profile;
 
     #[test]
     fn first_test() {
@@ -13,8 +150,7 @@ mod tests {
 
     #[tokio::test]
     async fn can_connect_to_solana() {
-        let rpc_url = "https://api.devnet.solana.com".to_string();
-        let client = RpcClient::new(rpc_url);
+        let client = Profile::devnet().rpc_client();
         let version = client.get_version().await;
         assert!(
             version.is_ok(),
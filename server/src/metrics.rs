@@ -0,0 +1,70 @@
+//! Prometheus export of the scheduler's metrics plus the server's own:
+//! RPC call latency and errors, and the number of managed wallets.
+
+use engine::WalletManager;
+use metrics::{counter, describe_counter, describe_gauge, describe_histogram, gauge, histogram};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use std::future::Future;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+pub const RPC_DURATION: &str = "sols_rpc_request_duration_seconds";
+pub const RPC_ERRORS: &str = "sols_rpc_errors_total";
+pub const WALLETS: &str = "sols_wallets";
+pub const SQUADS: &str = "sols_squads";
+/// Set by the balance monitor on each refresh
+pub const LOW_BALANCE_WALLETS: &str = "sols_low_balance_wallets";
+pub const BALANCE_ALERTS: &str = "sols_balance_alerts_total";
+
+/// From 5ms to a minute, covering single RPC calls up to confirmed transactions
+const LATENCY_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0,
+];
+
+static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+
+/// Installs the Prometheus recorder as the process-wide one, once, and returns
+/// the handle rendering it. Metrics recorded before this call are lost.
+pub fn install() -> &'static PrometheusHandle {
+    HANDLE.get_or_init(|| {
+        let handle = PrometheusBuilder::new()
+            .set_buckets_for_metric(Matcher::Suffix("seconds".to_string()), LATENCY_BUCKETS)
+            .and_then(|builder| builder.install_recorder())
+            .expect("metrics recorder already installed");
+        task_scheduler::metrics::describe();
+        describe_histogram!(
+            RPC_DURATION,
+            metrics::Unit::Seconds,
+            "RPC request latency by method"
+        );
+        describe_counter!(RPC_ERRORS, "Failed RPC requests by method");
+        describe_gauge!(WALLETS, "Wallets held by the wallet manager");
+        describe_gauge!(SQUADS, "Squads defined in the wallet manager");
+        describe_gauge!(
+            LOW_BALANCE_WALLETS,
+            "Wallets below the low-balance threshold"
+        );
+        describe_counter!(BALANCE_ALERTS, "Low-balance alerts raised, by kind");
+        handle
+    })
+}
+
+/// Awaits an RPC request, recording its latency and whether it failed
+pub async fn rpc_call<T, E>(
+    method: &'static str,
+    request: impl Future<Output = Result<T, E>>,
+) -> Result<T, E> {
+    let started = Instant::now();
+    let result = request.await;
+    histogram!(RPC_DURATION, "method" => method).record(started.elapsed().as_secs_f64());
+    if result.is_err() {
+        counter!(RPC_ERRORS, "method" => method).increment(1);
+    }
+    result
+}
+
+/// Sets the wallet and squad gauges
+pub fn record_wallets(wallets: &WalletManager) {
+    gauge!(WALLETS).set(wallets.wallet_count() as f64);
+    gauge!(SQUADS).set(wallets.list_squads().len() as f64);
+}
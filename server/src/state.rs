@@ -0,0 +1,53 @@
+use crate::auth::TokenStore;
+use crate::balances::BalanceCache;
+use crate::live::LiveFeed;
+use engine::{FeeSettings, Keystore, WalletManager};
+use std::sync::{Arc, Mutex, RwLock};
+use task_scheduler::TaskScheduler;
+
+/// Everything the API handlers share. Cloning is cheap.
+#[derive(Clone, Default)]
+pub struct AppState {
+    pub wallets: Arc<RwLock<WalletManager>>,
+    /// Where wallet changes are saved, if anywhere
+    pub keystore: Option<Arc<Keystore>>,
+    /// The same scheduler the worker pool drives
+    pub scheduler: Arc<Mutex<TaskScheduler>>,
+    /// Task events, balance changes and campaign progress for live streams
+    pub feed: LiveFeed,
+    pub tokens: Arc<RwLock<TokenStore>>,
+    /// Filled by the balance monitor, if one runs
+    pub balances: Arc<RwLock<BalanceCache>>,
+    /// Compute budget the executor sends with, to estimate fees by
+    pub fees: FeeSettings,
+}
+
+impl AppState {
+    pub fn new(wallets: WalletManager, scheduler: Arc<Mutex<TaskScheduler>>) -> Self {
+        Self {
+            wallets: Arc::new(RwLock::new(wallets)),
+            keystore: None,
+            scheduler,
+            feed: LiveFeed::default(),
+            tokens: Arc::default(),
+            balances: Arc::default(),
+            fees: FeeSettings::default(),
+        }
+    }
+
+    /// Saves the wallets to `keystore` after every change made through the API
+    pub fn with_keystore(mut self, keystore: Keystore) -> Self {
+        self.keystore = Some(Arc::new(keystore));
+        self
+    }
+
+    pub fn with_tokens(mut self, tokens: TokenStore) -> Self {
+        self.tokens = Arc::new(RwLock::new(tokens));
+        self
+    }
+
+    pub fn with_fees(mut self, fees: FeeSettings) -> Self {
+        self.fees = fees;
+        self
+    }
+}
@@ -0,0 +1,147 @@
+//! Log output for the server. Spans come from the engine and scheduler: every task
+//! is logged under a `task` span carrying its ID, wallet and campaign tag.
+//! Only public keys and signatures are ever recorded, never keypairs or their bytes.
+
+use crate::config::{LogFormat, LogSettings};
+use tracing::Subscriber;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::EnvFilter;
+
+/// Installs the global subscriber, writing to stdout
+pub fn init(settings: &LogSettings) -> Result<(), String> {
+    let subscriber = subscriber(settings, std::io::stdout)?;
+    tracing::subscriber::set_global_default(subscriber)
+        .map_err(|e| format!("Failed to install logger: {}", e))
+}
+
+/// A subscriber in the configured format and filter, writing to `writer`
+pub fn subscriber<W>(
+    settings: &LogSettings,
+    writer: W,
+) -> Result<Box<dyn Subscriber + Send + Sync>, String>
+where
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let filter = EnvFilter::try_new(&settings.filter)
+        .map_err(|e| format!("Invalid log filter '{}': {}", settings.filter, e))?;
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(writer);
+    Ok(match settings.format {
+        LogFormat::Pretty => Box::new(builder.pretty().finish()),
+        LogFormat::Json => Box::new(
+            builder
+                .json()
+                .flatten_event(true)
+                .with_current_span(true)
+                .with_span_list(true)
+                .finish(),
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine::transaction::{TransactionParams, TransferParams};
+    use engine::{TransactionBuilder, WalletManager};
+    use solana_sdk::hash::Hash;
+    use solana_sdk::pubkey::Pubkey;
+    use solana_sdk::signature::{Keypair, Signer};
+    use std::sync::{Arc, Mutex, RwLock};
+    use task_scheduler::{Task, TaskScheduler};
+
+    /// Collects everything written by the subscriber
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'w> MakeWriter<'w> for Captured {
+        type Writer = Captured;
+
+        fn make_writer(&'w self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    fn capture(format: LogFormat, run: impl FnOnce()) -> String {
+        let captured = Captured::default();
+        let settings = LogSettings {
+            format,
+            filter: "debug".to_string(),
+        };
+        let subscriber = subscriber(&settings, captured.clone()).unwrap();
+        tracing::subscriber::with_default(subscriber, run);
+        let bytes = captured.0.lock().unwrap().clone();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn test_task_lines_carry_task_wallet_and_campaign() {
+        let mut wallets = WalletManager::new();
+        let wallet = wallets.generate_wallet("payroll".to_string()).unwrap();
+        let wallets = Arc::new(RwLock::new(wallets));
+        let params = TransactionParams::Transfer(TransferParams {
+            from: wallet,
+            to: Pubkey::new_unique(),
+            lamports: 1,
+        });
+        let task = Task::new(serde_json::to_string(&params).unwrap()).with_tag("kamino");
+        let id = task.id().to_string();
+
+        let logs = capture(LogFormat::Json, || {
+            let mut scheduler = TaskScheduler::new().with_wallet_names(wallets);
+            scheduler.schedule_task(task).unwrap();
+            scheduler.execute_next().unwrap();
+            scheduler.complete_task(&id, "sig".to_string()).unwrap();
+        });
+
+        let lines: Vec<serde_json::Value> = logs
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert!(lines.len() >= 3, "{logs}");
+        for line in &lines {
+            assert_eq!(line["span"]["name"], "task");
+            assert_eq!(line["span"]["task_id"], id.as_str());
+            assert_eq!(line["span"]["wallet"], "payroll");
+            assert_eq!(line["span"]["pubkey"], wallet.to_string());
+            assert_eq!(line["span"]["campaign"], "kamino");
+        }
+        assert!(lines.iter().any(|line| line["message"] == "Task succeeded"));
+    }
+
+    #[test]
+    fn test_secret_keys_are_never_logged() {
+        let keypair = Keypair::new();
+        let secret_bytes = format!("{:?}", keypair.to_bytes().to_vec());
+        let secret_base58 = keypair.to_base58_string();
+
+        for format in [LogFormat::Pretty, LogFormat::Json] {
+            let logs = capture(format, || {
+                let mut wallets = WalletManager::new();
+                wallets
+                    .import_wallet("imported".to_string(), &keypair.to_bytes())
+                    .unwrap();
+                let payer = wallets.get_wallet("imported").unwrap();
+                TransactionBuilder::transfer(payer, &Pubkey::new_unique(), 1, Hash::default())
+                    .unwrap();
+                wallets.remove_wallet("imported").unwrap();
+            });
+
+            assert!(logs.contains(&keypair.pubkey().to_string()), "{logs}");
+            assert!(!logs.contains(&secret_base58));
+            assert!(!logs.contains(&secret_bytes));
+        }
+    }
+}
@@ -0,0 +1,307 @@
+//! Turns a treasury top-up plan into scheduled `BatchTransfer` tasks
+
+use crate::balances::{BalanceCache, BalanceSource};
+use crate::live::now_millis;
+use engine::transaction::{BatchTransferParams, TransactionParams};
+use engine::{FeeSettings, TopUpPlan, WalletManager};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use task_scheduler::{Task, TaskQuery, TaskScheduler, TaskStatus};
+
+/// What a top-up run scheduled
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TopUpReport {
+    pub treasury: Pubkey,
+    pub total_lamports: u64,
+    pub transfers: usize,
+    /// One `BatchTransfer` task per batch, tagged with its squad
+    pub tasks: Vec<Task>,
+}
+
+/// Balances a top-up is planned from, and when they were taken
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TopUpBalances {
+    pub lamports: HashMap<Pubkey, u64>,
+    pub rent_exempt_minimum: u64,
+    /// In ms since the Unix epoch
+    pub taken_at: u64,
+}
+
+impl TopUpBalances {
+    /// Plans top-ups from these balances, leaving the treasury its fees and rent.
+    /// Transfers still `in_flight` count as already received by their wallets
+    /// and already paid, with their fees, by the treasury.
+    pub fn plan(
+        &self,
+        wallets: &WalletManager,
+        fees: &FeeSettings,
+        in_flight: &[BatchTransferParams],
+    ) -> Result<TopUpPlan, String> {
+        let mut lamports = self.lamports.clone();
+        for batch in in_flight {
+            let mut paid = fees.estimate_fee(1, batch.transfers.len());
+            for (to, amount) in &batch.transfers {
+                let balance = lamports.entry(*to).or_default();
+                *balance = balance.saturating_add(*amount);
+                paid = paid.saturating_add(*amount);
+            }
+            if let Some(balance) = lamports.get_mut(&batch.from) {
+                *balance = balance.saturating_sub(paid);
+            }
+        }
+        wallets.plan_top_ups(&lamports, self.rent_exempt_minimum, fees)
+    }
+}
+
+/// Transfers from the treasury that the scheduler has yet to finish, so a new
+/// plan does not fund the same wallets again before they land
+pub fn in_flight_top_ups(
+    scheduler: &TaskScheduler,
+    wallets: &WalletManager,
+) -> Vec<BatchTransferParams> {
+    let Some(treasury) = wallets
+        .treasury()
+        .and_then(|name| wallets.get_pubkey(name).ok())
+    else {
+        return Vec::new();
+    };
+    let query = TaskQuery::new()
+        .status(TaskStatus::Pending)
+        .status(TaskStatus::InProgress)
+        .wallet(treasury)
+        .params_type("BatchTransfer")
+        .page(0, usize::MAX);
+    scheduler
+        .query(&query)
+        .tasks
+        .iter()
+        .filter_map(|task| match task.params() {
+            Ok(TransactionParams::BatchTransfer(params)) => Some(params),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Schedules each batch of the plan as a task. A batch is keyed by its exact
+/// transfers and the time of the balances it was planned from, so planning
+/// again from the same balances returns the tasks already scheduled instead of
+/// paying twice, while fresh balances plan anew even if the shortfalls repeat.
+pub fn schedule_top_ups(
+    scheduler: &mut TaskScheduler,
+    plan: &TopUpPlan,
+    balances_at: u64,
+) -> Result<TopUpReport, &'static str> {
+    let mut tasks = Vec::with_capacity(plan.batches.len());
+    for batch in &plan.batches {
+        let params = TransactionParams::BatchTransfer(batch.params.clone());
+        let params = serde_json::to_string(&params).map_err(|_| "Failed to serialize top-up")?;
+        let digest: String = Sha256::digest(params.as_bytes())
+            .iter()
+            .take(16)
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        let key = format!("top-up/{}/{}/{}", batch.squad, balances_at, digest);
+        tasks.push(
+            Task::new(params)
+                .with_tag(&batch.squad)
+                .with_idempotency_key(key),
+        );
+    }
+    let tasks = scheduler.schedule_batch(tasks)?;
+    Ok(TopUpReport {
+        treasury: plan.treasury,
+        total_lamports: plan.total_lamports,
+        transfers: plan.transfer_count(),
+        tasks,
+    })
+}
+
+/// The wallets a top-up plan needs balances for: the treasury and every member
+/// of a squad with a top-up policy
+pub fn top_up_wallets(wallets: &WalletManager) -> Result<Vec<Pubkey>, String> {
+    let mut names: Vec<String> = wallets.treasury().map(str::to_string).into_iter().collect();
+    for squad in wallets.list_squads() {
+        if squad.top_up.is_some() {
+            names.extend(squad.wallets.iter().cloned());
+        }
+    }
+    names.sort();
+    names.dedup();
+    Ok(wallets
+        .batch_get_pubkeys(&names)?
+        .into_iter()
+        .map(|(_, pubkey)| pubkey)
+        .collect())
+}
+
+/// Fetches fresh balances of the wallets a top-up plan needs
+pub async fn fetch_balances<S: BalanceSource>(
+    wallets: &WalletManager,
+    source: &S,
+) -> Result<TopUpBalances, String> {
+    let pubkeys = top_up_wallets(wallets)?;
+    let lamports = source.balances(&pubkeys).await?;
+    Ok(TopUpBalances {
+        lamports: pubkeys.into_iter().zip(lamports).collect(),
+        rent_exempt_minimum: source.rent_exempt_minimum().await?,
+        taken_at: now_millis(),
+    })
+}
+
+/// Balances as of the balance monitor's last refresh
+pub fn cached_balances(cache: &BalanceCache) -> Result<TopUpBalances, String> {
+    let (Some(taken_at), Some(rent_exempt_minimum)) =
+        (cache.refreshed_at, cache.rent_exempt_minimum)
+    else {
+        return Err("Balances have not been fetched yet".to_string());
+    };
+    Ok(TopUpBalances {
+        lamports: cache
+            .balances
+            .values()
+            .map(|cached| (cached.pubkey, cached.lamports))
+            .collect(),
+        rent_exempt_minimum,
+        taken_at,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine::TopUpPolicy;
+    use solana_sdk::native_token::LAMPORTS_PER_SOL;
+
+    fn planned() -> (WalletManager, TopUpPlan) {
+        let mut wallets = WalletManager::new();
+        wallets.generate_wallet("bank".to_string()).unwrap();
+        wallets.set_treasury("bank").unwrap();
+        let members = wallets.batch_generate("w", 3).unwrap();
+        wallets.create_squad("red".to_string(), None).unwrap();
+        wallets.assign_to_squad("red", &members).unwrap();
+        wallets
+            .set_squad_top_up(
+                "red",
+                Some(TopUpPolicy::new(10_000_000, 5_000_000).unwrap()),
+            )
+            .unwrap();
+        let plan = snapshot(&wallets, 1_000)
+            .plan(&wallets, &FeeSettings::default(), &[])
+            .unwrap();
+        (wallets, plan)
+    }
+
+    /// Every wallet holds 1 SOL but `w_0`, which is empty
+    fn snapshot(wallets: &WalletManager, taken_at: u64) -> TopUpBalances {
+        let lamports = top_up_wallets(wallets)
+            .unwrap()
+            .into_iter()
+            .map(|pubkey| (pubkey, LAMPORTS_PER_SOL))
+            .chain([(wallets.get_pubkey("w_0").unwrap(), 0)])
+            .collect();
+        TopUpBalances {
+            lamports,
+            rent_exempt_minimum: 890_880,
+            taken_at,
+        }
+    }
+
+    #[test]
+    fn test_top_up_wallets_are_treasury_and_policy_squads() {
+        let (mut wallets, _) = planned();
+        wallets.generate_wallet("loner".to_string()).unwrap();
+
+        let pubkeys = top_up_wallets(&wallets).unwrap();
+
+        assert_eq!(pubkeys.len(), 4);
+        assert!(!pubkeys.contains(&wallets.get_pubkey("loner").unwrap()));
+    }
+
+    #[test]
+    fn test_scheduled_top_ups_are_tagged_and_idempotent() {
+        let (wallets, plan) = planned();
+        let mut scheduler = TaskScheduler::new();
+
+        let first = schedule_top_ups(&mut scheduler, &plan, 1_000).unwrap();
+        let again = schedule_top_ups(&mut scheduler, &plan, 1_000).unwrap();
+
+        assert_eq!(first.transfers, 1);
+        assert_eq!(first.total_lamports, 10_000_000);
+        assert_eq!(first.tasks.len(), 1);
+        let task = &first.tasks[0];
+        assert_eq!(task.tag(), Some("red"));
+        assert_eq!(task.wallet(), Some(wallets.get_pubkey("bank").unwrap()));
+        assert_eq!(task.params().unwrap().type_name(), "BatchTransfer");
+        assert_eq!(again.tasks[0].id(), task.id());
+        assert_eq!(scheduler.get_pending_count(), 1);
+    }
+
+    #[test]
+    fn test_fresh_balances_do_not_repeat_a_top_up_in_flight() {
+        let (wallets, _) = planned();
+        let fees = FeeSettings::default();
+        let mut scheduler = TaskScheduler::new();
+        let top_up = |scheduler: &mut TaskScheduler, taken_at| {
+            let in_flight = in_flight_top_ups(scheduler, &wallets);
+            let plan = snapshot(&wallets, taken_at)
+                .plan(&wallets, &fees, &in_flight)
+                .unwrap();
+            schedule_top_ups(scheduler, &plan, taken_at).unwrap()
+        };
+
+        let first = top_up(&mut scheduler, 1_000);
+        // The monitor refreshed before the transfer landed
+        let while_in_flight = top_up(&mut scheduler, 2_000);
+
+        assert_eq!(first.tasks.len(), 1);
+        assert!(while_in_flight.tasks.is_empty());
+        assert_eq!(scheduler.get_pending_count(), 1);
+
+        let id = first.tasks[0].id().to_string();
+        scheduler.execute_next().unwrap();
+        scheduler.complete_task(&id, "sig".to_string()).unwrap();
+        // w_0 was emptied again after the first top-up landed
+        let after_landing = top_up(&mut scheduler, 3_000);
+
+        assert_eq!(after_landing.tasks.len(), 1);
+        assert_ne!(after_landing.tasks[0].id(), id);
+    }
+
+    #[test]
+    fn test_in_flight_transfers_are_charged_to_the_treasury() {
+        let (wallets, plan) = planned();
+        let treasury = wallets.get_pubkey("bank").unwrap();
+        let mut balances = snapshot(&wallets, 1_000);
+        // Enough for one top-up of 10_000_000 and its 5_000 fee, not two
+        balances.lamports.insert(treasury, 15_000_000);
+        balances
+            .lamports
+            .insert(wallets.get_pubkey("w_1").unwrap(), 0);
+
+        let error = balances
+            .plan(
+                &wallets,
+                &FeeSettings::default(),
+                &[plan.batches[0].params.clone()],
+            )
+            .unwrap_err();
+
+        assert!(error.contains("holds 4995000 lamports"), "{}", error);
+    }
+
+    #[test]
+    fn test_cached_balances_need_a_refresh() {
+        let mut cache = BalanceCache::new();
+        assert!(cached_balances(&cache).is_err());
+
+        cache.refreshed_at = Some(1_000);
+        cache.rent_exempt_minimum = Some(890_880);
+        let balances = cached_balances(&cache).unwrap();
+
+        assert_eq!(balances.taken_at, 1_000);
+        assert_eq!(balances.rent_exempt_minimum, 890_880);
+        assert!(balances.lamports.is_empty());
+    }
+}
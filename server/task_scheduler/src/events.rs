@@ -0,0 +1,252 @@
+use crate::task::{now_millis, Task};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use tokio::sync::broadcast;
+
+/// How many events a slow async subscriber may fall behind before it starts missing some
+pub const DEFAULT_EVENT_CAPACITY: usize = 1024;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TaskEventKind {
+    Scheduled,
+    Started,
+    /// An attempt failed and the task was requeued
+    Retrying,
+    Succeeded,
+    /// Retries are exhausted and the task was dead-lettered
+    Failed,
+    Cancelled,
+    /// An attempt outlived its execution lease
+    Expired,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TaskEvent {
+    pub task_id: String,
+    pub kind: TaskEventKind,
+    /// When the event happened, in ms since the Unix epoch
+    pub timestamp: u64,
+    /// When the task was created, in ms since the Unix epoch
+    pub created_at: u64,
+    pub tag: Option<String>,
+    /// Fee payer of the task's transaction
+    pub wallet: Option<Pubkey>,
+    pub signature: Option<String>,
+    pub error: Option<String>,
+}
+
+impl TaskEvent {
+    pub fn new(task: &Task, kind: TaskEventKind) -> Self {
+        Self {
+            task_id: task.id().to_string(),
+            kind,
+            timestamp: now_millis(),
+            created_at: task.created_at(),
+            tag: task.tag().map(str::to_string),
+            wallet: task.wallet(),
+            signature: None,
+            error: None,
+        }
+    }
+
+    pub fn with_signature(mut self, signature: impl Into<String>) -> Self {
+        self.signature = Some(signature.into());
+        self
+    }
+
+    pub fn with_error(mut self, error: impl Into<String>) -> Self {
+        self.error = Some(error.into());
+        self
+    }
+}
+
+type Hook = Box<dyn Fn(&TaskEvent) + Send + Sync>;
+
+/// Fans task lifecycle events out to async subscribers and synchronous hooks
+pub struct EventBus {
+    sender: broadcast::Sender<TaskEvent>,
+    hooks: Vec<Hook>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new(DEFAULT_EVENT_CAPACITY)
+    }
+}
+
+impl EventBus {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self {
+            sender,
+            hooks: Vec::new(),
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<TaskEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Hooks run inline on the emitting thread, so they should return quickly
+    pub fn add_hook(&mut self, hook: impl Fn(&TaskEvent) + Send + Sync + 'static) {
+        self.hooks.push(Box::new(hook));
+    }
+
+    pub fn emit(&self, event: TaskEvent) {
+        for hook in &self.hooks {
+            hook(&event);
+        }
+        // No subscribers is not an error
+        let _ = self.sender.send(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scheduler::TaskScheduler;
+    use std::sync::{Arc, Mutex};
+
+    fn drain(receiver: &mut broadcast::Receiver<TaskEvent>) -> Vec<(String, TaskEventKind)> {
+        std::iter::from_fn(|| receiver.try_recv().ok())
+            .map(|event| (event.task_id, event.kind))
+            .collect()
+    }
+
+    #[test]
+    fn test_successful_lifecycle_events() {
+        let mut scheduler = TaskScheduler::new();
+        let mut events = scheduler.subscribe();
+        let task = Task::new("{}".to_string());
+        let id = task.id().to_string();
+
+        scheduler.schedule_task(task).unwrap();
+        scheduler.execute_next().unwrap();
+        scheduler
+            .complete_task(&id, "5igna7ure".to_string())
+            .unwrap();
+
+        let received: Vec<TaskEvent> = std::iter::from_fn(|| events.try_recv().ok()).collect();
+        let kinds: Vec<TaskEventKind> = received.iter().map(|event| event.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TaskEventKind::Scheduled,
+                TaskEventKind::Started,
+                TaskEventKind::Succeeded
+            ]
+        );
+        assert!(received.iter().all(|event| event.task_id == id));
+        assert_eq!(received[2].signature.as_deref(), Some("5igna7ure"));
+        assert!(received[0].timestamp <= received[2].timestamp);
+    }
+
+    #[test]
+    fn test_retry_and_failure_events_carry_errors() {
+        let mut scheduler = TaskScheduler::new();
+        let task = Task::new("{}".to_string());
+        let id = task.id().to_string();
+        scheduler.schedule_task(task).unwrap();
+        let mut events = scheduler.subscribe();
+
+        for _ in 0..4 {
+            scheduler.execute_next().unwrap();
+            scheduler.fail_task(&id, "rpc error".to_string()).unwrap();
+        }
+
+        let received: Vec<TaskEvent> = std::iter::from_fn(|| events.try_recv().ok())
+            .filter(|event| event.kind != TaskEventKind::Started)
+            .collect();
+        let kinds: Vec<TaskEventKind> = received.iter().map(|event| event.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TaskEventKind::Retrying,
+                TaskEventKind::Retrying,
+                TaskEventKind::Retrying,
+                TaskEventKind::Failed
+            ]
+        );
+        assert!(received
+            .iter()
+            .all(|event| event.error.as_deref() == Some("rpc error")));
+    }
+
+    #[test]
+    fn test_cascaded_cancellation_emits_for_each_task() {
+        let mut scheduler = TaskScheduler::new();
+        let fund = Task::new("{}".to_string()).with_tag("kamino");
+        let deposit = Task::new("{}".to_string()).with_dependencies(vec![fund.id().to_string()]);
+        let (fund_id, deposit_id) = (fund.id().to_string(), deposit.id().to_string());
+        scheduler.schedule_batch(vec![fund, deposit]).unwrap();
+        let mut events = scheduler.subscribe();
+
+        scheduler.cancel_task(&fund_id).unwrap();
+
+        assert_eq!(
+            drain(&mut events),
+            vec![
+                (fund_id, TaskEventKind::Cancelled),
+                (deposit_id, TaskEventKind::Cancelled)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_event_tag_follows_task() {
+        let mut scheduler = TaskScheduler::new();
+        let mut events = scheduler.subscribe();
+
+        scheduler
+            .schedule_task(Task::new("{}".to_string()).with_tag("marginfi"))
+            .unwrap();
+
+        assert_eq!(events.try_recv().unwrap().tag.as_deref(), Some("marginfi"));
+    }
+
+    #[test]
+    fn test_sync_hooks_see_every_event() {
+        let mut scheduler = TaskScheduler::new();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        {
+            let seen = Arc::clone(&seen);
+            scheduler.on_event(move |event| seen.lock().unwrap().push(event.kind));
+        }
+        let task = Task::new("{}".to_string());
+        let id = task.id().to_string();
+
+        scheduler.schedule_task(task).unwrap();
+        scheduler.execute_next().unwrap();
+        scheduler.cancel_task(&id).unwrap();
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                TaskEventKind::Scheduled,
+                TaskEventKind::Started,
+                TaskEventKind::Cancelled
+            ]
+        );
+    }
+
+    #[test]
+    fn test_emit_without_subscribers() {
+        let bus = EventBus::default();
+        bus.emit(TaskEvent::new(
+            &Task::new("{}".to_string()),
+            TaskEventKind::Scheduled,
+        ));
+    }
+
+    #[test]
+    fn test_event_serialization() {
+        let event = TaskEvent::new(&Task::new("{}".to_string()), TaskEventKind::Succeeded)
+            .with_signature("sig");
+
+        let json = serde_json::to_string(&event).unwrap();
+        let restored: TaskEvent = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(event, restored);
+        assert!(json.contains(r#""kind":"Succeeded""#));
+    }
+}
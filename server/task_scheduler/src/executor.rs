@@ -0,0 +1,112 @@
+use crate::{scheduler::TaskScheduler, task::Task};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+/// What an executor observed while running one attempt of a task
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AttemptReport {
+    pub blockhash: Option<String>,
+    /// Set once the transaction was sent, whether or not it then failed
+    pub signature: Option<String>,
+    pub simulation_logs: Vec<String>,
+    pub fee_lamports: Option<u64>,
+    /// `None` means the attempt succeeded
+    pub error: Option<String>,
+    /// The RPC endpoint answered 429, so its rate limiter should back off
+    pub throttled: bool,
+}
+
+impl AttemptReport {
+    pub fn success(signature: impl Into<String>) -> Self {
+        Self {
+            signature: Some(signature.into()),
+            ..Self::default()
+        }
+    }
+
+    pub fn failure(error: impl Into<String>) -> Self {
+        Self {
+            error: Some(error.into()),
+            ..Self::default()
+        }
+    }
+
+    /// A failure caused by the RPC endpoint rejecting the request with a 429
+    pub fn throttled(error: impl Into<String>) -> Self {
+        Self {
+            throttled: true,
+            ..Self::failure(error)
+        }
+    }
+
+    pub fn with_blockhash(mut self, blockhash: impl Into<String>) -> Self {
+        self.blockhash = Some(blockhash.into());
+        self
+    }
+
+    pub fn with_signature(mut self, signature: impl Into<String>) -> Self {
+        self.signature = Some(signature.into());
+        self
+    }
+
+    pub fn with_simulation_logs(mut self, logs: Vec<String>) -> Self {
+        self.simulation_logs = logs;
+        self
+    }
+
+    pub fn with_fee(mut self, fee_lamports: u64) -> Self {
+        self.fee_lamports = Some(fee_lamports);
+        self
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Lets an executor record progress on the attempt it is running
+#[derive(Clone)]
+pub struct AttemptHandle {
+    scheduler: Arc<Mutex<TaskScheduler>>,
+    task_id: String,
+    attempt: usize,
+}
+
+impl AttemptHandle {
+    pub(crate) fn new(
+        scheduler: Arc<Mutex<TaskScheduler>>,
+        task_id: String,
+        attempt: usize,
+    ) -> Self {
+        Self {
+            scheduler,
+            task_id,
+            attempt,
+        }
+    }
+
+    /// Which attempt this is, numbered from 1
+    pub fn attempt(&self) -> usize {
+        self.attempt
+    }
+
+    /// Records the signature as soon as the transaction is sent, so the reaper can
+    /// check whether it landed if this attempt hangs afterwards
+    pub fn sent(&self, signature: impl Into<String>, blockhash: Option<String>) {
+        // The attempt may already have been resolved by the reaper
+        let _ = self
+            .scheduler
+            .lock()
+            .expect("scheduler lock poisoned")
+            .record_sent(&self.task_id, self.attempt, signature.into(), blockhash);
+    }
+}
+
+/// Turns a dispatched task into an on-chain transaction and reports how the attempt went
+pub trait TaskExecutor: Send + Sync + 'static {
+    fn execute(
+        &self,
+        task: Task,
+        attempt: AttemptHandle,
+    ) -> impl Future<Output = AttemptReport> + Send;
+}
@@ -1,7 +1,29 @@
+mod events;
+mod executor;
+pub mod metrics;
+mod query;
 mod queue;
+mod rate_limit;
+mod reaper;
 mod scheduler;
+mod schema;
+mod store;
 mod task;
+mod worker;
 
-pub use queue::TaskQueue;
-pub use scheduler::TaskScheduler;
-pub use task::Task;
+pub use events::{EventBus, TaskEvent, TaskEventKind, DEFAULT_EVENT_CAPACITY};
+pub use executor::{AttemptHandle, AttemptReport, TaskExecutor};
+pub use query::{TagProgress, TaskPage, TaskQuery, DEFAULT_PAGE_SIZE};
+pub use queue::{OverflowPolicy, QueueDepth, TaskQueue, DEFAULT_TAG_WEIGHT, QUEUE_FULL};
+pub use rate_limit::{
+    RateLimit, RateLimiter, TokenBucket, DEFAULT_INITIAL_BACKOFF, DEFAULT_MAX_BACKOFF,
+};
+pub use reaper::{LandingStatus, Reaper, ReaperConfig, ReaperHandle, SignatureChecker};
+pub use scheduler::{
+    TaskScheduler, DEFAULT_EXECUTION_TIMEOUT, DEFAULT_IDEMPOTENCY_WINDOW, DEFAULT_RETENTION,
+    INTERRUPTED, WRONG_CLUSTER,
+};
+pub use schema::{migrate_task, TASK_SCHEMA_VERSION};
+pub use store::{FileTaskStore, TaskStore};
+pub use task::{Task, TaskAttempt, TaskPriority, TaskStatus};
+pub use worker::{ShutdownReport, WorkerPool, WorkerPoolConfig, WorkerPoolHandle};
@@ -0,0 +1,226 @@
+//! Metrics recorded through the `metrics` facade. They go nowhere unless the
+//! embedding process installs a recorder, such as a Prometheus exporter.
+
+use crate::executor::AttemptReport;
+use crate::scheduler::INTERRUPTED;
+use crate::task::{Task, TaskPriority, TaskStatus};
+use metrics::{
+    counter, describe_counter, describe_gauge, describe_histogram, gauge, histogram, Unit,
+};
+use std::collections::HashMap;
+use std::time::Duration;
+
+pub const TASKS_SCHEDULED: &str = "sols_tasks_scheduled_total";
+/// Attempts by `outcome` (success or failure) and failure `error_class`
+pub const TASK_ATTEMPTS: &str = "sols_task_attempts_total";
+/// Tasks that reached a final state, by `status`
+pub const TASKS_FINISHED: &str = "sols_tasks_finished_total";
+/// Time from dispatch to report of one attempt, by `outcome`
+pub const ATTEMPT_DURATION: &str = "sols_task_attempt_duration_seconds";
+/// Time from creation to a final state, by `status`
+pub const TASK_LATENCY: &str = "sols_task_latency_seconds";
+pub const FEES_SPENT: &str = "sols_fees_lamports_total";
+/// Tracked tasks by `status` and `priority`, refreshed by `TaskScheduler::record_metrics`
+pub const TASKS: &str = "sols_tasks";
+/// Tasks waiting for a worker by `priority`, refreshed by `TaskScheduler::record_metrics`
+pub const QUEUE_DEPTH: &str = "sols_queue_depth";
+pub const DEAD_LETTERS: &str = "sols_dead_letters";
+/// Executor calls by `endpoint` and `result` (success, failure, throttled or timeout)
+pub const EXECUTIONS: &str = "sols_executions_total";
+pub const EXECUTION_DURATION: &str = "sols_execution_duration_seconds";
+
+/// Registers help text for every scheduler metric with the installed recorder
+pub fn describe() {
+    describe_counter!(TASKS_SCHEDULED, "Tasks accepted by the scheduler");
+    describe_counter!(TASK_ATTEMPTS, "Task attempts by outcome and error class");
+    describe_counter!(TASKS_FINISHED, "Tasks that reached a final state");
+    describe_histogram!(
+        ATTEMPT_DURATION,
+        Unit::Seconds,
+        "Duration of one task attempt"
+    );
+    describe_histogram!(
+        TASK_LATENCY,
+        Unit::Seconds,
+        "Time from task creation to its final state"
+    );
+    describe_counter!(FEES_SPENT, "Transaction fees paid, in lamports");
+    describe_gauge!(TASKS, "Tracked tasks by status and priority");
+    describe_gauge!(QUEUE_DEPTH, "Tasks ready to run, by priority");
+    describe_gauge!(DEAD_LETTERS, "Tasks in the dead-letter queue");
+    describe_counter!(EXECUTIONS, "Executor calls by endpoint and result");
+    describe_histogram!(
+        EXECUTION_DURATION,
+        Unit::Seconds,
+        "Time executors spent submitting transactions"
+    );
+}
+
+/// Buckets a failure message into a small, fixed set of label values
+pub fn error_class(error: &str) -> &'static str {
+    let error = error.to_ascii_lowercase();
+    let has = |needle: &str| error.contains(needle);
+    if error == INTERRUPTED.to_ascii_lowercase() {
+        "interrupted"
+    } else if has("429") || has("too many requests") || has("rate limit") {
+        "rate_limited"
+    } else if has("blockhash") {
+        "blockhash"
+    } else if has("insufficient") {
+        "insufficient_funds"
+    } else if has("simulation") {
+        "simulation"
+    } else if has("timed out") || has("timeout") || has("lease expired") {
+        "timeout"
+    } else if has("belongs to") {
+        "wrong_cluster"
+    } else if has("rpc") || has("connection") || has("network") {
+        "rpc"
+    } else {
+        "other"
+    }
+}
+
+pub(crate) fn record_scheduled(task: &Task) {
+    counter!(TASKS_SCHEDULED, "priority" => priority_label(task.priority())).increment(1);
+}
+
+/// Records one executor call; `report` is `None` when it timed out
+pub(crate) fn record_execution(
+    endpoint: Option<&str>,
+    report: Option<&AttemptReport>,
+    elapsed: Duration,
+) {
+    let endpoint = endpoint.unwrap_or("default").to_string();
+    let result = match report {
+        None => "timeout",
+        Some(report) if report.throttled => "throttled",
+        Some(report) if report.error.is_some() => "failure",
+        Some(_) => "success",
+    };
+    counter!(EXECUTIONS, "endpoint" => endpoint.clone(), "result" => result).increment(1);
+    histogram!(EXECUTION_DURATION, "endpoint" => endpoint).record(elapsed.as_secs_f64());
+}
+
+/// Records a closed attempt: its outcome, duration and fee
+pub(crate) fn record_attempt(task: &Task) {
+    let Some(attempt) = task.attempts().last() else {
+        return;
+    };
+    let outcome = match &attempt.error {
+        None => {
+            counter!(TASK_ATTEMPTS, "outcome" => "success", "error_class" => "none").increment(1);
+            "success"
+        }
+        Some(error) => {
+            counter!(TASK_ATTEMPTS, "outcome" => "failure", "error_class" => error_class(error))
+                .increment(1);
+            "failure"
+        }
+    };
+    if let Some(finished_at) = attempt.finished_at {
+        let seconds = finished_at.saturating_sub(attempt.started_at) as f64 / 1000.0;
+        histogram!(ATTEMPT_DURATION, "outcome" => outcome).record(seconds);
+    }
+    if let Some(fee) = attempt.fee_lamports {
+        counter!(FEES_SPENT).increment(fee);
+    }
+}
+
+/// Records a task reaching Completed, Failed or Cancelled
+pub(crate) fn record_finished(task: &Task, finished_at: u64) {
+    let status = status_label(task.status());
+    counter!(TASKS_FINISHED, "status" => status).increment(1);
+    let seconds = finished_at.saturating_sub(task.created_at()) as f64 / 1000.0;
+    histogram!(TASK_LATENCY, "status" => status).record(seconds);
+}
+
+/// Sets the task and queue gauges from a full view of the tracked tasks
+pub(crate) fn record_gauges<'a>(
+    tasks: impl Iterator<Item = &'a Task>,
+    is_ready: impl Fn(&Task) -> bool,
+    dead_letters: usize,
+) {
+    let mut by_status: HashMap<(TaskStatus, TaskPriority), usize> = HashMap::new();
+    let mut ready: HashMap<TaskPriority, usize> = HashMap::new();
+    for task in tasks {
+        *by_status
+            .entry((task.status(), task.priority()))
+            .or_default() += 1;
+        if is_ready(task) {
+            *ready.entry(task.priority()).or_default() += 1;
+        }
+    }
+    // Every combination is set, so counts that dropped to zero do not linger
+    for priority in PRIORITIES {
+        for status in STATUSES {
+            let count = by_status.get(&(status, priority)).copied().unwrap_or(0);
+            gauge!(TASKS, "status" => status_label(status), "priority" => priority_label(priority))
+                .set(count as f64);
+        }
+        let count = ready.get(&priority).copied().unwrap_or(0);
+        gauge!(QUEUE_DEPTH, "priority" => priority_label(priority)).set(count as f64);
+    }
+    gauge!(DEAD_LETTERS).set(dead_letters as f64);
+}
+
+const PRIORITIES: [TaskPriority; 4] = [
+    TaskPriority::Low,
+    TaskPriority::Normal,
+    TaskPriority::High,
+    TaskPriority::Critical,
+];
+
+const STATUSES: [TaskStatus; 5] = [
+    TaskStatus::Pending,
+    TaskStatus::InProgress,
+    TaskStatus::Completed,
+    TaskStatus::Failed,
+    TaskStatus::Cancelled,
+];
+
+fn priority_label(priority: TaskPriority) -> &'static str {
+    match priority {
+        TaskPriority::Low => "low",
+        TaskPriority::Normal => "normal",
+        TaskPriority::High => "high",
+        TaskPriority::Critical => "critical",
+    }
+}
+
+fn status_label(status: TaskStatus) -> &'static str {
+    match status {
+        TaskStatus::Pending => "pending",
+        TaskStatus::InProgress => "in_progress",
+        TaskStatus::Completed => "completed",
+        TaskStatus::Failed => "failed",
+        TaskStatus::Cancelled => "cancelled",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_classes() {
+        let cases = [
+            (INTERRUPTED, "interrupted"),
+            ("429 Too Many Requests", "rate_limited"),
+            ("Blockhash not found", "blockhash"),
+            (
+                "Transaction simulation failed: insufficient lamports",
+                "insufficient_funds",
+            ),
+            ("Transaction simulation failed", "simulation"),
+            ("Execution lease expired", "timeout"),
+            ("Task belongs to mainnet, not devnet", "wrong_cluster"),
+            ("RPC request failed: connection refused", "rpc"),
+            ("boom", "other"),
+        ];
+
+        for (error, class) in cases {
+            assert_eq!(error_class(error), class, "{}", error);
+        }
+    }
+}
@@ -0,0 +1,391 @@
+use crate::task::{Task, TaskPriority, TaskStatus};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+
+/// Filters for listing tracked tasks. Unset filters match everything;
+/// several statuses match any of them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskQuery {
+    pub statuses: Vec<TaskStatus>,
+    pub priority: Option<TaskPriority>,
+    pub wallet: Option<Pubkey>,
+    pub tag: Option<String>,
+    /// Inclusive lower bound on `created_at`, in ms since the Unix epoch
+    pub created_from: Option<u64>,
+    /// Exclusive upper bound on `created_at`, in ms since the Unix epoch
+    pub created_until: Option<u64>,
+    /// `TransactionParams` variant name, e.g. "Transfer"
+    pub params_type: Option<String>,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+/// One page of query results, oldest first
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TaskPage {
+    pub tasks: Vec<Task>,
+    /// Number of matching tasks across all pages
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+/// Task counts by status for one squad or campaign tag
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct TagProgress {
+    pub tag: String,
+    pub total: usize,
+    pub pending: usize,
+    pub in_progress: usize,
+    pub completed: usize,
+    pub failed: usize,
+    pub cancelled: usize,
+}
+
+impl TagProgress {
+    pub(crate) fn count<'a>(tag: &str, tasks: impl Iterator<Item = &'a Task>) -> Self {
+        let mut progress = TagProgress {
+            tag: tag.to_string(),
+            ..TagProgress::default()
+        };
+        for task in tasks.filter(|task| task.tag() == Some(tag)) {
+            progress.total += 1;
+            match task.status() {
+                TaskStatus::Pending => progress.pending += 1,
+                TaskStatus::InProgress => progress.in_progress += 1,
+                TaskStatus::Completed => progress.completed += 1,
+                TaskStatus::Failed => progress.failed += 1,
+                TaskStatus::Cancelled => progress.cancelled += 1,
+            }
+        }
+        progress
+    }
+}
+
+impl Default for TaskQuery {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TaskQuery {
+    pub fn new() -> Self {
+        Self {
+            statuses: Vec::new(),
+            priority: None,
+            wallet: None,
+            tag: None,
+            created_from: None,
+            created_until: None,
+            params_type: None,
+            offset: 0,
+            limit: DEFAULT_PAGE_SIZE,
+        }
+    }
+
+    pub fn status(mut self, status: TaskStatus) -> Self {
+        self.statuses.push(status);
+        self
+    }
+
+    pub fn priority(mut self, priority: TaskPriority) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    pub fn wallet(mut self, wallet: Pubkey) -> Self {
+        self.wallet = Some(wallet);
+        self
+    }
+
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tag = Some(tag.into());
+        self
+    }
+
+    pub fn created_between(mut self, from: u64, until: u64) -> Self {
+        self.created_from = Some(from);
+        self.created_until = Some(until);
+        self
+    }
+
+    pub fn params_type(mut self, params_type: impl Into<String>) -> Self {
+        self.params_type = Some(params_type.into());
+        self
+    }
+
+    pub fn page(mut self, offset: usize, limit: usize) -> Self {
+        self.offset = offset;
+        self.limit = limit;
+        self
+    }
+
+    pub fn matches(&self, task: &Task) -> bool {
+        if !self.statuses.is_empty() && !self.statuses.contains(&task.status()) {
+            return false;
+        }
+        if self
+            .priority
+            .is_some_and(|priority| priority != task.priority())
+        {
+            return false;
+        }
+        if self.tag.is_some() && self.tag.as_deref() != task.tag() {
+            return false;
+        }
+        if self
+            .created_from
+            .is_some_and(|from| task.created_at() < from)
+        {
+            return false;
+        }
+        if self
+            .created_until
+            .is_some_and(|until| task.created_at() >= until)
+        {
+            return false;
+        }
+        if self.wallet.is_none() && self.params_type.is_none() {
+            return true;
+        }
+
+        // Only decode params when a filter needs them
+        let Ok(params) = task.params() else {
+            return false;
+        };
+        if self
+            .wallet
+            .is_some_and(|wallet| wallet != params.fee_payer())
+        {
+            return false;
+        }
+        self.params_type
+            .as_deref()
+            .is_none_or(|params_type| params_type == params.type_name())
+    }
+
+    /// Filters, orders by creation time and paginates
+    pub(crate) fn run<'a>(&self, tasks: impl Iterator<Item = &'a Task>) -> TaskPage {
+        let mut matching: Vec<&Task> = tasks.filter(|task| self.matches(task)).collect();
+        matching.sort_by(|a, b| {
+            a.created_at()
+                .cmp(&b.created_at())
+                .then_with(|| a.id().cmp(b.id()))
+        });
+
+        TaskPage {
+            total: matching.len(),
+            tasks: matching
+                .into_iter()
+                .skip(self.offset)
+                .take(self.limit)
+                .cloned()
+                .collect(),
+            offset: self.offset,
+            limit: self.limit,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scheduler::TaskScheduler;
+    use crate::task::now_millis;
+    use engine::transaction::{
+        BatchTransferParams, CreateAccountParams, TransactionParams, TransferParams,
+    };
+
+    fn transfer_from(from: Pubkey) -> Task {
+        let params = TransactionParams::Transfer(TransferParams {
+            from,
+            to: Pubkey::new_unique(),
+            lamports: 1_000_000,
+        });
+        Task::new(serde_json::to_string(&params).unwrap())
+    }
+
+    fn create_account() -> Task {
+        let params = TransactionParams::CreateAccount(CreateAccountParams {
+            payer: Pubkey::new_unique(),
+            new_account: Pubkey::new_unique(),
+            owner: Pubkey::new_unique(),
+            lamports: 1_000_000,
+            space: 0,
+        });
+        Task::new(serde_json::to_string(&params).unwrap())
+    }
+
+    fn batch_transfer() -> Task {
+        let params = TransactionParams::BatchTransfer(BatchTransferParams {
+            from: Pubkey::new_unique(),
+            transfers: vec![(Pubkey::new_unique(), 1)],
+        });
+        Task::new(serde_json::to_string(&params).unwrap())
+    }
+
+    #[test]
+    fn test_query_by_status() {
+        let mut scheduler = TaskScheduler::new();
+        for _ in 0..3 {
+            scheduler
+                .schedule_task(transfer_from(Pubkey::new_unique()))
+                .unwrap();
+        }
+        let running = scheduler.execute_next().unwrap();
+
+        let in_progress = scheduler.query(&TaskQuery::new().status(TaskStatus::InProgress));
+        let active = scheduler.query(
+            &TaskQuery::new()
+                .status(TaskStatus::Pending)
+                .status(TaskStatus::InProgress),
+        );
+
+        assert_eq!(in_progress.total, 1);
+        assert_eq!(in_progress.tasks[0].id(), running.id());
+        assert_eq!(active.total, 3);
+    }
+
+    #[test]
+    fn test_query_by_priority_and_tag() {
+        let mut scheduler = TaskScheduler::new();
+        let urgent = transfer_from(Pubkey::new_unique())
+            .with_priority(TaskPriority::Critical)
+            .with_tag("kamino");
+        scheduler.schedule_task(urgent.clone()).unwrap();
+        scheduler
+            .schedule_task(transfer_from(Pubkey::new_unique()).with_tag("kamino"))
+            .unwrap();
+        scheduler
+            .schedule_task(
+                transfer_from(Pubkey::new_unique()).with_priority(TaskPriority::Critical),
+            )
+            .unwrap();
+
+        let page = scheduler.query(
+            &TaskQuery::new()
+                .priority(TaskPriority::Critical)
+                .tag("kamino"),
+        );
+
+        assert_eq!(page.total, 1);
+        assert_eq!(page.tasks, vec![urgent]);
+    }
+
+    #[test]
+    fn test_query_by_wallet() {
+        let mut scheduler = TaskScheduler::new();
+        let wallet = Pubkey::new_unique();
+        scheduler.schedule_task(transfer_from(wallet)).unwrap();
+        scheduler.schedule_task(transfer_from(wallet)).unwrap();
+        scheduler
+            .schedule_task(transfer_from(Pubkey::new_unique()))
+            .unwrap();
+
+        let page = scheduler.query(&TaskQuery::new().wallet(wallet));
+
+        assert_eq!(page.total, 2);
+        assert!(page.tasks.iter().all(|task| task.wallet() == Some(wallet)));
+    }
+
+    #[test]
+    fn test_query_by_params_type() {
+        let mut scheduler = TaskScheduler::new();
+        scheduler
+            .schedule_task(transfer_from(Pubkey::new_unique()))
+            .unwrap();
+        scheduler.schedule_task(create_account()).unwrap();
+        scheduler.schedule_task(batch_transfer()).unwrap();
+        scheduler
+            .schedule_task(Task::new("not params".to_string()))
+            .unwrap();
+
+        let page = scheduler.query(&TaskQuery::new().params_type("CreateAccount"));
+
+        assert_eq!(page.total, 1);
+        assert_eq!(page.tasks[0].params().unwrap().type_name(), "CreateAccount");
+    }
+
+    #[test]
+    fn test_query_by_creation_time() {
+        let mut scheduler = TaskScheduler::new();
+        scheduler
+            .schedule_task(transfer_from(Pubkey::new_unique()))
+            .unwrap();
+        let now = now_millis();
+
+        let recent = scheduler.query(&TaskQuery::new().created_between(0, now + 1));
+        let future = scheduler.query(&TaskQuery::new().created_between(now + 1, u64::MAX));
+
+        assert_eq!(recent.total, 1);
+        assert_eq!(future.total, 0);
+    }
+
+    #[test]
+    fn test_query_pagination() {
+        let mut scheduler = TaskScheduler::new();
+        for _ in 0..7 {
+            scheduler
+                .schedule_task(transfer_from(Pubkey::new_unique()))
+                .unwrap();
+        }
+
+        let pages: Vec<TaskPage> = (0..3)
+            .map(|page| scheduler.query(&TaskQuery::new().page(page * 3, 3)))
+            .collect();
+
+        assert_eq!(pages[0].tasks.len(), 3);
+        assert_eq!(pages[1].tasks.len(), 3);
+        assert_eq!(pages[2].tasks.len(), 1);
+        assert!(pages.iter().all(|page| page.total == 7));
+
+        let ids: std::collections::HashSet<&str> = pages
+            .iter()
+            .flat_map(|page| page.tasks.iter().map(|task| task.id()))
+            .collect();
+        assert_eq!(ids.len(), 7);
+    }
+
+    #[test]
+    fn test_lookup_task_by_id() {
+        let mut scheduler = TaskScheduler::new();
+        let task = transfer_from(Pubkey::new_unique());
+        scheduler.schedule_task(task.clone()).unwrap();
+
+        let found = scheduler.task(task.id()).unwrap();
+
+        assert_eq!(found.status(), TaskStatus::Pending);
+        assert_eq!(found.transaction_params(), task.transaction_params());
+        assert_eq!(found.max_retries(), 3);
+        assert!(found.last_attempt().is_none());
+        assert!(scheduler.task("missing").is_none());
+    }
+
+    #[test]
+    fn test_progress_counts_tagged_tasks_by_status() {
+        let mut scheduler = TaskScheduler::new();
+        for _ in 0..3 {
+            scheduler
+                .schedule_task(Task::new("{}".to_string()).with_tag("kamino"))
+                .unwrap();
+        }
+        scheduler
+            .schedule_task(Task::new("{}".to_string()).with_tag("marginfi"))
+            .unwrap();
+        let first = scheduler.execute_next().unwrap();
+        scheduler
+            .complete_task(first.id(), "5igna7ure".to_string())
+            .unwrap();
+        scheduler.execute_next().unwrap();
+
+        let progress = scheduler.progress("kamino");
+
+        assert_eq!(progress.tag, "kamino");
+        assert_eq!(progress.total, 3);
+        assert_eq!(progress.completed, 1);
+        assert_eq!(progress.in_progress + progress.pending, 2);
+        assert_eq!(scheduler.progress("unknown").total, 0);
+    }
+}
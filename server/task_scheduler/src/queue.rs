@@ -1,34 +1,589 @@
-use crate::task::Task;
-use crossbeam::queue::SegQueue;
+use crate::task::{Task, TaskPriority};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, RwLock};
+use tokio::sync::{futures::Notified, Notify};
 
+pub const QUEUE_FULL: &str = "Queue is bounded and it's full";
+
+/// Share of dispatches a tag gets when no weight was configured for it.
+/// Untagged tasks always share at this weight.
+pub const DEFAULT_TAG_WEIGHT: u32 = 1;
+
+/// What a bounded queue does when a task arrives and it is already full
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Refuse the new task
+    Reject,
+    /// Make producers wait for space; synchronous enqueues are refused
+    Block,
+    /// Evict the oldest task of the lowest priority, if it ranks below the new one
+    DropLowestPriority,
+}
+
+/// Queue depth against capacity, for producers that want to throttle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueueDepth {
+    pub queued: usize,
+    pub capacity: Option<usize>,
+}
+
+impl QueueDepth {
+    /// Free slots left, or `None` for an unbounded queue
+    pub fn remaining(&self) -> Option<usize> {
+        self.capacity
+            .map(|capacity| capacity.saturating_sub(self.queued))
+    }
+}
+
+/// Task queue with one lane per priority. Higher lanes always go first; within a
+/// lane, squad and campaign tags share dispatches by weighted fair queuing, and
+/// each tag is served in FIFO order.
 pub struct TaskQueue {
-    inner: SegQueue<Task>,
+    lanes: [Mutex<Lane>; 4],
+    weights: RwLock<HashMap<String, u32>>,
+    len: AtomicUsize,
     max_size: Option<usize>,
+    policy: OverflowPolicy,
+    space: Notify,
+}
+
+impl Default for TaskQueue {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl TaskQueue {
     pub fn new() -> Self {
         Self {
-            inner: SegQueue::new(),
+            lanes: Default::default(),
+            weights: RwLock::new(HashMap::new()),
+            len: AtomicUsize::new(0),
             max_size: None,
+            policy: OverflowPolicy::Reject,
+            space: Notify::new(),
+        }
+    }
+
+    pub fn with_capacity(max_size: usize, policy: OverflowPolicy) -> Self {
+        Self {
+            max_size: Some(max_size),
+            policy,
+            ..Self::new()
+        }
+    }
+
+    /// Sets a tag's share of dispatches relative to other tags at the same priority
+    pub fn with_tag_weight(self, tag: impl Into<String>, weight: u32) -> Self {
+        self.set_tag_weight(tag, weight);
+        self
+    }
+
+    /// Changes a tag's weight at runtime. A tag with weight 3 is dispatched three
+    /// times as often as one with weight 1 while both have work queued. Weights
+    /// below 1 are raised to 1.
+    pub fn set_tag_weight(&self, tag: impl Into<String>, weight: u32) {
+        self.weights
+            .write()
+            .expect("tag weights poisoned")
+            .insert(tag.into(), weight.max(1));
+    }
+
+    pub fn tag_weight(&self, tag: &str) -> u32 {
+        self.weights
+            .read()
+            .expect("tag weights poisoned")
+            .get(tag)
+            .copied()
+            .unwrap_or(DEFAULT_TAG_WEIGHT)
+    }
+
+    /// Adds a task, applying the overflow policy if the queue is full.
+    /// Returns the task evicted to make room, if any.
+    pub fn enqueue(&self, task: Task) -> Result<Option<Task>, &'static str> {
+        if self.try_reserve() {
+            self.push(task);
+            return Ok(None);
+        }
+
+        match self.policy {
+            OverflowPolicy::Reject | OverflowPolicy::Block => Err(QUEUE_FULL),
+            OverflowPolicy::DropLowestPriority => {
+                let evicted = self.evict_below(task.priority()).ok_or(QUEUE_FULL)?;
+                // The evicted task's slot goes straight to the new one
+                self.push(task);
+                Ok(Some(evicted))
+            }
         }
     }
 
-    pub fn enqueue(&mut self, task: Task) -> Result<(), &'static str> {
-        if let Some(max) = self.max_size {
-            if self.len() >= max {
-                return Err("Queue is bounded and it's full");
+    /// Waits for space under the `Block` policy; other policies behave like `enqueue`
+    pub async fn enqueue_async(&self, task: Task) -> Result<Option<Task>, &'static str> {
+        if self.policy != OverflowPolicy::Block {
+            return self.enqueue(task);
+        }
+        loop {
+            let space = self.space_notified();
+            tokio::pin!(space);
+            space.as_mut().enable();
+
+            if self.try_reserve() {
+                self.push(task);
+                return Ok(None);
             }
+            space.await;
         }
-        self.inner.push(task);
-        Ok(())
     }
 
-    pub fn dequeue(&mut self) -> Option<Task> {
-        self.inner.pop()
+    /// Resolves once a slot may have freed up. Enable it before checking
+    /// for space, otherwise a wakeup can be missed.
+    pub fn space_notified(&self) -> Notified<'_> {
+        self.space.notified()
+    }
+
+    /// Puts back work that was already admitted (retries, released dependents),
+    /// ignoring capacity so it is never lost to backpressure
+    pub(crate) fn requeue(&self, task: Task) {
+        self.len.fetch_add(1, Ordering::SeqCst);
+        self.push(task);
+    }
+
+    pub fn dequeue(&self) -> Option<Task> {
+        let task = self.lanes.iter().rev().find_map(|lane| {
+            let mut lane = lane.lock().expect("queue lane poisoned");
+            lane.pop(|tag| self.tag_weight(tag))
+        })?;
+        self.release();
+        Some(task)
+    }
+
+    /// Takes a task out of the queue wherever it waits, freeing its slot
+    pub(crate) fn remove(&self, id: &str) -> Option<Task> {
+        let task = self
+            .lanes
+            .iter()
+            .find_map(|lane| lane.lock().expect("queue lane poisoned").remove(id))?;
+        self.release();
+        Some(task)
+    }
+
+    /// Queued tasks per priority, lowest first
+    pub(crate) fn lane_lengths(&self) -> [usize; 4] {
+        std::array::from_fn(|priority| {
+            self.lanes[priority]
+                .lock()
+                .expect("queue lane poisoned")
+                .len()
+        })
     }
 
     pub fn len(&self) -> usize {
-        self.inner.len()
+        self.len.load(Ordering::SeqCst)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> Option<usize> {
+        self.max_size
+    }
+
+    pub fn policy(&self) -> OverflowPolicy {
+        self.policy
+    }
+
+    pub fn depth(&self) -> QueueDepth {
+        QueueDepth {
+            queued: self.len(),
+            capacity: self.max_size,
+        }
+    }
+
+    fn try_reserve(&self) -> bool {
+        let Some(max) = self.max_size else {
+            self.len.fetch_add(1, Ordering::SeqCst);
+            return true;
+        };
+        self.len
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |len| {
+                (len < max).then_some(len + 1)
+            })
+            .is_ok()
+    }
+
+    fn push(&self, task: Task) {
+        self.lanes[task.priority() as usize]
+            .lock()
+            .expect("queue lane poisoned")
+            .push(task);
+    }
+
+    fn release(&self) {
+        self.len.fetch_sub(1, Ordering::SeqCst);
+        self.space.notify_waiters();
+    }
+
+    fn evict_below(&self, priority: TaskPriority) -> Option<Task> {
+        self.lanes[..priority as usize]
+            .iter()
+            .find_map(|lane| lane.lock().expect("queue lane poisoned").pop_oldest())
+    }
+}
+
+/// One priority level, holding a FIFO flow per tag. Flows are served by stride
+/// scheduling: each has a pass that advances by 1/weight per dispatch, and the
+/// flow with the lowest pass goes next.
+#[derive(Default)]
+struct Lane {
+    flows: HashMap<Option<String>, Flow>,
+    // Pass of the last dispatch. Flows that went idle restart no lower than this,
+    // so idle time does not bank credit.
+    virtual_time: f64,
+    next_seq: u64,
+}
+
+struct Flow {
+    // (enqueue sequence, task)
+    tasks: VecDeque<(u64, Task)>,
+    pass: f64,
+}
+
+impl Lane {
+    fn push(&mut self, task: Task) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        let virtual_time = self.virtual_time;
+        let flow = self
+            .flows
+            .entry(task.tag().map(str::to_string))
+            .or_insert_with(|| Flow {
+                tasks: VecDeque::new(),
+                pass: virtual_time,
+            });
+        if flow.tasks.is_empty() {
+            flow.pass = flow.pass.max(virtual_time);
+        }
+        flow.tasks.push_back((seq, task));
+    }
+
+    fn pop(&mut self, weight: impl Fn(&str) -> u32) -> Option<Task> {
+        // Ties go to the flow holding the oldest task
+        let key = self
+            .flows
+            .iter()
+            .filter_map(|(key, flow)| Some((key, flow.pass, flow.tasks.front()?.0)))
+            .min_by(|a, b| a.1.total_cmp(&b.1).then(a.2.cmp(&b.2)))
+            .map(|(key, _, _)| key.clone())?;
+
+        let flow = self.flows.get_mut(&key)?;
+        let (_, task) = flow.tasks.pop_front()?;
+        self.virtual_time = flow.pass;
+        let weight = key.as_deref().map_or(DEFAULT_TAG_WEIGHT, weight);
+        flow.pass += 1.0 / f64::from(weight);
+
+        // Idle flows that have no credit left to keep are forgotten
+        let virtual_time = self.virtual_time;
+        self.flows
+            .retain(|_, flow| !flow.tasks.is_empty() || flow.pass > virtual_time);
+        Some(task)
+    }
+
+    fn remove(&mut self, id: &str) -> Option<Task> {
+        self.flows.values_mut().find_map(|flow| {
+            let position = flow.tasks.iter().position(|(_, task)| task.id() == id)?;
+            flow.tasks.remove(position).map(|(_, task)| task)
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.flows.values().map(|flow| flow.tasks.len()).sum()
+    }
+
+    /// Removes the task that has waited longest, regardless of tag
+    fn pop_oldest(&mut self) -> Option<Task> {
+        let key = self
+            .flows
+            .iter()
+            .filter_map(|(key, flow)| Some((key, flow.tasks.front()?.0)))
+            .min_by_key(|(_, seq)| *seq)
+            .map(|(key, _)| key.clone())?;
+        let (_, task) = self.flows.get_mut(&key)?.tasks.pop_front()?;
+        Some(task)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_queue_shared_across_threads() {
+        let queue = Arc::new(TaskQueue::new());
+
+        let producers: Vec<_> = (0..4)
+            .map(|_| {
+                let queue = Arc::clone(&queue);
+                thread::spawn(move || {
+                    for _ in 0..25 {
+                        queue.enqueue(Task::new("{}".to_string())).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for producer in producers {
+            producer.join().unwrap();
+        }
+        assert_eq!(queue.len(), 100);
+
+        let consumers: Vec<_> = (0..4)
+            .map(|_| {
+                let queue = Arc::clone(&queue);
+                thread::spawn(move || {
+                    let mut taken = 0;
+                    while queue.dequeue().is_some() {
+                        taken += 1;
+                    }
+                    taken
+                })
+            })
+            .collect();
+        let taken: usize = consumers.into_iter().map(|c| c.join().unwrap()).sum();
+
+        assert_eq!(taken, 100);
+        assert!(queue.is_empty());
+    }
+
+    fn task(priority: TaskPriority) -> Task {
+        Task::new("{}".to_string()).with_priority(priority)
+    }
+
+    #[test]
+    fn test_dequeue_by_priority_then_fifo() {
+        let queue = TaskQueue::new();
+        let low = task(TaskPriority::Low);
+        let normal_1 = task(TaskPriority::Normal);
+        let normal_2 = task(TaskPriority::Normal);
+        let critical = task(TaskPriority::Critical);
+        for task in [&low, &normal_1, &normal_2, &critical] {
+            queue.enqueue(task.clone()).unwrap();
+        }
+
+        let order: Vec<Task> = std::iter::from_fn(|| queue.dequeue()).collect();
+
+        assert_eq!(order, vec![critical, normal_1, normal_2, low]);
+    }
+
+    #[test]
+    fn test_bounded_queue_rejects_when_full() {
+        let queue = TaskQueue::with_capacity(2, OverflowPolicy::Reject);
+        queue.enqueue(task(TaskPriority::Normal)).unwrap();
+        queue.enqueue(task(TaskPriority::Normal)).unwrap();
+
+        assert_eq!(queue.enqueue(task(TaskPriority::Critical)), Err(QUEUE_FULL));
+        assert_eq!(
+            queue.depth(),
+            QueueDepth {
+                queued: 2,
+                capacity: Some(2)
+            }
+        );
+        assert_eq!(queue.depth().remaining(), Some(0));
+    }
+
+    #[test]
+    fn test_unbounded_queue_reports_no_capacity() {
+        let queue = TaskQueue::new();
+        queue.enqueue(task(TaskPriority::Normal)).unwrap();
+
+        assert_eq!(queue.capacity(), None);
+        assert_eq!(queue.depth().remaining(), None);
+        assert_eq!(queue.depth().queued, 1);
+    }
+
+    #[test]
+    fn test_drop_lowest_priority_evicts_oldest_lowest() {
+        let queue = TaskQueue::with_capacity(3, OverflowPolicy::DropLowestPriority);
+        let low_old = task(TaskPriority::Low);
+        let low_new = task(TaskPriority::Low);
+        let normal = task(TaskPriority::Normal);
+        for task in [&low_old, &low_new, &normal] {
+            queue.enqueue(task.clone()).unwrap();
+        }
+
+        let high = task(TaskPriority::High);
+        let evicted = queue.enqueue(high.clone()).unwrap();
+
+        assert_eq!(evicted, Some(low_old));
+        assert_eq!(queue.len(), 3);
+        assert_eq!(queue.dequeue(), Some(high));
+    }
+
+    #[test]
+    fn test_drop_lowest_priority_rejects_when_nothing_ranks_lower() {
+        let queue = TaskQueue::with_capacity(1, OverflowPolicy::DropLowestPriority);
+        queue.enqueue(task(TaskPriority::Normal)).unwrap();
+
+        assert_eq!(queue.enqueue(task(TaskPriority::Normal)), Err(QUEUE_FULL));
+        assert_eq!(queue.enqueue(task(TaskPriority::Low)), Err(QUEUE_FULL));
+    }
+
+    #[test]
+    fn test_block_policy_refuses_synchronous_enqueue() {
+        let queue = TaskQueue::with_capacity(1, OverflowPolicy::Block);
+        queue.enqueue(task(TaskPriority::Normal)).unwrap();
+
+        assert_eq!(queue.enqueue(task(TaskPriority::Normal)), Err(QUEUE_FULL));
+    }
+
+    #[tokio::test]
+    async fn test_block_policy_waits_for_space() {
+        let queue = Arc::new(TaskQueue::with_capacity(1, OverflowPolicy::Block));
+        queue.enqueue(task(TaskPriority::Normal)).unwrap();
+
+        let producer = {
+            let queue = Arc::clone(&queue);
+            tokio::spawn(async move { queue.enqueue_async(task(TaskPriority::High)).await })
+        };
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        assert!(!producer.is_finished());
+
+        queue.dequeue().unwrap();
+        let result = tokio::time::timeout(std::time::Duration::from_secs(1), producer)
+            .await
+            .expect("producer stayed blocked")
+            .unwrap();
+
+        assert_eq!(result, Ok(None));
+        assert_eq!(queue.len(), 1);
+    }
+
+    fn tagged(tag: &str) -> Task {
+        Task::new("{}".to_string()).with_tag(tag)
+    }
+
+    fn drain_tags(queue: &TaskQueue, count: usize) -> Vec<String> {
+        (0..count)
+            .map(|_| queue.dequeue().unwrap().tag().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_large_campaign_does_not_starve_small_one() {
+        let queue = TaskQueue::new();
+        for _ in 0..1000 {
+            queue.enqueue(tagged("airdrop")).unwrap();
+        }
+        for _ in 0..3 {
+            queue.enqueue(tagged("liquidation")).unwrap();
+        }
+
+        let first = drain_tags(&queue, 6);
+
+        assert_eq!(first.iter().filter(|tag| *tag == "liquidation").count(), 3);
+    }
+
+    #[test]
+    fn test_weights_split_dispatches() {
+        let queue = TaskQueue::new().with_tag_weight("kamino", 3);
+        for _ in 0..20 {
+            queue.enqueue(tagged("kamino")).unwrap();
+            queue.enqueue(tagged("marginfi")).unwrap();
+        }
+
+        let first = drain_tags(&queue, 8);
+
+        assert_eq!(first.iter().filter(|tag| *tag == "kamino").count(), 6);
+        assert_eq!(queue.tag_weight("kamino"), 3);
+        assert_eq!(queue.tag_weight("marginfi"), DEFAULT_TAG_WEIGHT);
+    }
+
+    #[test]
+    fn test_priority_outranks_tag_weight() {
+        let queue = TaskQueue::new().with_tag_weight("kamino", 100);
+        queue.enqueue(tagged("kamino")).unwrap();
+        let urgent = tagged("marginfi").with_priority(TaskPriority::High);
+        queue.enqueue(urgent.clone()).unwrap();
+
+        assert_eq!(queue.dequeue().unwrap().id(), urgent.id());
+    }
+
+    #[test]
+    fn test_each_tag_stays_fifo() {
+        let queue = TaskQueue::new();
+        let tasks: Vec<Task> = (0..4).map(|_| tagged("kamino")).collect();
+        for task in &tasks {
+            queue.enqueue(task.clone()).unwrap();
+            queue.enqueue(tagged("marginfi")).unwrap();
+        }
+
+        let kamino: Vec<String> = std::iter::from_fn(|| queue.dequeue())
+            .filter(|task| task.tag() == Some("kamino"))
+            .map(|task| task.id().to_string())
+            .collect();
+
+        let expected: Vec<String> = tasks.iter().map(|task| task.id().to_string()).collect();
+        assert_eq!(kamino, expected);
+    }
+
+    #[test]
+    fn test_trickling_tag_does_not_bank_credit() {
+        let queue = TaskQueue::new();
+        for _ in 0..10 {
+            queue.enqueue(tagged("airdrop")).unwrap();
+        }
+
+        // "drip" never has more than one task waiting, but keeps coming back
+        let mut served = Vec::new();
+        for _ in 0..10 {
+            queue.enqueue(tagged("drip")).unwrap();
+            served.push(queue.dequeue().unwrap().tag().unwrap().to_string());
+        }
+
+        let airdrop = served.iter().filter(|tag| *tag == "airdrop").count();
+        assert!((4..=6).contains(&airdrop), "airdrop got {airdrop} of 10");
+    }
+
+    #[test]
+    fn test_untagged_tasks_share_with_tagged() {
+        let queue = TaskQueue::new();
+        for _ in 0..5 {
+            queue.enqueue(tagged("airdrop")).unwrap();
+        }
+        queue.enqueue(Task::new("{}".to_string())).unwrap();
+
+        let untagged_at = (0..6)
+            .position(|_| queue.dequeue().unwrap().tag().is_none())
+            .unwrap();
+        assert!(untagged_at <= 1);
+    }
+
+    #[test]
+    fn test_remove_frees_the_slot() {
+        let queue = TaskQueue::with_capacity(1, OverflowPolicy::Reject);
+        let queued = tagged("kamino");
+        queue.enqueue(queued.clone()).unwrap();
+
+        assert_eq!(queue.remove(queued.id()), Some(queued.clone()));
+        assert_eq!(queue.remove(queued.id()), None);
+        assert!(queue.is_empty());
+        assert_eq!(queue.enqueue(task(TaskPriority::Normal)), Ok(None));
+    }
+
+    #[test]
+    fn test_eviction_takes_oldest_across_tags() {
+        let queue = TaskQueue::with_capacity(2, OverflowPolicy::DropLowestPriority);
+        let oldest = tagged("kamino").with_priority(TaskPriority::Low);
+        queue.enqueue(oldest.clone()).unwrap();
+        queue
+            .enqueue(tagged("marginfi").with_priority(TaskPriority::Low))
+            .unwrap();
+
+        let evicted = queue.enqueue(task(TaskPriority::High)).unwrap();
+
+        assert_eq!(evicted.unwrap().id(), oldest.id());
     }
 }
@@ -0,0 +1,298 @@
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// First pause after a 429; doubles on every further 429 up to `DEFAULT_MAX_BACKOFF`
+pub const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+pub const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Sustained request rate plus how many requests may go out back to back
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub requests_per_second: f64,
+    pub burst: u32,
+}
+
+impl RateLimit {
+    /// Allows a burst of one second's worth of requests. Rates below 1 are raised to 1.
+    pub fn per_second(requests_per_second: u32) -> Self {
+        let requests_per_second = requests_per_second.max(1);
+        Self {
+            requests_per_second: f64::from(requests_per_second),
+            burst: requests_per_second,
+        }
+    }
+
+    pub fn with_burst(mut self, burst: u32) -> Self {
+        self.burst = burst.max(1);
+        self
+    }
+}
+
+/// Token bucket that pauses entirely for a while after being told about a 429
+#[derive(Debug)]
+pub struct TokenBucket {
+    limit: RateLimit,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    state: Mutex<BucketState>,
+}
+
+#[derive(Debug)]
+struct BucketState {
+    tokens: f64,
+    refilled_at: Instant,
+    backoff: Duration,
+    paused_until: Option<Instant>,
+}
+
+impl TokenBucket {
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+            state: Mutex::new(BucketState {
+                tokens: f64::from(limit.burst),
+                refilled_at: Instant::now(),
+                backoff: Duration::ZERO,
+                paused_until: None,
+            }),
+        }
+    }
+
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    pub fn limit(&self) -> RateLimit {
+        self.limit
+    }
+
+    /// Waits until a request may be sent, then takes a token for it
+    pub async fn acquire(&self) {
+        while let Err(wait) = self.try_acquire() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Takes a token if one is available, otherwise returns how long to wait
+    pub fn try_acquire(&self) -> Result<(), Duration> {
+        let mut state = self.state.lock().expect("token bucket poisoned");
+        let now = Instant::now();
+        if let Some(until) = state.paused_until {
+            if now < until {
+                return Err(until - now);
+            }
+            state.paused_until = None;
+            state.refilled_at = now;
+        }
+
+        let elapsed = now.duration_since(state.refilled_at).as_secs_f64();
+        state.tokens = (state.tokens + elapsed * self.limit.requests_per_second)
+            .min(f64::from(self.limit.burst));
+        state.refilled_at = now;
+
+        if state.tokens >= 1.0 {
+            state.tokens -= 1.0;
+            return Ok(());
+        }
+        let missing = 1.0 - state.tokens;
+        Err(Duration::from_secs_f64(
+            missing / self.limit.requests_per_second,
+        ))
+    }
+
+    /// Records a 429: empties the bucket and pauses it, backing off further each time
+    pub fn throttled(&self) {
+        let mut state = self.state.lock().expect("token bucket poisoned");
+        state.backoff = (state.backoff * 2)
+            .max(self.initial_backoff)
+            .min(self.max_backoff);
+        state.tokens = 0.0;
+        state.paused_until = Some(Instant::now() + state.backoff);
+    }
+
+    /// Records a request that was not throttled, resetting the backoff
+    pub fn succeeded(&self) {
+        self.state.lock().expect("token bucket poisoned").backoff = Duration::ZERO;
+    }
+
+    /// Current backoff, or zero if the last request was not throttled
+    pub fn backoff(&self) -> Duration {
+        self.state.lock().expect("token bucket poisoned").backoff
+    }
+}
+
+/// Token buckets per RPC endpoint and per target program. Requests to endpoints
+/// or programs without a configured limit are not throttled.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    endpoints: HashMap<String, TokenBucket>,
+    programs: HashMap<Pubkey, TokenBucket>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_endpoint_limit(mut self, endpoint: impl Into<String>, limit: RateLimit) -> Self {
+        self.endpoints
+            .insert(endpoint.into(), TokenBucket::new(limit));
+        self
+    }
+
+    pub fn with_program_limit(mut self, program: Pubkey, limit: RateLimit) -> Self {
+        self.programs.insert(program, TokenBucket::new(limit));
+        self
+    }
+
+    pub fn endpoint(&self, endpoint: &str) -> Option<&TokenBucket> {
+        self.endpoints.get(endpoint)
+    }
+
+    pub fn program(&self, program: &Pubkey) -> Option<&TokenBucket> {
+        self.programs.get(program)
+    }
+
+    /// Waits on the endpoint's bucket and then on each program's bucket
+    pub async fn acquire(&self, endpoint: Option<&str>, programs: &[Pubkey]) {
+        for bucket in self.buckets(endpoint, programs) {
+            bucket.acquire().await;
+        }
+    }
+
+    /// Backs off the buckets involved in a request that came back with a 429
+    pub fn throttled(&self, endpoint: Option<&str>, programs: &[Pubkey]) {
+        for bucket in self.buckets(endpoint, programs) {
+            bucket.throttled();
+        }
+    }
+
+    pub fn succeeded(&self, endpoint: Option<&str>, programs: &[Pubkey]) {
+        for bucket in self.buckets(endpoint, programs) {
+            bucket.succeeded();
+        }
+    }
+
+    fn buckets<'a>(
+        &'a self,
+        endpoint: Option<&str>,
+        programs: &'a [Pubkey],
+    ) -> impl Iterator<Item = &'a TokenBucket> {
+        endpoint
+            .and_then(|endpoint| self.endpoints.get(endpoint))
+            .into_iter()
+            .chain(
+                programs
+                    .iter()
+                    .filter_map(|program| self.programs.get(program)),
+            )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENDPOINT: &str = "https://api.devnet.solana.com";
+
+    #[tokio::test(start_paused = true)]
+    async fn test_burst_then_steady_rate() {
+        let bucket = TokenBucket::new(RateLimit::per_second(10).with_burst(5));
+        let start = Instant::now();
+
+        for _ in 0..5 {
+            bucket.acquire().await;
+        }
+        assert_eq!(start.elapsed(), Duration::ZERO);
+
+        for _ in 0..10 {
+            bucket.acquire().await;
+        }
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(990), "{elapsed:?}");
+        assert!(elapsed <= Duration::from_millis(1010), "{elapsed:?}");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_try_acquire_reports_wait() {
+        let bucket = TokenBucket::new(RateLimit::per_second(4).with_burst(1));
+
+        assert!(bucket.try_acquire().is_ok());
+        let wait = bucket.try_acquire().unwrap_err();
+
+        assert_eq!(wait, Duration::from_millis(250));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_throttled_backs_off_exponentially() {
+        let bucket = TokenBucket::new(RateLimit::per_second(100))
+            .with_backoff(Duration::from_millis(100), Duration::from_millis(300));
+
+        bucket.throttled();
+        assert_eq!(bucket.backoff(), Duration::from_millis(100));
+        bucket.throttled();
+        assert_eq!(bucket.backoff(), Duration::from_millis(200));
+        bucket.throttled();
+        assert_eq!(bucket.backoff(), Duration::from_millis(300));
+
+        let start = Instant::now();
+        bucket.acquire().await;
+        assert!(start.elapsed() >= Duration::from_millis(300));
+
+        bucket.succeeded();
+        assert_eq!(bucket.backoff(), Duration::ZERO);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_limiter_waits_on_endpoint_and_program() {
+        let program = Pubkey::new_unique();
+        let limiter = RateLimiter::new()
+            .with_endpoint_limit(ENDPOINT, RateLimit::per_second(100))
+            .with_program_limit(program, RateLimit::per_second(2).with_burst(1));
+        let start = Instant::now();
+
+        limiter.acquire(Some(ENDPOINT), &[program]).await;
+        limiter.acquire(Some(ENDPOINT), &[program]).await;
+
+        assert!(start.elapsed() >= Duration::from_millis(500));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_unlimited_requests_do_not_wait() {
+        let limiter =
+            RateLimiter::new().with_program_limit(Pubkey::new_unique(), RateLimit::per_second(1));
+        let start = Instant::now();
+
+        for _ in 0..100 {
+            limiter
+                .acquire(Some(ENDPOINT), &[Pubkey::new_unique()])
+                .await;
+            limiter.acquire(None, &[]).await;
+        }
+
+        assert_eq!(start.elapsed(), Duration::ZERO);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_throttle_only_touches_involved_buckets() {
+        let program = Pubkey::new_unique();
+        let limiter = RateLimiter::new()
+            .with_endpoint_limit(ENDPOINT, RateLimit::per_second(10))
+            .with_program_limit(program, RateLimit::per_second(10));
+
+        limiter.throttled(Some(ENDPOINT), &[]);
+
+        assert_eq!(
+            limiter.endpoint(ENDPOINT).unwrap().backoff(),
+            DEFAULT_INITIAL_BACKOFF
+        );
+        assert_eq!(limiter.program(&program).unwrap().backoff(), Duration::ZERO);
+    }
+}
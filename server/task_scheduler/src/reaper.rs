@@ -0,0 +1,447 @@
+use crate::{executor::AttemptReport, scheduler::TaskScheduler};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::{warn, Instrument};
+
+/// What the chain knows about a signature
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LandingStatus {
+    /// Confirmed without error
+    Landed,
+    /// Confirmed, but the transaction itself failed
+    FailedOnChain(String),
+    /// Not found; with the blockhash expired it can no longer land
+    NotLanded,
+}
+
+/// Looks up whether a sent transaction made it on chain
+pub trait SignatureChecker: Send + Sync + 'static {
+    fn check(&self, signature: &str) -> impl Future<Output = Result<LandingStatus, String>> + Send;
+}
+
+#[derive(Debug, Clone)]
+pub struct ReaperConfig {
+    /// How often the reaper looks for expired leases
+    pub interval: Duration,
+    /// Added to a task's timeout to form its lease. It should outlast blockhash
+    /// expiry, so a transaction that is not found by then can no longer land.
+    pub grace: Duration,
+}
+
+impl Default for ReaperConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(10),
+            grace: Duration::from_secs(90),
+        }
+    }
+}
+
+/// Resolves tasks left in progress beyond their lease, e.g. by a hung executor,
+/// and restored tasks whose interrupted attempt may have landed
+pub struct Reaper<C: SignatureChecker> {
+    scheduler: Arc<Mutex<TaskScheduler>>,
+    checker: Arc<C>,
+    config: ReaperConfig,
+}
+
+pub struct ReaperHandle {
+    stop: watch::Sender<bool>,
+    task: JoinHandle<()>,
+}
+
+impl<C: SignatureChecker> Reaper<C> {
+    pub fn new(scheduler: Arc<Mutex<TaskScheduler>>, checker: C, config: ReaperConfig) -> Self {
+        Self {
+            scheduler,
+            checker: Arc::new(checker),
+            config,
+        }
+    }
+
+    /// Runs one sweep and returns how many expired attempts and held restored tasks
+    /// were resolved. Those whose signature could not be checked are left for the
+    /// next sweep.
+    pub async fn reap(&self) -> usize {
+        let expired = self
+            .scheduler
+            .lock()
+            .expect("scheduler lock poisoned")
+            .expired_leases(self.config.grace);
+
+        let mut resolved = 0;
+        for task in expired {
+            let Some(attempt) = task.attempts().last() else {
+                continue;
+            };
+            let number = task.attempts().len();
+            let span = task.span();
+            let report = match &attempt.signature {
+                Some(signature) => {
+                    match self.checker.check(signature).instrument(span.clone()).await {
+                        Ok(LandingStatus::Landed) => AttemptReport::success(signature.clone()),
+                        Ok(LandingStatus::FailedOnChain(error)) => {
+                            AttemptReport::failure(error).with_signature(signature.clone())
+                        }
+                        Ok(LandingStatus::NotLanded) => {
+                            AttemptReport::failure("Execution lease expired")
+                        }
+                        Err(error) => {
+                            span.in_scope(|| warn!(%signature, %error, "Could not check landing"));
+                            continue;
+                        }
+                    }
+                }
+                None => AttemptReport::failure("Execution lease expired"),
+            };
+
+            // The attempt may have reported back while the signature was being checked
+            let expired = self
+                .scheduler
+                .lock()
+                .expect("scheduler lock poisoned")
+                .expire_attempt(task.id(), number, report);
+            if expired.is_ok() {
+                resolved += 1;
+            }
+        }
+        resolved + self.verify_restored().await
+    }
+
+    async fn verify_restored(&self) -> usize {
+        let restored = self
+            .scheduler
+            .lock()
+            .expect("scheduler lock poisoned")
+            .unverified_restores(self.config.grace);
+
+        let mut resolved = 0;
+        for task in restored {
+            let Some(signature) = task
+                .attempts()
+                .last()
+                .and_then(|attempt| attempt.signature.clone())
+            else {
+                continue;
+            };
+            let span = task.span();
+            let landing = match self
+                .checker
+                .check(&signature)
+                .instrument(span.clone())
+                .await
+            {
+                Ok(landing) => landing,
+                Err(error) => {
+                    span.in_scope(|| warn!(%signature, %error, "Could not check landing"));
+                    continue;
+                }
+            };
+            // The task may have been cancelled while the signature was being checked
+            let checked = self
+                .scheduler
+                .lock()
+                .expect("scheduler lock poisoned")
+                .resolve_restored(task.id(), landing);
+            if checked.is_ok() {
+                resolved += 1;
+            }
+        }
+        resolved
+    }
+
+    pub fn spawn(self) -> ReaperHandle {
+        let (stop, mut stopped) = watch::channel(false);
+        let task = tokio::spawn(async move {
+            while !*stopped.borrow() {
+                self.reap().await;
+                tokio::select! {
+                    _ = tokio::time::sleep(self.config.interval) => {}
+                    _ = stopped.changed() => {}
+                }
+            }
+        });
+        ReaperHandle { stop, task }
+    }
+}
+
+impl ReaperHandle {
+    pub async fn shutdown(self) {
+        let _ = self.stop.send(true);
+        let _ = self.task.await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::TaskEventKind;
+    use crate::executor::{AttemptHandle, TaskExecutor};
+    use crate::task::{Task, TaskStatus};
+    use crate::worker::{WorkerPool, WorkerPoolConfig};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::sync::Semaphore;
+
+    struct FixedChecker(Result<LandingStatus, String>);
+
+    impl SignatureChecker for FixedChecker {
+        async fn check(&self, _signature: &str) -> Result<LandingStatus, String> {
+            self.0.clone()
+        }
+    }
+
+    fn config() -> ReaperConfig {
+        ReaperConfig {
+            interval: Duration::from_millis(5),
+            grace: Duration::ZERO,
+        }
+    }
+
+    /// Dispatches a task with an already-elapsed timeout, optionally recording a sent signature
+    fn stuck_task(signature: Option<&str>) -> (Arc<Mutex<TaskScheduler>>, String) {
+        let mut scheduler = TaskScheduler::new();
+        let task = Task::new("{}".to_string()).with_timeout(Duration::ZERO);
+        let id = task.id().to_string();
+        scheduler.schedule_task(task).unwrap();
+        scheduler.execute_next().unwrap();
+        if let Some(signature) = signature {
+            scheduler
+                .record_sent(&id, 1, signature.to_string(), None)
+                .unwrap();
+        }
+        (Arc::new(Mutex::new(scheduler)), id)
+    }
+
+    /// Restores `stuck_task` into a fresh scheduler, as after a restart mid-attempt
+    fn restored_task(signature: &str) -> (Arc<Mutex<TaskScheduler>>, String) {
+        let (old, id) = stuck_task(Some(signature));
+        let unfinished = old.lock().unwrap().take_unfinished();
+        let mut scheduler = TaskScheduler::new();
+        scheduler.restore(unfinished).unwrap();
+        (Arc::new(Mutex::new(scheduler)), id)
+    }
+
+    fn status_of(scheduler: &Mutex<TaskScheduler>, id: &str) -> TaskStatus {
+        scheduler.lock().unwrap().task(id).unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_landed_transaction_completes_task() {
+        let (scheduler, id) = stuck_task(Some("5igna7ure"));
+        let mut events = scheduler.lock().unwrap().subscribe();
+        let reaper = Reaper::new(
+            Arc::clone(&scheduler),
+            FixedChecker(Ok(LandingStatus::Landed)),
+            config(),
+        );
+
+        assert_eq!(reaper.reap().await, 1);
+
+        assert_eq!(status_of(&scheduler, &id), TaskStatus::Completed);
+        let expired = events.try_recv().unwrap();
+        assert_eq!(expired.kind, TaskEventKind::Expired);
+        assert_eq!(expired.signature.as_deref(), Some("5igna7ure"));
+        assert_eq!(events.try_recv().unwrap().kind, TaskEventKind::Succeeded);
+    }
+
+    #[tokio::test]
+    async fn test_missing_transaction_is_retried() {
+        let (scheduler, id) = stuck_task(Some("5igna7ure"));
+        let reaper = Reaper::new(
+            Arc::clone(&scheduler),
+            FixedChecker(Ok(LandingStatus::NotLanded)),
+            config(),
+        );
+
+        assert_eq!(reaper.reap().await, 1);
+
+        let scheduler = scheduler.lock().unwrap();
+        let task = scheduler.task(&id).unwrap();
+        assert_eq!(task.status(), TaskStatus::Pending);
+        assert_eq!(task.retry_count(), 1);
+        assert_eq!(task.errors(), vec!["Execution lease expired"]);
+    }
+
+    #[tokio::test]
+    async fn test_failed_on_chain_keeps_signature() {
+        let (scheduler, id) = stuck_task(Some("5igna7ure"));
+        let reaper = Reaper::new(
+            Arc::clone(&scheduler),
+            FixedChecker(Ok(LandingStatus::FailedOnChain(
+                "insufficient funds".to_string(),
+            ))),
+            config(),
+        );
+
+        reaper.reap().await;
+
+        let scheduler = scheduler.lock().unwrap();
+        let attempt = &scheduler.task(&id).unwrap().attempts()[0];
+        assert_eq!(attempt.signature.as_deref(), Some("5igna7ure"));
+        assert_eq!(attempt.error.as_deref(), Some("insufficient funds"));
+    }
+
+    #[tokio::test]
+    async fn test_unsent_attempt_is_retried_without_checking() {
+        let (scheduler, id) = stuck_task(None);
+        let reaper = Reaper::new(
+            Arc::clone(&scheduler),
+            FixedChecker(Err("should not be called".to_string())),
+            config(),
+        );
+
+        assert_eq!(reaper.reap().await, 1);
+        assert_eq!(status_of(&scheduler, &id), TaskStatus::Pending);
+    }
+
+    #[tokio::test]
+    async fn test_check_error_leaves_task_for_next_sweep() {
+        let (scheduler, id) = stuck_task(Some("5igna7ure"));
+        let reaper = Reaper::new(
+            Arc::clone(&scheduler),
+            FixedChecker(Err("rpc unavailable".to_string())),
+            config(),
+        );
+
+        assert_eq!(reaper.reap().await, 0);
+        assert_eq!(status_of(&scheduler, &id), TaskStatus::InProgress);
+    }
+
+    #[tokio::test]
+    async fn test_restored_task_whose_attempt_landed_is_not_dispatched_again() {
+        let (scheduler, id) = restored_task("5igna7ure");
+        assert!(scheduler.lock().unwrap().execute_next().is_none());
+        let reaper = Reaper::new(
+            Arc::clone(&scheduler),
+            FixedChecker(Ok(LandingStatus::Landed)),
+            config(),
+        );
+
+        assert_eq!(reaper.reap().await, 1);
+
+        let mut scheduler = scheduler.lock().unwrap();
+        let task = scheduler.task(&id).unwrap();
+        assert_eq!(task.status(), TaskStatus::Completed);
+        assert_eq!(task.attempts().len(), 1);
+        assert_eq!(task.attempts()[0].error, None);
+        assert!(scheduler.execute_next().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_restored_task_whose_attempt_never_landed_is_requeued() {
+        let (scheduler, id) = restored_task("5igna7ure");
+        let reaper = Reaper::new(
+            Arc::clone(&scheduler),
+            FixedChecker(Ok(LandingStatus::NotLanded)),
+            config(),
+        );
+
+        assert_eq!(reaper.reap().await, 1);
+
+        let mut scheduler = scheduler.lock().unwrap();
+        assert_eq!(scheduler.task(&id).unwrap().retry_count(), 0);
+        assert_eq!(scheduler.execute_next().unwrap().id(), id);
+    }
+
+    #[tokio::test]
+    async fn test_restored_task_stays_held_when_check_fails() {
+        let (scheduler, id) = restored_task("5igna7ure");
+        let reaper = Reaper::new(
+            Arc::clone(&scheduler),
+            FixedChecker(Err("rpc unavailable".to_string())),
+            config(),
+        );
+
+        assert_eq!(reaper.reap().await, 0);
+
+        let mut scheduler = scheduler.lock().unwrap();
+        assert_eq!(scheduler.task(&id).unwrap().status(), TaskStatus::Pending);
+        assert!(scheduler.execute_next().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_live_lease_is_not_reaped() {
+        let scheduler = Arc::new(Mutex::new(TaskScheduler::new()));
+        let task = Task::new("{}".to_string());
+        let id = task.id().to_string();
+        {
+            let mut scheduler = scheduler.lock().unwrap();
+            scheduler.schedule_task(task).unwrap();
+            scheduler.execute_next().unwrap();
+        }
+        let reaper = Reaper::new(
+            Arc::clone(&scheduler),
+            FixedChecker(Ok(LandingStatus::Landed)),
+            config(),
+        );
+
+        assert_eq!(reaper.reap().await, 0);
+        assert_eq!(status_of(&scheduler, &id), TaskStatus::InProgress);
+    }
+
+    /// Sends, then hangs forever on its first attempt; succeeds on later ones
+    struct HangingExecutor {
+        calls: AtomicUsize,
+    }
+
+    impl TaskExecutor for Arc<HangingExecutor> {
+        async fn execute(&self, _task: Task, attempt: AttemptHandle) -> AttemptReport {
+            if self.calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                attempt.sent("lost", None);
+                std::future::pending::<()>().await;
+            }
+            AttemptReport::success("found")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hung_executor_times_out_and_is_retried() {
+        let scheduler = Arc::new(Mutex::new(TaskScheduler::new()));
+        let executor = Arc::new(HangingExecutor {
+            calls: AtomicUsize::new(0),
+        });
+        let pool = WorkerPool::new(
+            Arc::clone(&scheduler),
+            Arc::clone(&executor),
+            WorkerPoolConfig {
+                concurrency: 1,
+                rpc_limit: Arc::new(Semaphore::new(1)),
+                idle_poll: Duration::from_millis(5),
+                ..WorkerPoolConfig::default()
+            },
+        );
+        let task = Task::new("{}".to_string()).with_timeout(Duration::from_millis(20));
+        let id = task.id().to_string();
+        pool.submit(task).unwrap();
+        let workers = pool.spawn();
+        let reaper = Reaper::new(
+            Arc::clone(&scheduler),
+            FixedChecker(Ok(LandingStatus::NotLanded)),
+            config(),
+        )
+        .spawn();
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while status_of(&scheduler, &id) != TaskStatus::Completed {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("task was not reaped and retried");
+        reaper.shutdown().await;
+        workers.shutdown().await;
+
+        let scheduler = scheduler.lock().unwrap();
+        let attempts = scheduler.task(&id).unwrap().attempts();
+        assert_eq!(attempts.len(), 2);
+        assert_eq!(attempts[0].signature.as_deref(), Some("lost"));
+        assert_eq!(
+            attempts[0].error.as_deref(),
+            Some("Execution lease expired")
+        );
+        assert_eq!(attempts[1].signature.as_deref(), Some("found"));
+    }
+}
@@ -1,43 +1,1194 @@
-use crate::{queue::TaskQueue, task::Task};
+use crate::{
+    events::{EventBus, TaskEvent, TaskEventKind},
+    executor::AttemptReport,
+    metrics,
+    query::{TagProgress, TaskPage, TaskQuery},
+    queue::{OverflowPolicy, QueueDepth, TaskQueue, QUEUE_FULL},
+    reaper::LandingStatus,
+    task::{now_millis, Task, TaskStatus},
+};
+use engine::transaction::TransactionParams;
+use engine::{Cluster, WalletManager};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::{debug, error, info, warn};
+
+/// How long an idempotency key keeps suppressing duplicates by default
+pub const DEFAULT_IDEMPOTENCY_WINDOW: Duration = Duration::from_secs(60 * 60);
+/// How long finished tasks stay tracked by default
+pub const DEFAULT_RETENTION: Duration = Duration::from_secs(24 * 60 * 60);
+/// Error recorded on attempts still open when the scheduler shut down
+pub const INTERRUPTED: &str = "Interrupted by shutdown";
+/// How long an attempt may run when its task does not set its own timeout
+pub const DEFAULT_EXECUTION_TIMEOUT: Duration = Duration::from_secs(60);
+/// Returned when scheduling a task pinned to a cluster other than the scheduler's
+pub const WRONG_CLUSTER: &str = "Task belongs to another cluster";
 
 pub struct TaskScheduler {
-    queue: TaskQueue,
+    queue: Arc<TaskQueue>,
+    tasks: HashMap<String, Task>,
+    blocked: HashSet<String>,
+    // Restored tasks whose interrupted attempt sent a transaction, held out of the
+    // queue until the reaper finds out whether it landed
+    unverified: HashSet<String>,
+    dependents: HashMap<String, Vec<String>>,
+    dead_letters: Vec<String>,
+    in_flight: HashSet<String>,
+    paused: bool,
+    shutting_down: bool,
+    // Idempotency key -> (task ID, recorded at in ms)
+    idempotency_keys: HashMap<String, (String, u64)>,
+    // The same keys oldest first, so expiry never scans the whole map
+    idempotency_order: VecDeque<(u64, String)>,
+    idempotency_window: Duration,
+    finished: Retirements,
+    retention: Duration,
+    default_timeout: Duration,
+    // Retry budget stamped on newly scheduled tasks, if not the tasks' own
+    max_retries: Option<u8>,
+    // Cluster the scheduler executes on; tasks pinned elsewhere are refused
+    cluster: Option<Cluster>,
+    // Names tasks' wallets as they are scheduled. Locked while the scheduler is,
+    // so nothing may lock the scheduler while holding it.
+    wallet_names: Option<Arc<RwLock<WalletManager>>>,
+    events: EventBus,
+}
+
+impl Default for TaskScheduler {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl TaskScheduler {
     pub fn new() -> Self {
         Self {
-            queue: TaskQueue::new(),
+            queue: Arc::new(TaskQueue::new()),
+            tasks: HashMap::new(),
+            blocked: HashSet::new(),
+            unverified: HashSet::new(),
+            dependents: HashMap::new(),
+            dead_letters: Vec::new(),
+            in_flight: HashSet::new(),
+            paused: false,
+            shutting_down: false,
+            idempotency_keys: HashMap::new(),
+            idempotency_order: VecDeque::new(),
+            idempotency_window: DEFAULT_IDEMPOTENCY_WINDOW,
+            finished: Retirements::default(),
+            retention: DEFAULT_RETENTION,
+            default_timeout: DEFAULT_EXECUTION_TIMEOUT,
+            max_retries: None,
+            cluster: None,
+            wallet_names: None,
+            events: EventBus::default(),
+        }
+    }
+
+    /// Replaces the default unbounded queue, e.g. with `TaskQueue::with_capacity`
+    pub fn with_queue(mut self, queue: TaskQueue) -> Self {
+        self.queue = Arc::new(queue);
+        self
+    }
+
+    /// Replaces the default event bus, e.g. to change its buffer capacity
+    pub fn with_event_bus(mut self, events: EventBus) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Sets how long a scheduled idempotency key suppresses duplicates
+    pub fn with_idempotency_window(mut self, window: Duration) -> Self {
+        self.idempotency_window = window;
+        self
+    }
+
+    /// Sets how long finished tasks stay tracked, and so queryable and usable as
+    /// dependencies, before they are forgotten. Tasks a live task depends on are kept.
+    pub fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Sets the execution timeout for tasks that do not carry their own
+    pub fn with_default_timeout(mut self, timeout: Duration) -> Self {
+        self.default_timeout = timeout;
+        self
+    }
+
+    /// Sets how many times newly scheduled tasks are retried before being dead-lettered
+    pub fn with_max_retries(mut self, max_retries: u8) -> Self {
+        self.max_retries = Some(max_retries);
+        self
+    }
+
+    /// Pins untagged tasks to this cluster as they are scheduled, and refuses
+    /// to schedule or execute tasks pinned to any other
+    pub fn with_cluster(mut self, cluster: Cluster) -> Self {
+        self.cluster = Some(cluster);
+        self
+    }
+
+    /// Looks up the name of each newly scheduled task's wallet in `wallets`,
+    /// so the task's logs carry it
+    pub fn with_wallet_names(mut self, wallets: Arc<RwLock<WalletManager>>) -> Self {
+        self.wallet_names = Some(wallets);
+        self
+    }
+
+    pub fn cluster(&self) -> Option<Cluster> {
+        self.cluster
+    }
+
+    /// Schedules a task and returns it as tracked. If the task carries an
+    /// idempotency key seen within the retention window, the existing task is
+    /// returned instead and nothing new is enqueued.
+    pub fn schedule_task(&mut self, task: Task) -> Result<Task, &'static str> {
+        let mut scheduled = self.schedule_batch(vec![task])?;
+        Ok(scheduled.remove(0))
+    }
+
+    /// Schedules a group of tasks that may depend on each other in any order.
+    /// The whole batch is rejected if it references an unknown task or forms a cycle.
+    /// Tasks deduplicated by idempotency key are replaced by the existing task,
+    /// including in the dependencies of the rest of the batch.
+    pub fn schedule_batch(&mut self, tasks: Vec<Task>) -> Result<Vec<Task>, &'static str> {
+        if tasks
+            .iter()
+            .any(|task| self.foreign_cluster(task).is_some())
+        {
+            return Err(WRONG_CLUSTER);
+        }
+        self.enqueue_batch(tasks)
+    }
+
+    /// `schedule_batch` without the cluster check, so restored tasks of another
+    /// cluster are tracked and then refused when dispatched
+    fn enqueue_batch(&mut self, tasks: Vec<Task>) -> Result<Vec<Task>, &'static str> {
+        if self.shutting_down {
+            return Err("Scheduler is shutting down");
+        }
+        self.evict_finished();
+        self.expire_idempotency_keys();
+
+        let mut resolved_ids = Vec::with_capacity(tasks.len());
+        let mut aliases = HashMap::new();
+        let mut batch_keys: HashMap<String, String> = HashMap::new();
+        let mut tasks: Vec<Task> = tasks
+            .into_iter()
+            .filter_map(|task| {
+                let existing = task.idempotency_key().and_then(|key| {
+                    self.idempotency_keys
+                        .get(key)
+                        .filter(|(id, _)| self.tasks.contains_key(id))
+                        .map(|(id, _)| id.clone())
+                        .or_else(|| batch_keys.get(key).cloned())
+                });
+                match existing {
+                    Some(existing) => {
+                        aliases.insert(task.id().to_string(), existing.clone());
+                        resolved_ids.push(existing);
+                        None
+                    }
+                    None => {
+                        if let Some(key) = task.idempotency_key() {
+                            batch_keys.insert(key.to_string(), task.id().to_string());
+                        }
+                        resolved_ids.push(task.id().to_string());
+                        Some(task)
+                    }
+                }
+            })
+            .collect();
+        for task in &mut tasks {
+            task.remap_dependencies(&aliases);
         }
+
+        let mut batch_ids = HashSet::new();
+        for task in &tasks {
+            if self.tasks.contains_key(task.id()) || !batch_ids.insert(task.id()) {
+                return Err("Task already scheduled");
+            }
+        }
+        for task in &tasks {
+            for dep in task.dependencies() {
+                if !self.tasks.contains_key(dep) && !batch_ids.contains(dep.as_str()) {
+                    return Err("Task depends on an unknown task");
+                }
+            }
+        }
+        let ordered = Self::topological_order(tasks)?;
+        self.check_capacity(&ordered)?;
+
+        let recorded_at = now_millis();
+        for mut task in ordered {
+            if let Some(max_retries) = self.max_retries {
+                task.set_max_retries(max_retries);
+            }
+            if let (Some(cluster), None) = (self.cluster, task.cluster()) {
+                task = task.with_cluster(cluster);
+            }
+            if let (Some(wallets), Some(pubkey)) = (&self.wallet_names, task.wallet()) {
+                let wallets = wallets.read().expect("wallet lock poisoned");
+                task.set_wallet_name(wallets.wallet_name(&pubkey).map(str::to_string));
+            }
+            if let Some(key) = task.idempotency_key() {
+                self.idempotency_keys
+                    .insert(key.to_string(), (task.id().to_string(), recorded_at));
+                self.idempotency_order
+                    .push_back((recorded_at, key.to_string()));
+            }
+            self.insert(task)?;
+        }
+
+        Ok(resolved_ids
+            .iter()
+            .filter_map(|id| self.tasks.get(id).cloned())
+            .collect())
+    }
+
+    /// Rejects a batch up front if its immediately runnable tasks won't fit, even
+    /// after evicting lower priorities, so a bounded queue never leaves a batch
+    /// half scheduled
+    fn check_capacity(&self, batch: &[Task]) -> Result<(), &'static str> {
+        let Some(remaining) = self.queue.depth().remaining() else {
+            return Ok(());
+        };
+        let ready = batch.iter().filter(|task| {
+            task.dependencies().iter().all(|dep| {
+                self.tasks
+                    .get(dep)
+                    .is_some_and(|parent| parent.status() == TaskStatus::Completed)
+            })
+        });
+
+        if self.queue.policy() != OverflowPolicy::DropLowestPriority {
+            if ready.count() > remaining {
+                return Err(QUEUE_FULL);
+            }
+            return Ok(());
+        }
+        // Replay the evictions the batch would make, in the order it is inserted
+        let mut lanes = self.queue.lane_lengths();
+        let mut free = remaining;
+        for task in ready {
+            let priority = task.priority() as usize;
+            if free > 0 {
+                free -= 1;
+            } else {
+                let lower = lanes[..priority]
+                    .iter_mut()
+                    .find(|queued| **queued > 0)
+                    .ok_or(QUEUE_FULL)?;
+                *lower -= 1;
+            }
+            lanes[priority] += 1;
+        }
+        Ok(())
     }
 
-    pub fn schedule_task(&mut self, task: Task) -> Result<(), &'static str> {
-        match self.queue.enqueue(task) {
-            Err(e) => Err(e),
-            Ok(_) => Ok(()),
+    fn expire_idempotency_keys(&mut self) {
+        let now = now_millis();
+        let window = self.idempotency_window.as_millis() as u64;
+        while let Some((recorded_at, _)) = self.idempotency_order.front() {
+            if now.saturating_sub(*recorded_at) < window {
+                break;
+            }
+            let (recorded_at, key) = self.idempotency_order.pop_front().expect("front exists");
+            // The key may have been recorded again since, for a newer task
+            if self
+                .idempotency_keys
+                .get(&key)
+                .is_some_and(|(_, at)| *at == recorded_at)
+            {
+                self.idempotency_keys.remove(&key);
+            }
+        }
+    }
+
+    /// Forgets tasks that finished longer than the retention period ago, dead
+    /// letters included, unless a live task depends on them or their last attempt
+    /// has yet to report back. Runs whenever tasks are scheduled; returns how many
+    /// were forgotten.
+    pub fn evict_finished(&mut self) -> usize {
+        let now = now_millis();
+        let retention = self.retention.as_millis() as u64;
+        if !self.finished.is_due(now, retention) {
+            return 0;
+        }
+        let needed: HashSet<String> = self
+            .tasks
+            .values()
+            .filter(|task| matches!(task.status(), TaskStatus::Pending | TaskStatus::InProgress))
+            .flat_map(|task| task.dependencies().iter().cloned())
+            .collect();
+
+        let mut evicted = 0;
+        let mut kept = Vec::new();
+        while let Some(id) = self.finished.pop_due(now, retention) {
+            let Some(task) = self.tasks.get(&id) else {
+                continue;
+            };
+            // Replayed since it finished
+            if matches!(task.status(), TaskStatus::Pending | TaskStatus::InProgress) {
+                continue;
+            }
+            if needed.contains(&id) || self.in_flight.contains(&id) {
+                kept.push(id);
+                continue;
+            }
+            if let Some(key) = task.idempotency_key() {
+                if self
+                    .idempotency_keys
+                    .get(key)
+                    .is_some_and(|(owner, _)| *owner == id)
+                {
+                    self.idempotency_keys.remove(key);
+                }
+            }
+            self.dead_letters.retain(|dead| *dead != id);
+            self.forget(&id);
+            evicted += 1;
+        }
+        // Look again once another retention period has passed
+        for id in kept {
+            self.finished.retire(&id);
         }
+        evicted
     }
 
     pub fn execute_next(&mut self) -> Option<Task> {
-        todo!("Execute next task")
+        if self.paused || self.shutting_down {
+            return None;
+        }
+        while let Some(task) = self.queue.dequeue() {
+            let Some(tracked) = self.tasks.get(task.id()) else {
+                continue;
+            };
+            if tracked.status() != TaskStatus::Pending {
+                continue;
+            }
+            if let Some(cluster) = self.foreign_cluster(&task) {
+                self.refuse(task.id(), cluster);
+                continue;
+            }
+            let tracked = self.tasks.get_mut(task.id()).expect("task is tracked");
+            tracked.mark_attempt();
+            task.span().in_scope(|| {
+                debug!(attempt = tracked.attempts().len(), "Dispatched task");
+            });
+            self.events
+                .emit(TaskEvent::new(tracked, TaskEventKind::Started));
+            self.in_flight.insert(task.id().to_string());
+            return Some(task);
+        }
+        None
+    }
+
+    /// Marks an in-progress task as completed and releases any dependents
+    /// whose parents have now all succeeded.
+    pub fn complete_task(&mut self, id: &str, signature: String) -> Result<(), &'static str> {
+        self.report_attempt(id, AttemptReport::success(signature))
+    }
+
+    /// Records a failed attempt. The task is requeued while it has retries left,
+    /// otherwise it is dead-lettered and every task downstream of it is cancelled.
+    pub fn fail_task(&mut self, id: &str, error: String) -> Result<(), &'static str> {
+        self.report_attempt(id, AttemptReport::failure(error))
+    }
+
+    /// Closes the task's current attempt with the executor's report, then
+    /// completes or fails the task depending on the outcome
+    pub fn report_attempt(&mut self, id: &str, report: AttemptReport) -> Result<(), &'static str> {
+        if !self.tasks.contains_key(id) {
+            return Err("Task not found");
+        }
+        if !self.in_flight.remove(id) {
+            return Err("Task is not in progress");
+        }
+        self.close_attempt(id, report)
+    }
+
+    /// Like `report_attempt`, but only if `attempt` (numbered from 1) is still the
+    /// task's open attempt. Guards against a late report from an attempt the reaper
+    /// already resolved and requeued.
+    pub fn report_attempt_number(
+        &mut self,
+        id: &str,
+        attempt: usize,
+        report: AttemptReport,
+    ) -> Result<(), &'static str> {
+        self.check_open_attempt(id, attempt)?;
+        self.in_flight.remove(id);
+        self.close_attempt(id, report)
+    }
+
+    /// Records the signature an open attempt has sent, before it is confirmed
+    pub fn record_sent(
+        &mut self,
+        id: &str,
+        attempt: usize,
+        signature: String,
+        blockhash: Option<String>,
+    ) -> Result<(), &'static str> {
+        self.check_open_attempt(id, attempt)?;
+        let task = self.tasks.get_mut(id).ok_or("Task not found")?;
+        task.span()
+            .in_scope(|| info!(attempt, %signature, "Sent transaction"));
+        task.record_sent(signature, blockhash);
+        Ok(())
+    }
+
+    fn close_attempt(&mut self, id: &str, report: AttemptReport) -> Result<(), &'static str> {
+        let task = self.tasks.get_mut(id).ok_or("Task not found")?;
+        let signature = report.signature.clone();
+        let error = report.error.clone();
+        let throttled = report.throttled;
+        task.finish_attempt(report);
+        metrics::record_attempt(task);
+        let _span = task.span().entered();
+
+        // A cancelled task stays cancelled whatever its last attempt did; the
+        // outcome is kept in its attempt history. Its dependents went with it.
+        if task.status() == TaskStatus::Cancelled {
+            if error.is_none() {
+                info!(
+                    signature = signature.as_deref(),
+                    "Cancelled task's last attempt landed"
+                );
+            }
+            return Ok(());
+        }
+        let Some(error) = error else {
+            task.set_status(TaskStatus::Completed);
+            metrics::record_finished(task, now_millis());
+            self.finished.retire(id);
+            info!(signature = signature.as_deref(), "Task succeeded");
+            let mut event = TaskEvent::new(task, TaskEventKind::Succeeded);
+            event.signature = signature;
+            self.events.emit(event);
+
+            for child in self.dependents.remove(id).unwrap_or_default() {
+                if self.blocked.contains(&child) && self.parents_completed(&child) {
+                    self.blocked.remove(&child);
+                    self.enqueue_tracked(&child)?;
+                }
+            }
+            return Ok(());
+        };
+
+        if throttled {
+            // The endpoint refused the request, so the task itself is not at fault
+            task.set_status(TaskStatus::Pending);
+            warn!(%error, "Attempt throttled, requeued");
+            self.events
+                .emit(TaskEvent::new(task, TaskEventKind::Retrying).with_error(error));
+            self.enqueue_tracked(id)
+        } else if task.record_failure() {
+            warn!(%error, retries = task.retry_count(), "Attempt failed, retrying");
+            self.events
+                .emit(TaskEvent::new(task, TaskEventKind::Retrying).with_error(error));
+            self.enqueue_tracked(id)
+        } else {
+            metrics::record_finished(task, now_millis());
+            self.finished.retire(id);
+            error!(%error, attempts = task.attempts().len(), "Task failed, dead-lettered");
+            self.events
+                .emit(TaskEvent::new(task, TaskEventKind::Failed).with_error(error));
+            self.dead_letters.push(id.to_string());
+            self.cancel_dependents(id);
+            Ok(())
+        }
+    }
+
+    // Execution leases
+    /// How long an attempt of this task may run before it is abandoned
+    pub fn timeout_for(&self, task: &Task) -> Duration {
+        task.timeout().unwrap_or(self.default_timeout)
+    }
+
+    /// In-progress tasks whose open attempt has run longer than its timeout plus `grace`
+    pub fn expired_leases(&self, grace: Duration) -> Vec<Task> {
+        let now = now_millis();
+        self.in_flight
+            .iter()
+            .filter_map(|id| self.tasks.get(id))
+            .filter(|task| {
+                let lease = (self.timeout_for(task) + grace).as_millis() as u64;
+                task.attempts()
+                    .last()
+                    .is_some_and(|attempt| attempt.started_at.saturating_add(lease) <= now)
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Resolves an attempt that outlived its lease. `report` carries what was found
+    /// on chain: a success completes the task, a failure goes through the retry policy.
+    pub fn expire_attempt(
+        &mut self,
+        id: &str,
+        attempt: usize,
+        report: AttemptReport,
+    ) -> Result<(), &'static str> {
+        self.check_open_attempt(id, attempt)?;
+        let task = &self.tasks[id];
+        task.span()
+            .in_scope(|| warn!(attempt, "Execution lease expired"));
+        let mut event = TaskEvent::new(task, TaskEventKind::Expired);
+        event.signature = task.attempts()[attempt - 1].signature.clone();
+        self.events.emit(event);
+        self.report_attempt_number(id, attempt, report)
+    }
+
+    /// Restored tasks held for a landing check whose interrupted attempt has
+    /// outlived its lease, so a signature not found by now can no longer land
+    pub fn unverified_restores(&self, grace: Duration) -> Vec<Task> {
+        let now = now_millis();
+        self.unverified
+            .iter()
+            .filter_map(|id| self.tasks.get(id))
+            .filter(|task| {
+                let lease = (self.timeout_for(task) + grace).as_millis() as u64;
+                task.attempts()
+                    .last()
+                    .is_some_and(|attempt| attempt.started_at.saturating_add(lease) <= now)
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Resolves a restored task held for a landing check. A landed signature
+    /// completes it and a failed one goes through the retry policy; one that never
+    /// landed puts the task back in the queue without using up a retry.
+    pub fn resolve_restored(
+        &mut self,
+        id: &str,
+        landing: LandingStatus,
+    ) -> Result<(), &'static str> {
+        if !self.unverified.remove(id) {
+            return Err("Task is not awaiting a landing check");
+        }
+        let task = self.tasks.get_mut(id).ok_or("Task not found")?;
+        let signature = task
+            .attempts()
+            .last()
+            .and_then(|attempt| attempt.signature.clone())
+            .unwrap_or_default();
+        let report = match landing {
+            LandingStatus::Landed => AttemptReport::success(signature),
+            LandingStatus::FailedOnChain(error) => {
+                AttemptReport::failure(error).with_signature(signature)
+            }
+            LandingStatus::NotLanded => {
+                task.span()
+                    .in_scope(|| info!("Interrupted attempt did not land, requeued"));
+                if self.parents_completed(id) {
+                    return self.enqueue_tracked(id);
+                }
+                self.blocked.insert(id.to_string());
+                return Ok(());
+            }
+        };
+        // The interrupted attempt is what landed, so it takes the outcome
+        task.reopen_attempt();
+        self.close_attempt(id, report)
+    }
+
+    /// Whether `attempt` (numbered from 1) is still the task's open attempt
+    pub(crate) fn is_attempt_open(&self, id: &str, attempt: usize) -> bool {
+        self.check_open_attempt(id, attempt).is_ok()
+    }
+
+    fn check_open_attempt(&self, id: &str, attempt: usize) -> Result<(), &'static str> {
+        let task = self.tasks.get(id).ok_or("Task not found")?;
+        let open = task.open_attempt().is_some();
+        if !self.in_flight.contains(id) || !open || task.attempts().len() != attempt {
+            return Err("Attempt is no longer in progress");
+        }
+        Ok(())
+    }
+
+    // Cancellation and dispatch control
+    /// Cancels a task along with everything downstream of it. An in-progress
+    /// attempt is allowed to finish, but the task will not be retried.
+    pub fn cancel_task(&mut self, id: &str) -> Result<(), &'static str> {
+        let task = self.tasks.get(id).ok_or("Task not found")?;
+        match task.status() {
+            TaskStatus::Pending | TaskStatus::InProgress => {
+                self.cancel(id);
+                Ok(())
+            }
+            _ => Err("Task already finished"),
+        }
+    }
+
+    /// Cancels every pending or in-progress task carrying the given squad or
+    /// campaign tag, returning how many were cancelled
+    pub fn cancel_tag(&mut self, tag: &str) -> usize {
+        let ids: Vec<String> = self
+            .tasks
+            .values()
+            .filter(|task| task.tag() == Some(tag))
+            .filter(|task| matches!(task.status(), TaskStatus::Pending | TaskStatus::InProgress))
+            .map(|task| task.id().to_string())
+            .collect();
+
+        // Cascading from an earlier ID may already have cancelled a later one
+        ids.iter().filter(|id| self.cancel_task(id).is_ok()).count()
+    }
+
+    /// Sets how large a share of dispatches a squad or campaign tag gets, relative
+    /// to other tags waiting at the same priority
+    pub fn set_tag_weight(&mut self, tag: &str, weight: u32) {
+        self.queue.set_tag_weight(tag, weight);
+    }
+
+    /// Stops dispatching tasks without dropping any queued work
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    // Shutdown
+    /// Stops accepting new tasks and dispatching queued ones. Attempts already
+    /// in flight may still report back, and failures are still requeued.
+    pub fn begin_shutdown(&mut self) {
+        self.shutting_down = true;
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down
+    }
+
+    /// How many dispatched attempts have not reported back yet
+    pub fn in_flight_count(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    /// Removes every task that has not finished and returns them as Pending, oldest
+    /// first, ready to persist. Open attempts are closed as interrupted without
+    /// using up a retry, and keep any signature they recorded so it can be checked
+    /// on chain before the task runs again.
+    pub fn take_unfinished(&mut self) -> Vec<Task> {
+        while self.queue.dequeue().is_some() {}
+        self.blocked.clear();
+        self.unverified.clear();
+        self.in_flight.clear();
+
+        let ids: Vec<String> = self
+            .tasks
+            .values()
+            .filter(|task| matches!(task.status(), TaskStatus::Pending | TaskStatus::InProgress))
+            .map(|task| task.id().to_string())
+            .collect();
+        let mut unfinished: Vec<Task> = ids
+            .iter()
+            .filter_map(|id| {
+                let mut task = self.tasks.get(id)?.clone();
+                self.forget(id);
+                if task.open_attempt().is_some() {
+                    task.finish_attempt(AttemptReport::failure(INTERRUPTED));
+                }
+                task.set_status(TaskStatus::Pending);
+                Some(task)
+            })
+            .collect();
+        unfinished.sort_by(|a, b| (a.created_at(), a.id()).cmp(&(b.created_at(), b.id())));
+        unfinished
+    }
+
+    /// Reschedules tasks persisted by `take_unfinished`. Dependencies on tasks that
+    /// are neither in the batch nor tracked are dropped: only unfinished tasks are
+    /// persisted, so those parents had already completed. Tasks whose interrupted
+    /// attempt sent a transaction are held until `resolve_restored` says whether it
+    /// landed, so they are not sent twice.
+    pub fn restore(&mut self, tasks: Vec<Task>) -> Result<Vec<Task>, &'static str> {
+        let batch_ids: HashSet<String> = tasks.iter().map(|task| task.id().to_string()).collect();
+        let held: Vec<String> = tasks
+            .iter()
+            .filter(|task| {
+                task.attempts().last().is_some_and(|attempt| {
+                    attempt.signature.is_some() && attempt.error.as_deref() == Some(INTERRUPTED)
+                })
+            })
+            .map(|task| task.id().to_string())
+            .collect();
+        let tasks = tasks
+            .into_iter()
+            .map(|mut task| {
+                task.retain_dependencies(|dep| {
+                    batch_ids.contains(dep) || self.tasks.contains_key(dep)
+                });
+                task.set_status(TaskStatus::Pending);
+                task
+            })
+            .collect();
+        self.unverified.extend(held.iter().cloned());
+        let restored = self.enqueue_batch(tasks);
+        if restored.is_err() {
+            for id in &held {
+                self.unverified.remove(id);
+            }
+        }
+        restored
+    }
+
+    /// Every tracked task, finished ones included, oldest first
+    pub fn snapshot(&self) -> Vec<Task> {
+        let mut tasks: Vec<Task> = self.tasks.values().cloned().collect();
+        tasks.sort_by(|a, b| (a.created_at(), a.id()).cmp(&(b.created_at(), b.id())));
+        tasks
+    }
+
+    /// Tracks tasks from a `snapshot` as they were. Finished tasks keep their status,
+    /// failed ones going back to the dead-letter queue, and unfinished ones are
+    /// restored as Pending with any open attempt closed as interrupted.
+    pub fn load(&mut self, tasks: Vec<Task>) -> Result<Vec<Task>, &'static str> {
+        let (finished, unfinished): (Vec<Task>, Vec<Task>) = tasks.into_iter().partition(|task| {
+            !matches!(task.status(), TaskStatus::Pending | TaskStatus::InProgress)
+        });
+        if finished
+            .iter()
+            .any(|task| self.tasks.contains_key(task.id()))
+        {
+            return Err("Task already scheduled");
+        }
+
+        for task in finished {
+            if task.status() == TaskStatus::Failed {
+                self.dead_letters.push(task.id().to_string());
+            }
+            self.finished.retire(task.id());
+            self.tasks.insert(task.id().to_string(), task);
+        }
+        let unfinished = unfinished
+            .into_iter()
+            .map(|mut task| {
+                if task.open_attempt().is_some() {
+                    task.finish_attempt(AttemptReport::failure(INTERRUPTED));
+                }
+                task
+            })
+            .collect();
+        self.restore(unfinished)
+    }
+
+    // Lifecycle events
+    /// Receives every task lifecycle event emitted after subscribing
+    pub fn subscribe(&self) -> broadcast::Receiver<TaskEvent> {
+        self.events.subscribe()
+    }
+
+    /// Registers a callback run synchronously, under the scheduler's lock, for every event
+    pub fn on_event(&mut self, hook: impl Fn(&TaskEvent) + Send + Sync + 'static) {
+        self.events.add_hook(hook);
+    }
+
+    /// Lets a worker drop a dispatched task that was cancelled before it started
+    pub(crate) fn release_cancelled(&mut self, id: &str) -> bool {
+        let Some(task) = self.tasks.get_mut(id) else {
+            return false;
+        };
+        if task.status() != TaskStatus::Cancelled || !self.in_flight.remove(id) {
+            return false;
+        }
+        task.finish_attempt(AttemptReport::failure("Cancelled before start"));
+        true
+    }
+
+    // Dead-letter queue
+    /// Tasks that exhausted their retries, in the order they failed
+    pub fn dead_letters(&self) -> Vec<&Task> {
+        self.dead_letters
+            .iter()
+            .filter_map(|id| self.tasks.get(id))
+            .collect()
+    }
+
+    pub fn dead_letter(&self, id: &str) -> Option<&Task> {
+        self.dead_letters
+            .iter()
+            .any(|dead| dead == id)
+            .then(|| self.tasks.get(id))
+            .flatten()
+    }
+
+    /// Replaces the params of a dead-lettered task before it is replayed
+    pub fn edit_dead_letter_params(
+        &mut self,
+        id: &str,
+        transaction_params: String,
+    ) -> Result<(), &'static str> {
+        if serde_json::from_str::<TransactionParams>(&transaction_params).is_err() {
+            return Err("Invalid transaction params");
+        }
+        if self.dead_letter(id).is_none() {
+            return Err("Task is not dead-lettered");
+        }
+        let task = self.tasks.get_mut(id).ok_or("Task not found")?;
+        task.set_transaction_params(transaction_params);
+        Ok(())
+    }
+
+    /// Re-enqueues a dead-lettered task with a reset retry count.
+    /// Dependents cancelled by the original failure are not revived.
+    pub fn replay_dead_letter(&mut self, id: &str) -> Result<(), &'static str> {
+        let position = self
+            .dead_letters
+            .iter()
+            .position(|dead| dead == id)
+            .ok_or("Task is not dead-lettered")?;
+        let task = self.tasks.get_mut(id).ok_or("Task not found")?;
+
+        task.reset_for_replay();
+        self.events
+            .emit(TaskEvent::new(task, TaskEventKind::Scheduled));
+        self.dead_letters.remove(position);
+        self.enqueue_tracked(id)
+    }
+
+    // Queries
+    pub fn task(&self, id: &str) -> Option<&Task> {
+        self.tasks.get(id)
+    }
+
+    /// Lists tracked tasks matching the query, one page at a time
+    pub fn query(&self, query: &TaskQuery) -> TaskPage {
+        query.run(self.tasks.values())
+    }
+
+    /// Counts the tracked tasks carrying `tag` by status
+    pub fn progress(&self, tag: &str) -> TagProgress {
+        TagProgress::count(tag, self.tasks.values())
+    }
+
+    pub fn queue_depth(&self) -> QueueDepth {
+        self.queue.depth()
+    }
+
+    pub(crate) fn queue(&self) -> Arc<TaskQueue> {
+        Arc::clone(&self.queue)
+    }
+
+    /// Sets the task, queue depth and dead-letter gauges from the tracked tasks.
+    /// Call before each scrape, as the gauges are not kept current otherwise.
+    pub fn record_metrics(&self) {
+        metrics::record_gauges(
+            self.tasks.values(),
+            |task| {
+                task.status() == TaskStatus::Pending
+                    && !self.blocked.contains(task.id())
+                    && !self.unverified.contains(task.id())
+            },
+            self.dead_letters.len(),
+        );
     }
 
     pub fn get_pending_count(&self) -> usize {
-        self.queue.len()
+        self.queue.len() + self.blocked.len() + self.unverified.len()
     }
 
     pub fn clear_all(&mut self) {
-        todo!("Clear all tasks")
+        while let Some(task) = self.queue.dequeue() {
+            let pending = self
+                .tasks
+                .get(task.id())
+                .is_some_and(|tracked| tracked.status() == TaskStatus::Pending);
+            if pending {
+                self.forget(task.id());
+            }
+        }
+        for id in std::mem::take(&mut self.blocked) {
+            self.forget(&id);
+        }
+        for id in std::mem::take(&mut self.unverified) {
+            self.forget(&id);
+        }
     }
 
     pub fn is_empty(&self) -> bool {
-        self.queue.len() == 0
+        self.get_pending_count() == 0
+    }
+
+    // Dependency graph
+    fn insert(&mut self, mut task: Task) -> Result<(), &'static str> {
+        let id = task.id().to_string();
+        metrics::record_scheduled(&task);
+        let _span = task.span().entered();
+        let mut unfinished = Vec::new();
+        let mut doomed = false;
+
+        for dep in task.dependencies() {
+            match self.tasks.get(dep).map(|parent| parent.status()) {
+                Some(TaskStatus::Completed) => {}
+                Some(TaskStatus::Failed) | Some(TaskStatus::Cancelled) => doomed = true,
+                _ => unfinished.push(dep.clone()),
+            }
+        }
+        if doomed {
+            task.set_status(TaskStatus::Cancelled);
+            metrics::record_finished(&task, now_millis());
+            self.finished.retire(&id);
+            warn!("Cancelled at scheduling, a dependency did not succeed");
+            self.events
+                .emit(TaskEvent::new(&task, TaskEventKind::Scheduled));
+            self.events.emit(
+                TaskEvent::new(&task, TaskEventKind::Cancelled)
+                    .with_error("Dependency did not succeed"),
+            );
+            self.tasks.insert(id, task);
+            return Ok(());
+        }
+
+        debug!(
+            priority = ?task.priority(),
+            blocked_on = unfinished.len(),
+            "Scheduled task"
+        );
+        if self.unverified.contains(&id) {
+            // Held out of the queue until its interrupted attempt is checked on chain
+            for dep in unfinished {
+                self.dependents.entry(dep).or_default().push(id.clone());
+            }
+            self.events
+                .emit(TaskEvent::new(&task, TaskEventKind::Scheduled));
+            self.tasks.insert(id, task);
+        } else if unfinished.is_empty() {
+            let evicted = self.queue.enqueue(task.clone())?;
+            self.events
+                .emit(TaskEvent::new(&task, TaskEventKind::Scheduled));
+            self.tasks.insert(id, task);
+            if let Some(evicted) = evicted {
+                self.drop_evicted(evicted);
+            }
+        } else {
+            for dep in unfinished {
+                self.dependents.entry(dep).or_default().push(id.clone());
+            }
+            self.events
+                .emit(TaskEvent::new(&task, TaskEventKind::Scheduled));
+            self.tasks.insert(id.clone(), task);
+            self.blocked.insert(id);
+        }
+        Ok(())
+    }
+
+    fn enqueue_tracked(&mut self, id: &str) -> Result<(), &'static str> {
+        let task = self.tasks.get(id).ok_or("Task not found")?;
+        self.queue.requeue(task.clone());
+        Ok(())
+    }
+
+    fn drop_evicted(&mut self, evicted: Task) {
+        let Some(task) = self.tasks.get_mut(evicted.id()) else {
+            return;
+        };
+        if task.status() != TaskStatus::Pending {
+            return;
+        }
+        // Never dispatched, but the history should still say why it ended
+        task.finish_attempt(AttemptReport::failure("Dropped from full queue"));
+        task.set_status(TaskStatus::Cancelled);
+        metrics::record_finished(task, now_millis());
+        self.finished.retire(evicted.id());
+        task.span().in_scope(|| warn!("Dropped from full queue"));
+        self.events.emit(
+            TaskEvent::new(task, TaskEventKind::Cancelled).with_error("Dropped from full queue"),
+        );
+        self.cancel_dependents(evicted.id());
+    }
+
+    fn parents_completed(&self, id: &str) -> bool {
+        self.tasks.get(id).is_some_and(|task| {
+            task.dependencies().iter().all(|dep| {
+                self.tasks
+                    .get(dep)
+                    .is_some_and(|parent| parent.status() == TaskStatus::Completed)
+            })
+        })
+    }
+
+    fn cancel(&mut self, id: &str) {
+        let Some(task) = self.tasks.get_mut(id) else {
+            return;
+        };
+        let was_pending = task.status() == TaskStatus::Pending;
+        task.set_status(TaskStatus::Cancelled);
+        metrics::record_finished(task, now_millis());
+        self.finished.retire(id);
+        task.span().in_scope(|| info!("Cancelled task"));
+        self.events
+            .emit(TaskEvent::new(task, TaskEventKind::Cancelled));
+
+        // Free the queue slot right away rather than leave a dead entry behind
+        if was_pending && !self.blocked.remove(id) && !self.unverified.remove(id) {
+            self.queue.remove(id);
+        }
+        self.cancel_dependents(id);
+    }
+
+    /// The cluster a task is pinned to, if that is not the scheduler's
+    fn foreign_cluster(&self, task: &Task) -> Option<Cluster> {
+        let own = self.cluster?;
+        task.cluster().filter(|cluster| *cluster != own)
+    }
+
+    /// Dead-letters a task pinned to another cluster without running it
+    fn refuse(&mut self, id: &str, cluster: Cluster) {
+        let Some(task) = self.tasks.get_mut(id) else {
+            return;
+        };
+        let error = format!(
+            "Task belongs to {}, not {}",
+            cluster,
+            self.cluster.map_or("this cluster", |own| own.as_str())
+        );
+        task.mark_attempt();
+        task.finish_attempt(AttemptReport::failure(error.clone()));
+        task.set_status(TaskStatus::Failed);
+        metrics::record_attempt(task);
+        metrics::record_finished(task, now_millis());
+        self.finished.retire(id);
+        task.span()
+            .in_scope(|| error!(%error, "Refused task of another cluster"));
+        self.events
+            .emit(TaskEvent::new(task, TaskEventKind::Failed).with_error(error));
+        self.dead_letters.push(id.to_string());
+        self.cancel_dependents(id);
+    }
+
+    fn cancel_dependents(&mut self, id: &str) {
+        for child in self.dependents.remove(id).unwrap_or_default() {
+            let pending = self
+                .tasks
+                .get(&child)
+                .is_some_and(|task| task.status() == TaskStatus::Pending);
+            if pending {
+                self.cancel(&child);
+            }
+        }
+    }
+
+    fn forget(&mut self, id: &str) {
+        self.dependents.remove(id);
+        self.finished.forget(id);
+        if let Some(task) = self.tasks.remove(id) {
+            for dep in task.dependencies() {
+                if let Some(children) = self.dependents.get_mut(dep) {
+                    children.retain(|child| child != id);
+                }
+            }
+        }
+    }
+
+    /// Orders a batch so that parents come before their children (Kahn's algorithm).
+    /// Anything left over once no more tasks can be released sits on a cycle.
+    fn topological_order(tasks: Vec<Task>) -> Result<Vec<Task>, &'static str> {
+        let ids: HashSet<String> = tasks.iter().map(|task| task.id().to_string()).collect();
+        let mut in_degree: HashMap<String, usize> = HashMap::new();
+        let mut children: HashMap<String, Vec<String>> = HashMap::new();
+
+        for task in &tasks {
+            let local_parents: Vec<&String> = task
+                .dependencies()
+                .iter()
+                .filter(|dep| ids.contains(*dep))
+                .collect();
+            in_degree.insert(task.id().to_string(), local_parents.len());
+            for parent in local_parents {
+                children
+                    .entry(parent.clone())
+                    .or_default()
+                    .push(task.id().to_string());
+            }
+        }
+
+        let mut ready: Vec<String> = in_degree
+            .iter()
+            .filter(|(_, degree)| **degree == 0)
+            .map(|(id, _)| id.clone())
+            .collect();
+        let mut order = Vec::with_capacity(tasks.len());
+
+        while let Some(id) = ready.pop() {
+            for child in children.remove(&id).unwrap_or_default() {
+                let degree = in_degree.get_mut(&child).expect("child is in batch");
+                *degree -= 1;
+                if *degree == 0 {
+                    ready.push(child);
+                }
+            }
+            order.push(id);
+        }
+
+        if order.len() != tasks.len() {
+            return Err("Task dependencies form a cycle");
+        }
+
+        let mut by_id: HashMap<String, Task> = tasks
+            .into_iter()
+            .map(|task| (task.id().to_string(), task))
+            .collect();
+        Ok(order
+            .into_iter()
+            .filter_map(|id| by_id.remove(&id))
+            .collect())
+    }
+}
+
+/// Finished tasks in the order they finished, for the retention sweep
+#[derive(Default)]
+struct Retirements {
+    // (finished at in ms, task ID), oldest first. A task retired twice, e.g. after
+    // a replay, is listed twice; only the entry matching `latest` counts.
+    order: VecDeque<(u64, String)>,
+    latest: HashMap<String, u64>,
+}
+
+impl Retirements {
+    fn retire(&mut self, id: &str) {
+        let now = now_millis();
+        self.latest.insert(id.to_string(), now);
+        self.order.push_back((now, id.to_string()));
+    }
+
+    fn forget(&mut self, id: &str) {
+        self.latest.remove(id);
+    }
+
+    fn is_due(&self, now: u64, retention: u64) -> bool {
+        self.order
+            .front()
+            .is_some_and(|(at, _)| now.saturating_sub(*at) >= retention)
+    }
+
+    /// The next task retired at least `retention` ms before `now`, skipping stale entries
+    fn pop_due(&mut self, now: u64, retention: u64) -> Option<String> {
+        while self.is_due(now, retention) {
+            let (at, id) = self.order.pop_front()?;
+            if self.latest.get(&id) == Some(&at) {
+                self.latest.remove(&id);
+                return Some(id);
+            }
+        }
+        None
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::task::TaskPriority;
     use engine::transaction::{TransactionParams, TransferParams};
     use solana_sdk::pubkey::Pubkey;
 
@@ -58,7 +1209,7 @@ mod tests {
         });
         let task = Task::new(serde_json::to_string(&params).unwrap());
 
-        scheduler.schedule_task(task);
+        scheduler.schedule_task(task).unwrap();
 
         assert!(!scheduler.is_empty());
         assert_eq!(scheduler.get_pending_count(), 1);
@@ -74,7 +1225,9 @@ mod tests {
                 to: Pubkey::new_unique(),
                 lamports: (i + 1) * 100_000,
             });
-            scheduler.schedule_task(Task::new(serde_json::to_string(&params).unwrap()));
+            scheduler
+                .schedule_task(Task::new(serde_json::to_string(&params).unwrap()))
+                .unwrap();
         }
 
         assert_eq!(scheduler.get_pending_count(), 5);
@@ -91,7 +1244,7 @@ mod tests {
         });
         let task = Task::new(serde_json::to_string(&params).unwrap());
 
-        scheduler.schedule_task(task.clone());
+        scheduler.schedule_task(task.clone()).unwrap();
         let executed_task = scheduler.execute_next();
 
         assert!(executed_task.is_some());
@@ -112,7 +1265,7 @@ mod tests {
                 lamports: (i + 1) * 100_000,
             });
             let task = Task::new(serde_json::to_string(&params).unwrap());
-            scheduler.schedule_task(task);
+            scheduler.schedule_task(task).unwrap();
         }
 
         // Execute all tasks and verify FIFO order
@@ -146,7 +1299,9 @@ mod tests {
                 to: Pubkey::new_unique(),
                 lamports: (i + 1) * 100_000,
             });
-            scheduler.schedule_task(Task::new(serde_json::to_string(&params).unwrap()));
+            scheduler
+                .schedule_task(Task::new(serde_json::to_string(&params).unwrap()))
+                .unwrap();
         }
 
         assert_eq!(scheduler.get_pending_count(), 5);
@@ -171,7 +1326,9 @@ mod tests {
             to: Pubkey::new_unique(),
             lamports: 1_000_000,
         });
-        scheduler.schedule_task(Task::new(serde_json::to_string(&params).unwrap()));
+        scheduler
+            .schedule_task(Task::new(serde_json::to_string(&params).unwrap()))
+            .unwrap();
         assert!(!scheduler.is_empty());
         assert_eq!(scheduler.get_pending_count(), 1);
 
@@ -196,8 +1353,12 @@ mod tests {
             to: Pubkey::new_unique(),
             lamports: 2_000_000,
         });
-        scheduler.schedule_task(Task::new(serde_json::to_string(&params1).unwrap()));
-        scheduler.schedule_task(Task::new(serde_json::to_string(&params2).unwrap()));
+        scheduler
+            .schedule_task(Task::new(serde_json::to_string(&params1).unwrap()))
+            .unwrap();
+        scheduler
+            .schedule_task(Task::new(serde_json::to_string(&params2).unwrap()))
+            .unwrap();
         assert_eq!(scheduler.get_pending_count(), 2);
 
         // Execute one
@@ -210,7 +1371,9 @@ mod tests {
             to: Pubkey::new_unique(),
             lamports: 3_000_000,
         });
-        scheduler.schedule_task(Task::new(serde_json::to_string(&params3).unwrap()));
+        scheduler
+            .schedule_task(Task::new(serde_json::to_string(&params3).unwrap()))
+            .unwrap();
         assert_eq!(scheduler.get_pending_count(), 2);
 
         // Clear all
@@ -218,4 +1381,1237 @@ mod tests {
         assert!(scheduler.is_empty());
         assert_eq!(scheduler.get_pending_count(), 0);
     }
+
+    fn transfer_task() -> Task {
+        let params = TransactionParams::Transfer(TransferParams {
+            from: Pubkey::new_unique(),
+            to: Pubkey::new_unique(),
+            lamports: 1_000_000,
+        });
+        Task::new(serde_json::to_string(&params).unwrap())
+    }
+
+    fn status_of(scheduler: &TaskScheduler, id: &str) -> TaskStatus {
+        scheduler.task(id).unwrap().status()
+    }
+
+    // Dependency graph tests
+    #[test]
+    fn test_dependent_task_waits_for_parent() {
+        let mut scheduler = TaskScheduler::new();
+        let fund = transfer_task();
+        let deposit = transfer_task().with_dependencies(vec![fund.id().to_string()]);
+
+        scheduler.schedule_task(fund.clone()).unwrap();
+        scheduler.schedule_task(deposit.clone()).unwrap();
+        assert_eq!(scheduler.get_pending_count(), 2);
+
+        let first = scheduler.execute_next().unwrap();
+        assert_eq!(first.id(), fund.id());
+        assert!(scheduler.execute_next().is_none());
+
+        scheduler
+            .complete_task(fund.id(), "signature".to_string())
+            .unwrap();
+        let second = scheduler.execute_next().unwrap();
+        assert_eq!(second.id(), deposit.id());
+    }
+
+    #[test]
+    fn test_task_with_multiple_parents_waits_for_all() {
+        let mut scheduler = TaskScheduler::new();
+        let fund = transfer_task();
+        let create_account = transfer_task();
+        let deposit = transfer_task()
+            .with_dependencies(vec![fund.id().to_string(), create_account.id().to_string()]);
+
+        scheduler
+            .schedule_batch(vec![fund.clone(), create_account.clone(), deposit.clone()])
+            .unwrap();
+
+        let a = scheduler.execute_next().unwrap();
+        let b = scheduler.execute_next().unwrap();
+        assert!(scheduler.execute_next().is_none());
+
+        scheduler
+            .complete_task(a.id(), "signature".to_string())
+            .unwrap();
+        assert!(scheduler.execute_next().is_none());
+
+        scheduler
+            .complete_task(b.id(), "signature".to_string())
+            .unwrap();
+        assert_eq!(scheduler.execute_next().unwrap().id(), deposit.id());
+    }
+
+    #[test]
+    fn test_batch_accepts_children_before_parents() {
+        let mut scheduler = TaskScheduler::new();
+        let fund = transfer_task();
+        let deposit = transfer_task().with_dependencies(vec![fund.id().to_string()]);
+        let claim = transfer_task().with_dependencies(vec![deposit.id().to_string()]);
+
+        scheduler
+            .schedule_batch(vec![claim.clone(), deposit.clone(), fund.clone()])
+            .unwrap();
+
+        let mut order = Vec::new();
+        while let Some(task) = scheduler.execute_next() {
+            order.push(task.id().to_string());
+            scheduler
+                .complete_task(task.id(), "signature".to_string())
+                .unwrap();
+        }
+        assert_eq!(order, vec![fund.id(), deposit.id(), claim.id()]);
+    }
+
+    #[test]
+    fn test_unknown_dependency_is_rejected() {
+        let mut scheduler = TaskScheduler::new();
+        let task = transfer_task().with_dependencies(vec!["missing".to_string()]);
+
+        assert!(scheduler.schedule_task(task).is_err());
+        assert!(scheduler.is_empty());
+    }
+
+    #[test]
+    fn test_self_dependency_is_rejected() {
+        let mut scheduler = TaskScheduler::new();
+        let task = transfer_task();
+        let id = task.id().to_string();
+
+        assert!(scheduler
+            .schedule_task(task.with_dependencies(vec![id]))
+            .is_err());
+        assert!(scheduler.is_empty());
+    }
+
+    #[test]
+    fn test_cycle_in_batch_is_rejected() {
+        let mut scheduler = TaskScheduler::new();
+        let a = transfer_task();
+        let b = transfer_task().with_dependencies(vec![a.id().to_string()]);
+        let c = transfer_task().with_dependencies(vec![b.id().to_string()]);
+        let a = a.with_dependencies(vec![c.id().to_string()]);
+
+        let result = scheduler.schedule_batch(vec![a, b, c]);
+
+        assert_eq!(result, Err("Task dependencies form a cycle"));
+        assert!(scheduler.is_empty());
+    }
+
+    #[test]
+    fn test_duplicate_task_id_is_rejected() {
+        let mut scheduler = TaskScheduler::new();
+        let task = transfer_task();
+
+        scheduler.schedule_task(task.clone()).unwrap();
+        assert!(scheduler.schedule_task(task).is_err());
+        assert_eq!(scheduler.get_pending_count(), 1);
+    }
+
+    #[test]
+    fn test_failed_task_is_retried() {
+        let mut scheduler = TaskScheduler::new();
+        let task = transfer_task();
+        scheduler.schedule_task(task.clone()).unwrap();
+
+        scheduler.execute_next().unwrap();
+        scheduler
+            .fail_task(task.id(), "rpc error".to_string())
+            .unwrap();
+
+        assert_eq!(status_of(&scheduler, task.id()), TaskStatus::Pending);
+        assert_eq!(scheduler.execute_next().unwrap().id(), task.id());
+    }
+
+    #[test]
+    fn test_permanent_failure_cascades_cancellation() {
+        let mut scheduler = TaskScheduler::new();
+        let fund = transfer_task();
+        let deposit = transfer_task().with_dependencies(vec![fund.id().to_string()]);
+        let claim = transfer_task().with_dependencies(vec![deposit.id().to_string()]);
+        scheduler
+            .schedule_batch(vec![fund.clone(), deposit.clone(), claim.clone()])
+            .unwrap();
+
+        // Initial attempt plus three retries
+        for _ in 0..4 {
+            scheduler.execute_next().unwrap();
+            scheduler
+                .fail_task(fund.id(), "rpc error".to_string())
+                .unwrap();
+        }
+
+        assert_eq!(status_of(&scheduler, fund.id()), TaskStatus::Failed);
+        assert_eq!(status_of(&scheduler, deposit.id()), TaskStatus::Cancelled);
+        assert_eq!(status_of(&scheduler, claim.id()), TaskStatus::Cancelled);
+        assert!(scheduler.execute_next().is_none());
+        assert!(scheduler.is_empty());
+    }
+
+    #[test]
+    fn test_task_depending_on_failed_parent_is_cancelled() {
+        let mut scheduler = TaskScheduler::new();
+        let fund = transfer_task();
+        scheduler.schedule_task(fund.clone()).unwrap();
+        for _ in 0..4 {
+            scheduler.execute_next().unwrap();
+            scheduler
+                .fail_task(fund.id(), "rpc error".to_string())
+                .unwrap();
+        }
+
+        let deposit = transfer_task().with_dependencies(vec![fund.id().to_string()]);
+        scheduler.schedule_task(deposit.clone()).unwrap();
+
+        assert_eq!(status_of(&scheduler, deposit.id()), TaskStatus::Cancelled);
+        assert!(scheduler.is_empty());
+    }
+
+    #[test]
+    fn test_complete_requires_in_progress_task() {
+        let mut scheduler = TaskScheduler::new();
+        let task = transfer_task();
+        scheduler.schedule_task(task.clone()).unwrap();
+
+        assert!(scheduler
+            .complete_task(task.id(), "signature".to_string())
+            .is_err());
+        assert!(scheduler
+            .complete_task("missing", "signature".to_string())
+            .is_err());
+    }
+
+    #[test]
+    fn test_clear_all_drops_blocked_tasks() {
+        let mut scheduler = TaskScheduler::new();
+        let fund = transfer_task();
+        let deposit = transfer_task().with_dependencies(vec![fund.id().to_string()]);
+        scheduler.schedule_batch(vec![fund, deposit]).unwrap();
+
+        scheduler.clear_all();
+
+        assert!(scheduler.is_empty());
+        assert!(scheduler.execute_next().is_none());
+    }
+
+    // Dead-letter queue tests
+    fn exhaust_retries(scheduler: &mut TaskScheduler, id: &str) {
+        for attempt in 0..4 {
+            assert_eq!(scheduler.execute_next().unwrap().id(), id);
+            scheduler
+                .fail_task(id, format!("attempt {} failed", attempt))
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_exhausted_task_is_dead_lettered() {
+        let mut scheduler = TaskScheduler::new();
+        let task = transfer_task();
+        scheduler.schedule_task(task.clone()).unwrap();
+
+        exhaust_retries(&mut scheduler, task.id());
+
+        let dead = scheduler.dead_letters();
+        assert_eq!(dead.len(), 1);
+        assert_eq!(dead[0].id(), task.id());
+        assert_eq!(
+            dead[0].errors(),
+            &[
+                "attempt 0 failed",
+                "attempt 1 failed",
+                "attempt 2 failed",
+                "attempt 3 failed"
+            ]
+        );
+        assert!(scheduler.dead_letter(task.id()).is_some());
+    }
+
+    #[test]
+    fn test_retried_task_is_not_dead_lettered() {
+        let mut scheduler = TaskScheduler::new();
+        let task = transfer_task();
+        scheduler.schedule_task(task.clone()).unwrap();
+
+        scheduler.execute_next().unwrap();
+        scheduler
+            .fail_task(task.id(), "blockhash expired".to_string())
+            .unwrap();
+
+        assert!(scheduler.dead_letters().is_empty());
+        assert!(scheduler.dead_letter(task.id()).is_none());
+    }
+
+    #[test]
+    fn test_untagged_tasks_are_pinned_to_scheduler_cluster() {
+        let mut scheduler = TaskScheduler::new().with_cluster(Cluster::Devnet);
+
+        let scheduled = scheduler.schedule_task(transfer_task()).unwrap();
+
+        assert_eq!(scheduled.cluster(), Some(Cluster::Devnet));
+        assert_eq!(
+            scheduler.execute_next().unwrap().cluster(),
+            Some(Cluster::Devnet)
+        );
+    }
+
+    #[test]
+    fn test_task_of_another_cluster_is_not_scheduled() {
+        let mut scheduler = TaskScheduler::new().with_cluster(Cluster::Devnet);
+        let foreign = transfer_task().with_cluster(Cluster::Mainnet);
+
+        let result = scheduler.schedule_batch(vec![transfer_task(), foreign]);
+
+        assert_eq!(result.unwrap_err(), WRONG_CLUSTER);
+        assert_eq!(scheduler.get_pending_count(), 0);
+    }
+
+    #[test]
+    fn test_restored_task_of_another_cluster_is_refused() {
+        let mut scheduler = TaskScheduler::new().with_cluster(Cluster::Devnet);
+        let foreign = transfer_task().with_cluster(Cluster::Mainnet);
+        let child = transfer_task().with_dependencies(vec![foreign.id().to_string()]);
+        let local = transfer_task();
+        scheduler
+            .restore(vec![foreign.clone(), child.clone(), local.clone()])
+            .unwrap();
+
+        let executed = scheduler.execute_next().unwrap();
+
+        assert_eq!(executed.id(), local.id());
+        assert!(scheduler.execute_next().is_none());
+        let refused = scheduler.dead_letter(foreign.id()).unwrap();
+        assert_eq!(refused.status(), TaskStatus::Failed);
+        assert_eq!(
+            refused.errors(),
+            vec!["Task belongs to mainnet, not devnet"]
+        );
+        assert_eq!(status_of(&scheduler, child.id()), TaskStatus::Cancelled);
+    }
+
+    #[test]
+    fn test_scheduler_without_cluster_runs_any_task() {
+        let mut scheduler = TaskScheduler::new();
+        let task = transfer_task().with_cluster(Cluster::Mainnet);
+        scheduler.schedule_task(task.clone()).unwrap();
+
+        assert_eq!(scheduler.execute_next().unwrap().id(), task.id());
+    }
+
+    #[test]
+    fn test_scheduled_tasks_carry_their_wallet_name() {
+        let mut wallets = WalletManager::new();
+        let from = wallets.generate_wallet("payroll".to_string()).unwrap();
+        let mut scheduler = TaskScheduler::new().with_wallet_names(Arc::new(RwLock::new(wallets)));
+        let params = TransactionParams::Transfer(TransferParams {
+            from,
+            to: Pubkey::new_unique(),
+            lamports: 1_000,
+        });
+
+        let named = scheduler
+            .schedule_task(Task::new(serde_json::to_string(&params).unwrap()))
+            .unwrap();
+        let unknown = scheduler.schedule_task(transfer_task()).unwrap();
+
+        assert_eq!(named.wallet_name(), Some("payroll"));
+        assert_eq!(unknown.wallet_name(), None);
+    }
+
+    #[test]
+    fn test_max_retries_applies_to_scheduled_tasks() {
+        let mut scheduler = TaskScheduler::new().with_max_retries(1);
+        let task = transfer_task();
+
+        let scheduled = scheduler.schedule_task(task.clone()).unwrap();
+
+        assert_eq!(scheduled.max_retries(), 1);
+        for _ in 0..2 {
+            scheduler.execute_next().unwrap();
+            scheduler
+                .fail_task(task.id(), "rpc down".to_string())
+                .unwrap();
+        }
+        assert!(scheduler.dead_letter(task.id()).is_some());
+    }
+
+    #[test]
+    fn test_replay_dead_letter_resets_retries() {
+        let mut scheduler = TaskScheduler::new();
+        let task = transfer_task();
+        scheduler.schedule_task(task.clone()).unwrap();
+        exhaust_retries(&mut scheduler, task.id());
+
+        scheduler.replay_dead_letter(task.id()).unwrap();
+
+        assert!(scheduler.dead_letters().is_empty());
+        let replayed = scheduler.execute_next().unwrap();
+        assert_eq!(replayed.id(), task.id());
+        assert_eq!(replayed.retry_count(), 0);
+        // History survives the replay
+        assert_eq!(replayed.errors().len(), 4);
+    }
+
+    #[test]
+    fn test_edit_dead_letter_params() {
+        let mut scheduler = TaskScheduler::new();
+        let task = transfer_task();
+        scheduler.schedule_task(task.clone()).unwrap();
+        exhaust_retries(&mut scheduler, task.id());
+
+        let fixed = TransactionParams::Transfer(TransferParams {
+            from: Pubkey::new_unique(),
+            to: Pubkey::new_unique(),
+            lamports: 500_000,
+        });
+        scheduler
+            .edit_dead_letter_params(task.id(), serde_json::to_string(&fixed).unwrap())
+            .unwrap();
+        scheduler.replay_dead_letter(task.id()).unwrap();
+
+        assert_eq!(scheduler.execute_next().unwrap().params().unwrap(), fixed);
+    }
+
+    #[test]
+    fn test_edit_dead_letter_rejects_invalid_params() {
+        let mut scheduler = TaskScheduler::new();
+        let task = transfer_task();
+        scheduler.schedule_task(task.clone()).unwrap();
+        exhaust_retries(&mut scheduler, task.id());
+
+        let result = scheduler.edit_dead_letter_params(task.id(), "{}".to_string());
+
+        assert_eq!(result, Err("Invalid transaction params"));
+    }
+
+    #[test]
+    fn test_live_task_cannot_be_replayed_or_edited() {
+        let mut scheduler = TaskScheduler::new();
+        let task = transfer_task();
+        scheduler.schedule_task(task.clone()).unwrap();
+        let params = task.params().unwrap();
+
+        assert!(scheduler.replay_dead_letter(task.id()).is_err());
+        assert!(scheduler
+            .edit_dead_letter_params(task.id(), serde_json::to_string(&params).unwrap())
+            .is_err());
+    }
+
+    // Cancellation and dispatch control tests
+    #[test]
+    fn test_cancel_queued_task() {
+        let mut scheduler = TaskScheduler::new();
+        let keep = transfer_task();
+        let cancel = transfer_task();
+        scheduler.schedule_task(cancel.clone()).unwrap();
+        scheduler.schedule_task(keep.clone()).unwrap();
+
+        scheduler.cancel_task(cancel.id()).unwrap();
+
+        assert_eq!(status_of(&scheduler, cancel.id()), TaskStatus::Cancelled);
+        assert_eq!(scheduler.get_pending_count(), 1);
+        assert_eq!(scheduler.execute_next().unwrap().id(), keep.id());
+        assert!(scheduler.execute_next().is_none());
+        assert!(scheduler.is_empty());
+    }
+
+    #[test]
+    fn test_cancel_cascades_to_dependents() {
+        let mut scheduler = TaskScheduler::new();
+        let fund = transfer_task();
+        let deposit = transfer_task().with_dependencies(vec![fund.id().to_string()]);
+        scheduler
+            .schedule_batch(vec![fund.clone(), deposit.clone()])
+            .unwrap();
+
+        scheduler.cancel_task(fund.id()).unwrap();
+
+        assert_eq!(status_of(&scheduler, deposit.id()), TaskStatus::Cancelled);
+        assert!(scheduler.is_empty());
+    }
+
+    #[test]
+    fn test_cancel_in_progress_blocks_retries() {
+        let mut scheduler = TaskScheduler::new();
+        let task = transfer_task();
+        scheduler.schedule_task(task.clone()).unwrap();
+        scheduler.execute_next().unwrap();
+
+        scheduler.cancel_task(task.id()).unwrap();
+        assert_eq!(status_of(&scheduler, task.id()), TaskStatus::Cancelled);
+
+        // The running attempt still reports back, but is not retried
+        scheduler
+            .fail_task(task.id(), "rpc error".to_string())
+            .unwrap();
+        assert_eq!(status_of(&scheduler, task.id()), TaskStatus::Cancelled);
+        assert!(scheduler.execute_next().is_none());
+        assert!(scheduler.dead_letters().is_empty());
+    }
+
+    #[test]
+    fn test_cancel_in_progress_stays_cancelled_when_attempt_lands() {
+        let mut scheduler = TaskScheduler::new();
+        let fund = transfer_task();
+        let deposit = transfer_task().with_dependencies(vec![fund.id().to_string()]);
+        scheduler
+            .schedule_batch(vec![fund.clone(), deposit.clone()])
+            .unwrap();
+        scheduler.execute_next().unwrap();
+        let mut events = scheduler.subscribe();
+
+        scheduler.cancel_task(fund.id()).unwrap();
+        scheduler
+            .complete_task(fund.id(), "signature".to_string())
+            .unwrap();
+
+        assert_eq!(status_of(&scheduler, fund.id()), TaskStatus::Cancelled);
+        let attempt = &scheduler.task(fund.id()).unwrap().attempts()[0];
+        assert_eq!(attempt.signature.as_deref(), Some("signature"));
+        assert_eq!(attempt.error, None);
+        assert_eq!(status_of(&scheduler, deposit.id()), TaskStatus::Cancelled);
+        assert!(scheduler.execute_next().is_none());
+        let kinds: Vec<TaskEventKind> = std::iter::from_fn(|| events.try_recv().ok())
+            .map(|event| event.kind)
+            .collect();
+        assert!(!kinds.contains(&TaskEventKind::Succeeded));
+    }
+
+    #[test]
+    fn test_cancel_finished_task_fails() {
+        let mut scheduler = TaskScheduler::new();
+        let task = transfer_task();
+        scheduler.schedule_task(task.clone()).unwrap();
+        scheduler.execute_next().unwrap();
+        scheduler
+            .complete_task(task.id(), "signature".to_string())
+            .unwrap();
+
+        assert_eq!(
+            scheduler.cancel_task(task.id()),
+            Err("Task already finished")
+        );
+        assert_eq!(scheduler.cancel_task("missing"), Err("Task not found"));
+    }
+
+    #[test]
+    fn test_cancel_tag() {
+        let mut scheduler = TaskScheduler::new();
+        let kamino: Vec<Task> = (0..3).map(|_| transfer_task().with_tag("kamino")).collect();
+        let marginfi = transfer_task().with_tag("marginfi");
+        let untagged = transfer_task();
+        for task in kamino.iter().chain([&marginfi, &untagged]) {
+            scheduler.schedule_task(task.clone()).unwrap();
+        }
+
+        assert_eq!(scheduler.cancel_tag("kamino"), 3);
+
+        for task in &kamino {
+            assert_eq!(status_of(&scheduler, task.id()), TaskStatus::Cancelled);
+        }
+        assert_eq!(scheduler.get_pending_count(), 2);
+        assert_eq!(scheduler.cancel_tag("kamino"), 0);
+    }
+
+    #[test]
+    fn test_pause_and_resume() {
+        let mut scheduler = TaskScheduler::new();
+        let task = transfer_task();
+        scheduler.schedule_task(task.clone()).unwrap();
+
+        scheduler.pause();
+        assert!(scheduler.is_paused());
+        assert!(scheduler.execute_next().is_none());
+        assert_eq!(scheduler.get_pending_count(), 1);
+
+        // Scheduling still works while paused
+        scheduler.schedule_task(transfer_task()).unwrap();
+        assert_eq!(scheduler.get_pending_count(), 2);
+
+        scheduler.resume();
+        assert!(!scheduler.is_paused());
+        assert_eq!(scheduler.execute_next().unwrap().id(), task.id());
+    }
+
+    // Idempotency tests
+    #[test]
+    fn test_duplicate_idempotency_key_returns_existing_task() {
+        let mut scheduler = TaskScheduler::new();
+        let first = transfer_task().with_idempotency_key("claim-wallet_1");
+        let retry = transfer_task().with_idempotency_key("claim-wallet_1");
+
+        let scheduled = scheduler.schedule_task(first.clone()).unwrap();
+        let deduplicated = scheduler.schedule_task(retry).unwrap();
+
+        assert_eq!(scheduled, first);
+        assert_eq!(deduplicated.id(), first.id());
+        assert_eq!(scheduler.get_pending_count(), 1);
+    }
+
+    #[test]
+    fn test_tasks_without_keys_are_not_deduplicated() {
+        let mut scheduler = TaskScheduler::new();
+
+        scheduler.schedule_task(transfer_task()).unwrap();
+        scheduler.schedule_task(transfer_task()).unwrap();
+
+        assert_eq!(scheduler.get_pending_count(), 2);
+    }
+
+    #[test]
+    fn test_different_keys_are_both_scheduled() {
+        let mut scheduler = TaskScheduler::new();
+
+        scheduler
+            .schedule_task(transfer_task().with_idempotency_key("a"))
+            .unwrap();
+        scheduler
+            .schedule_task(transfer_task().with_idempotency_key("b"))
+            .unwrap();
+
+        assert_eq!(scheduler.get_pending_count(), 2);
+    }
+
+    #[test]
+    fn test_idempotency_key_expires_after_window() {
+        let mut scheduler = TaskScheduler::new().with_idempotency_window(Duration::ZERO);
+        let first = transfer_task().with_idempotency_key("claim");
+        let second = transfer_task().with_idempotency_key("claim");
+
+        scheduler.schedule_task(first).unwrap();
+        let scheduled = scheduler.schedule_task(second.clone()).unwrap();
+
+        assert_eq!(scheduled.id(), second.id());
+        assert_eq!(scheduler.get_pending_count(), 2);
+    }
+
+    #[test]
+    fn test_idempotency_key_outlives_task_completion() {
+        let mut scheduler = TaskScheduler::new();
+        let first = transfer_task().with_idempotency_key("claim");
+        scheduler.schedule_task(first.clone()).unwrap();
+        scheduler.execute_next().unwrap();
+        scheduler
+            .complete_task(first.id(), "signature".to_string())
+            .unwrap();
+
+        let deduplicated = scheduler
+            .schedule_task(transfer_task().with_idempotency_key("claim"))
+            .unwrap();
+
+        assert_eq!(deduplicated.id(), first.id());
+        assert_eq!(deduplicated.status(), TaskStatus::Completed);
+        assert!(scheduler.is_empty());
+    }
+
+    #[test]
+    fn test_finished_tasks_are_forgotten_after_retention() {
+        let mut scheduler = TaskScheduler::new()
+            .with_retention(Duration::ZERO)
+            .with_max_retries(0);
+        let done = transfer_task().with_idempotency_key("claim");
+        scheduler.schedule_task(done.clone()).unwrap();
+        scheduler.execute_next().unwrap();
+        scheduler
+            .complete_task(done.id(), "signature".to_string())
+            .unwrap();
+        let dead = scheduler.schedule_task(transfer_task()).unwrap();
+        scheduler.execute_next().unwrap();
+        scheduler
+            .fail_task(dead.id(), "rpc error".to_string())
+            .unwrap();
+
+        let again = scheduler
+            .schedule_task(transfer_task().with_idempotency_key("claim"))
+            .unwrap();
+
+        assert!(scheduler.task(done.id()).is_none());
+        assert!(scheduler.task(dead.id()).is_none());
+        assert!(scheduler.dead_letters().is_empty());
+        assert_ne!(again.id(), done.id());
+        assert_eq!(scheduler.snapshot(), vec![again]);
+    }
+
+    #[test]
+    fn test_retention_keeps_parents_of_live_tasks() {
+        let mut scheduler = TaskScheduler::new().with_retention(Duration::ZERO);
+        let fund = transfer_task();
+        let approve = transfer_task();
+        let deposit = transfer_task()
+            .with_dependencies(vec![fund.id().to_string(), approve.id().to_string()]);
+        scheduler
+            .schedule_batch(vec![fund.clone(), approve.clone(), deposit.clone()])
+            .unwrap();
+        let first = scheduler.execute_next().unwrap();
+        let second = scheduler.execute_next().unwrap();
+        scheduler
+            .complete_task(first.id(), "signature".to_string())
+            .unwrap();
+
+        assert_eq!(scheduler.evict_finished(), 0);
+        scheduler
+            .complete_task(second.id(), "signature".to_string())
+            .unwrap();
+        assert_eq!(scheduler.execute_next().unwrap().id(), deposit.id());
+        scheduler
+            .complete_task(deposit.id(), "signature".to_string())
+            .unwrap();
+        assert_eq!(scheduler.evict_finished(), 3);
+        assert!(scheduler.snapshot().is_empty());
+    }
+
+    #[test]
+    fn test_cancelled_task_is_kept_until_its_attempt_reports() {
+        let mut scheduler = TaskScheduler::new().with_retention(Duration::ZERO);
+        let task = scheduler.schedule_task(transfer_task()).unwrap();
+        scheduler.execute_next().unwrap();
+        scheduler.cancel_task(task.id()).unwrap();
+
+        assert_eq!(scheduler.evict_finished(), 0);
+        scheduler
+            .fail_task(task.id(), "rpc error".to_string())
+            .unwrap();
+        assert_eq!(scheduler.in_flight_count(), 0);
+        assert_eq!(scheduler.evict_finished(), 1);
+    }
+
+    #[test]
+    fn test_resubmitted_batch_is_deduplicated() {
+        let mut scheduler = TaskScheduler::new();
+        let flow = |scheduler: &mut TaskScheduler| {
+            let fund = transfer_task().with_idempotency_key("fund");
+            let deposit = transfer_task()
+                .with_idempotency_key("deposit")
+                .with_dependencies(vec![fund.id().to_string()]);
+            scheduler.schedule_batch(vec![fund, deposit]).unwrap()
+        };
+
+        let original = flow(&mut scheduler);
+        let resubmitted = flow(&mut scheduler);
+
+        assert_eq!(original, resubmitted);
+        assert_eq!(scheduler.get_pending_count(), 2);
+    }
+
+    #[test]
+    fn test_new_dependent_is_remapped_onto_existing_parent() {
+        let mut scheduler = TaskScheduler::new();
+        let fund = transfer_task().with_idempotency_key("fund");
+        scheduler.schedule_task(fund.clone()).unwrap();
+
+        let fund_again = transfer_task().with_idempotency_key("fund");
+        let deposit = transfer_task().with_dependencies(vec![fund_again.id().to_string()]);
+        let scheduled = scheduler.schedule_batch(vec![fund_again, deposit]).unwrap();
+
+        assert_eq!(scheduled[0].id(), fund.id());
+        assert_eq!(scheduled[1].dependencies(), &[fund.id().to_string()]);
+        assert_eq!(scheduler.get_pending_count(), 2);
+    }
+
+    // Bounded queue tests
+    #[test]
+    fn test_bounded_scheduler_rejects_when_full() {
+        let mut scheduler =
+            TaskScheduler::new().with_queue(TaskQueue::with_capacity(2, OverflowPolicy::Reject));
+        scheduler.schedule_task(transfer_task()).unwrap();
+        scheduler.schedule_task(transfer_task()).unwrap();
+
+        let rejected = transfer_task();
+        assert_eq!(scheduler.schedule_task(rejected.clone()), Err(QUEUE_FULL));
+        assert!(scheduler.task(rejected.id()).is_none());
+        assert_eq!(
+            scheduler.queue_depth(),
+            QueueDepth {
+                queued: 2,
+                capacity: Some(2)
+            }
+        );
+    }
+
+    #[test]
+    fn test_batch_that_does_not_fit_is_rejected_whole() {
+        let mut scheduler =
+            TaskScheduler::new().with_queue(TaskQueue::with_capacity(2, OverflowPolicy::Reject));
+        let batch: Vec<Task> = (0..3).map(|_| transfer_task()).collect();
+
+        assert_eq!(scheduler.schedule_batch(batch), Err(QUEUE_FULL));
+        assert!(scheduler.is_empty());
+    }
+
+    #[test]
+    fn test_blocked_dependents_do_not_count_against_capacity() {
+        let mut scheduler =
+            TaskScheduler::new().with_queue(TaskQueue::with_capacity(1, OverflowPolicy::Reject));
+        let fund = transfer_task();
+        let deposit = transfer_task().with_dependencies(vec![fund.id().to_string()]);
+        scheduler
+            .schedule_batch(vec![fund.clone(), deposit])
+            .unwrap();
+
+        // Released and retried work is already admitted and bypasses the bound
+        scheduler.execute_next().unwrap();
+        scheduler.schedule_task(transfer_task()).unwrap();
+        scheduler
+            .complete_task(fund.id(), "signature".to_string())
+            .unwrap();
+
+        assert_eq!(scheduler.queue_depth().queued, 2);
+    }
+
+    #[test]
+    fn test_cancelled_tasks_free_their_queue_slots() {
+        let mut scheduler =
+            TaskScheduler::new().with_queue(TaskQueue::with_capacity(2, OverflowPolicy::Reject));
+        let cancelled: Vec<Task> = (0..2)
+            .map(|_| scheduler.schedule_task(transfer_task()).unwrap())
+            .collect();
+        for task in &cancelled {
+            scheduler.cancel_task(task.id()).unwrap();
+        }
+
+        assert_eq!(scheduler.queue_depth().queued, 0);
+        let batch: Vec<Task> = (0..2).map(|_| transfer_task()).collect();
+        scheduler.schedule_batch(batch).unwrap();
+        assert_eq!(scheduler.get_pending_count(), 2);
+    }
+
+    #[test]
+    fn test_drop_lowest_priority_rejects_batch_that_cannot_evict_enough() {
+        let mut scheduler = TaskScheduler::new().with_queue(TaskQueue::with_capacity(
+            2,
+            OverflowPolicy::DropLowestPriority,
+        ));
+        let low = transfer_task().with_priority(TaskPriority::Low);
+        let normal = transfer_task();
+        scheduler.schedule_task(low.clone()).unwrap();
+        scheduler.schedule_task(normal.clone()).unwrap();
+
+        // Only the Low task ranks below Normal, so the second task has nothing to evict
+        let batch = vec![
+            transfer_task().with_priority(TaskPriority::Normal),
+            transfer_task().with_priority(TaskPriority::Normal),
+        ];
+        assert_eq!(scheduler.schedule_batch(batch.clone()), Err(QUEUE_FULL));
+
+        assert_eq!(status_of(&scheduler, low.id()), TaskStatus::Pending);
+        assert!(batch.iter().all(|task| scheduler.task(task.id()).is_none()));
+        assert_eq!(scheduler.get_pending_count(), 2);
+    }
+
+    #[test]
+    fn test_drop_lowest_priority_cancels_evicted_task() {
+        let mut scheduler = TaskScheduler::new().with_queue(TaskQueue::with_capacity(
+            1,
+            OverflowPolicy::DropLowestPriority,
+        ));
+        let low = transfer_task().with_priority(TaskPriority::Low);
+        let child = transfer_task().with_dependencies(vec![low.id().to_string()]);
+        scheduler
+            .schedule_batch(vec![low.clone(), child.clone()])
+            .unwrap();
+
+        let critical = transfer_task().with_priority(TaskPriority::Critical);
+        scheduler.schedule_task(critical.clone()).unwrap();
+
+        assert_eq!(status_of(&scheduler, low.id()), TaskStatus::Cancelled);
+        let evicted = scheduler.task(low.id()).unwrap();
+        assert_eq!(evicted.errors(), vec!["Dropped from full queue"]);
+        assert!(evicted.attempts()[0].finished_at.is_some());
+        assert_eq!(status_of(&scheduler, child.id()), TaskStatus::Cancelled);
+        assert_eq!(scheduler.execute_next().unwrap().id(), critical.id());
+        assert!(scheduler.is_empty());
+    }
+
+    #[test]
+    fn test_throttled_attempt_does_not_use_a_retry() {
+        let mut scheduler = TaskScheduler::new().with_max_retries(0);
+        let task = scheduler.schedule_task(transfer_task()).unwrap();
+        scheduler.execute_next().unwrap();
+
+        scheduler
+            .report_attempt(task.id(), AttemptReport::throttled("429 Too Many Requests"))
+            .unwrap();
+
+        let tracked = scheduler.task(task.id()).unwrap();
+        assert_eq!(tracked.status(), TaskStatus::Pending);
+        assert_eq!(tracked.retry_count(), 0);
+        assert_eq!(tracked.errors(), vec!["429 Too Many Requests"]);
+        assert!(scheduler.dead_letters().is_empty());
+        assert_eq!(scheduler.execute_next().unwrap().id(), task.id());
+    }
+
+    // Attempt history tests
+    #[test]
+    fn test_attempts_recorded_across_retries() {
+        let mut scheduler = TaskScheduler::new();
+        let task = transfer_task();
+        scheduler.schedule_task(task.clone()).unwrap();
+
+        scheduler.execute_next().unwrap();
+        scheduler
+            .report_attempt(
+                task.id(),
+                AttemptReport::failure("Transaction simulation failed")
+                    .with_blockhash("hash-1")
+                    .with_simulation_logs(vec!["insufficient lamports".to_string()]),
+            )
+            .unwrap();
+        scheduler.execute_next().unwrap();
+        scheduler
+            .report_attempt(
+                task.id(),
+                AttemptReport::success("sig-2")
+                    .with_blockhash("hash-2")
+                    .with_fee(5_000),
+            )
+            .unwrap();
+
+        let attempts = scheduler.task(task.id()).unwrap().attempts();
+        assert_eq!(attempts.len(), 2);
+        assert_eq!(attempts[0].blockhash.as_deref(), Some("hash-1"));
+        assert_eq!(
+            attempts[0].error.as_deref(),
+            Some("Transaction simulation failed")
+        );
+        assert_eq!(attempts[0].simulation_logs, vec!["insufficient lamports"]);
+        assert_eq!(attempts[1].signature.as_deref(), Some("sig-2"));
+        assert_eq!(attempts[1].fee_lamports, Some(5_000));
+        assert!(attempts[1].error.is_none());
+    }
+
+    #[test]
+    fn test_in_progress_attempt_is_open() {
+        let mut scheduler = TaskScheduler::new();
+        let task = transfer_task();
+        scheduler.schedule_task(task.clone()).unwrap();
+
+        scheduler.execute_next().unwrap();
+
+        let tracked = scheduler.task(task.id()).unwrap();
+        assert_eq!(tracked.attempts().len(), 1);
+        assert!(tracked.attempts()[0].finished_at.is_none());
+        assert_eq!(
+            tracked.last_attempt(),
+            Some(tracked.attempts()[0].started_at)
+        );
+    }
+
+    // Execution lease tests
+    #[test]
+    fn test_expired_leases_use_task_timeout() {
+        let mut scheduler = TaskScheduler::new();
+        let stuck = transfer_task().with_timeout(Duration::ZERO);
+        let live = transfer_task();
+        scheduler.schedule_batch(vec![stuck.clone(), live]).unwrap();
+        scheduler.execute_next().unwrap();
+        scheduler.execute_next().unwrap();
+
+        let expired = scheduler.expired_leases(Duration::ZERO);
+
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].id(), stuck.id());
+        assert_eq!(scheduler.timeout_for(&expired[0]), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_default_timeout_applies_without_task_timeout() {
+        let mut scheduler = TaskScheduler::new().with_default_timeout(Duration::ZERO);
+        scheduler.schedule_task(transfer_task()).unwrap();
+        scheduler.execute_next().unwrap();
+
+        assert_eq!(scheduler.expired_leases(Duration::ZERO).len(), 1);
+        assert!(scheduler.expired_leases(Duration::from_secs(60)).is_empty());
+    }
+
+    #[test]
+    fn test_sent_signature_survives_report_without_one() {
+        let mut scheduler = TaskScheduler::new();
+        let task = transfer_task();
+        scheduler.schedule_task(task.clone()).unwrap();
+        scheduler.execute_next().unwrap();
+
+        scheduler
+            .record_sent(task.id(), 1, "sig".to_string(), Some("hash".to_string()))
+            .unwrap();
+        scheduler
+            .report_attempt_number(task.id(), 1, AttemptReport::failure("confirmation failed"))
+            .unwrap();
+
+        let attempt = &scheduler.task(task.id()).unwrap().attempts()[0];
+        assert_eq!(attempt.signature.as_deref(), Some("sig"));
+        assert_eq!(attempt.blockhash.as_deref(), Some("hash"));
+    }
+
+    #[test]
+    fn test_late_report_from_expired_attempt_is_rejected() {
+        let mut scheduler = TaskScheduler::new();
+        let task = transfer_task();
+        scheduler.schedule_task(task.clone()).unwrap();
+        scheduler.execute_next().unwrap();
+
+        scheduler
+            .expire_attempt(
+                task.id(),
+                1,
+                AttemptReport::failure("Execution lease expired"),
+            )
+            .unwrap();
+        scheduler.execute_next().unwrap();
+
+        assert!(scheduler
+            .report_attempt_number(task.id(), 1, AttemptReport::success("late"))
+            .is_err());
+        assert!(scheduler
+            .record_sent(task.id(), 1, "late".to_string(), None)
+            .is_err());
+        assert_eq!(status_of(&scheduler, task.id()), TaskStatus::InProgress);
+        assert!(scheduler
+            .report_attempt_number(task.id(), 2, AttemptReport::success("sig"))
+            .is_ok());
+        assert_eq!(status_of(&scheduler, task.id()), TaskStatus::Completed);
+    }
+
+    // Fair scheduling tests
+    #[test]
+    fn test_tag_weights_apply_to_dispatch() {
+        let mut scheduler = TaskScheduler::new();
+        scheduler.set_tag_weight("kamino", 2);
+        for _ in 0..6 {
+            scheduler
+                .schedule_task(transfer_task().with_tag("kamino"))
+                .unwrap();
+            scheduler
+                .schedule_task(transfer_task().with_tag("marginfi"))
+                .unwrap();
+        }
+
+        let kamino = (0..6)
+            .filter(|_| scheduler.execute_next().unwrap().tag() == Some("kamino"))
+            .count();
+
+        assert_eq!(kamino, 4);
+    }
+
+    // Shutdown tests
+    #[test]
+    fn test_shutdown_stops_intake_and_dispatch() {
+        let mut scheduler = TaskScheduler::new();
+        scheduler.schedule_task(transfer_task()).unwrap();
+
+        scheduler.begin_shutdown();
+
+        assert!(scheduler.is_shutting_down());
+        assert_eq!(
+            scheduler.schedule_task(transfer_task()),
+            Err("Scheduler is shutting down")
+        );
+        assert!(scheduler.execute_next().is_none());
+    }
+
+    #[test]
+    fn test_in_flight_attempt_may_report_during_shutdown() {
+        let mut scheduler = TaskScheduler::new();
+        let task = transfer_task();
+        scheduler.schedule_task(task.clone()).unwrap();
+        scheduler.execute_next().unwrap();
+
+        scheduler.begin_shutdown();
+        assert_eq!(scheduler.in_flight_count(), 1);
+        scheduler
+            .complete_task(task.id(), "sig".to_string())
+            .unwrap();
+
+        assert_eq!(scheduler.in_flight_count(), 0);
+        assert_eq!(status_of(&scheduler, task.id()), TaskStatus::Completed);
+    }
+
+    #[test]
+    fn test_take_unfinished_returns_pending_tasks() {
+        let mut scheduler = TaskScheduler::new();
+        let running = transfer_task();
+        let queued = transfer_task();
+        let blocked = transfer_task().with_dependencies(vec![running.id().to_string()]);
+        let done = transfer_task();
+        scheduler.schedule_task(running.clone()).unwrap();
+        scheduler.execute_next().unwrap();
+        scheduler
+            .record_sent(running.id(), 1, "sent".to_string(), None)
+            .unwrap();
+        scheduler.schedule_task(done.clone()).unwrap();
+        scheduler.execute_next().unwrap();
+        scheduler
+            .complete_task(done.id(), "sig".to_string())
+            .unwrap();
+        scheduler
+            .schedule_batch(vec![queued.clone(), blocked.clone()])
+            .unwrap();
+        scheduler.execute_next().unwrap();
+        scheduler
+            .fail_task(queued.id(), "rpc error".to_string())
+            .unwrap();
+
+        let unfinished = scheduler.take_unfinished();
+
+        let ids: HashSet<&str> = unfinished.iter().map(|task| task.id()).collect();
+        assert_eq!(
+            ids,
+            HashSet::from([running.id(), queued.id(), blocked.id()])
+        );
+        assert!(unfinished
+            .iter()
+            .all(|task| task.status() == TaskStatus::Pending));
+        let running = unfinished
+            .iter()
+            .find(|task| task.id() == running.id())
+            .unwrap();
+        assert_eq!(running.retry_count(), 0);
+        assert_eq!(running.attempts()[0].error.as_deref(), Some(INTERRUPTED));
+        assert_eq!(running.attempts()[0].signature.as_deref(), Some("sent"));
+        assert!(scheduler.is_empty());
+        assert_eq!(scheduler.in_flight_count(), 0);
+        assert_eq!(status_of(&scheduler, done.id()), TaskStatus::Completed);
+    }
+
+    #[test]
+    fn test_restore_reschedules_unfinished_tasks() {
+        let mut old = TaskScheduler::new();
+        let parent = transfer_task();
+        let child = transfer_task().with_dependencies(vec![parent.id().to_string()]);
+        let grandchild = transfer_task().with_dependencies(vec![child.id().to_string()]);
+        old.schedule_batch(vec![parent.clone(), child.clone(), grandchild.clone()])
+            .unwrap();
+        old.execute_next().unwrap();
+        old.complete_task(parent.id(), "sig".to_string()).unwrap();
+        old.begin_shutdown();
+        let unfinished = old.take_unfinished();
+
+        let mut restored = TaskScheduler::new();
+        restored.restore(unfinished).unwrap();
+
+        // The completed parent was not persisted, so the child is runnable at once
+        assert_eq!(restored.execute_next().unwrap().id(), child.id());
+        assert!(restored.execute_next().is_none());
+        restored
+            .complete_task(child.id(), "sig".to_string())
+            .unwrap();
+        assert_eq!(restored.execute_next().unwrap().id(), grandchild.id());
+    }
+
+    #[test]
+    fn test_restore_holds_tasks_whose_interrupted_attempt_was_sent() {
+        let mut old = TaskScheduler::new();
+        old.schedule_batch(vec![transfer_task(), transfer_task()])
+            .unwrap();
+        let sent = old.execute_next().unwrap();
+        old.record_sent(sent.id(), 1, "5igna7ure".to_string(), None)
+            .unwrap();
+        let unsent = old.execute_next().unwrap();
+        let unfinished = old.take_unfinished();
+
+        let mut restored = TaskScheduler::new();
+        restored.restore(unfinished).unwrap();
+
+        assert_eq!(restored.get_pending_count(), 2);
+        assert_eq!(restored.execute_next().unwrap().id(), unsent.id());
+        assert!(restored.execute_next().is_none());
+        // Its lease has not run out, so the transaction could still land
+        assert!(restored.unverified_restores(Duration::ZERO).is_empty());
+
+        restored
+            .resolve_restored(sent.id(), LandingStatus::Landed)
+            .unwrap();
+        assert_eq!(status_of(&restored, sent.id()), TaskStatus::Completed);
+        assert_eq!(
+            restored.resolve_restored(sent.id(), LandingStatus::Landed),
+            Err("Task is not awaiting a landing check")
+        );
+    }
+
+    #[test]
+    fn test_cancelling_a_held_restored_task_releases_it() {
+        let mut old = TaskScheduler::new();
+        let task = old.schedule_task(transfer_task()).unwrap();
+        old.execute_next().unwrap();
+        old.record_sent(task.id(), 1, "5igna7ure".to_string(), None)
+            .unwrap();
+        let mut restored = TaskScheduler::new();
+        restored.restore(old.take_unfinished()).unwrap();
+
+        restored.cancel_task(task.id()).unwrap();
+
+        assert!(restored.is_empty());
+        assert!(restored
+            .resolve_restored(task.id(), LandingStatus::NotLanded)
+            .is_err());
+    }
+
+    #[test]
+    fn test_snapshot_and_load_preserve_finished_tasks() {
+        let mut scheduler = TaskScheduler::new();
+        let done = transfer_task();
+        let done_id = done.id().to_string();
+        scheduler.schedule_task(done).unwrap();
+        scheduler.execute_next().unwrap();
+        scheduler
+            .complete_task(&done_id, "5igna7ure".to_string())
+            .unwrap();
+        let failed_id = scheduler
+            .schedule_task(transfer_task())
+            .unwrap()
+            .id()
+            .to_string();
+        exhaust_retries(&mut scheduler, &failed_id);
+        let waiting_id = scheduler
+            .schedule_task(transfer_task())
+            .unwrap()
+            .id()
+            .to_string();
+        let child = transfer_task().with_dependencies(vec![done_id.clone(), waiting_id.clone()]);
+        let child_id = scheduler.schedule_task(child).unwrap().id().to_string();
+
+        let mut loaded = TaskScheduler::new();
+        loaded.load(scheduler.snapshot()).unwrap();
+
+        assert_eq!(status_of(&loaded, &done_id), TaskStatus::Completed);
+        assert_eq!(status_of(&loaded, &failed_id), TaskStatus::Failed);
+        assert!(loaded.dead_letter(&failed_id).is_some());
+        assert_eq!(loaded.get_pending_count(), 2);
+        assert_eq!(loaded.snapshot().len(), 4);
+        assert_eq!(loaded.execute_next().unwrap().id(), waiting_id);
+        assert!(loaded.execute_next().is_none());
+        loaded
+            .complete_task(&waiting_id, "5igna7ure".to_string())
+            .unwrap();
+        assert_eq!(loaded.execute_next().unwrap().id(), child_id);
+    }
+
+    #[test]
+    fn test_load_interrupts_open_attempts() {
+        let mut scheduler = TaskScheduler::new();
+        scheduler.schedule_task(transfer_task()).unwrap();
+        let running = scheduler.execute_next().unwrap();
+
+        let mut loaded = TaskScheduler::new();
+        loaded.load(scheduler.snapshot()).unwrap();
+
+        let task = loaded.task(running.id()).unwrap();
+        assert_eq!(task.status(), TaskStatus::Pending);
+        assert_eq!(task.attempts()[0].error.as_deref(), Some(INTERRUPTED));
+        assert_eq!(task.retry_count(), 0);
+    }
+
+    #[test]
+    fn test_load_rejects_tracked_tasks() {
+        let mut scheduler = TaskScheduler::new();
+        scheduler.schedule_task(transfer_task()).unwrap();
+        let running = scheduler.execute_next().unwrap();
+        scheduler
+            .complete_task(running.id(), "5igna7ure".to_string())
+            .unwrap();
+        let snapshot = scheduler.snapshot();
+
+        assert_eq!(scheduler.load(snapshot), Err("Task already scheduled"));
+    }
 }
@@ -0,0 +1,199 @@
+use crate::task::Task;
+use engine::transaction::{migrate_params, PARAMS_SCHEMA_VERSION};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+/// Version of the persisted task envelope. Bump it, and add a step to
+/// `migrate_task`, whenever a change to `Task` would break older JSON.
+pub const TASK_SCHEMA_VERSION: u32 = 4;
+
+/// The persisted form of a task. Version 1 predates the envelope and was a bare task.
+#[derive(Serialize, Deserialize)]
+struct TaskEnvelope<T> {
+    schema_version: u32,
+    /// Version of the `TransactionParams` JSON embedded in the task
+    params_version: u32,
+    task: T,
+}
+
+impl Task {
+    /// Serializes the task inside a versioned envelope, for persisting
+    pub fn to_versioned_json(&self) -> Result<String, String> {
+        let envelope = TaskEnvelope {
+            schema_version: TASK_SCHEMA_VERSION,
+            params_version: PARAMS_SCHEMA_VERSION,
+            task: self,
+        };
+        serde_json::to_string(&envelope).map_err(|e| format!("Failed to serialize task: {}", e))
+    }
+
+    /// Loads a task persisted at any known schema version, migrating it and its
+    /// params to the current format
+    pub fn from_versioned_json(json: &str) -> Result<Task, String> {
+        let value: Value =
+            serde_json::from_str(json).map_err(|e| format!("Invalid task JSON: {}", e))?;
+        let envelope = match value.get("schema_version") {
+            Some(_) => serde_json::from_value(value)
+                .map_err(|e| format!("Invalid task envelope: {}", e))?,
+            None => TaskEnvelope {
+                schema_version: 1,
+                params_version: 1,
+                task: value,
+            },
+        };
+
+        let mut task = migrate_task(envelope.task, envelope.schema_version)?;
+        if let Some(params) = task.get_mut("transaction_params") {
+            let json = params.as_str().ok_or("Task params are not a string")?;
+            *params = Value::String(migrate_params(json, envelope.params_version)?);
+        }
+        serde_json::from_value(task).map_err(|e| format!("Invalid task: {}", e))
+    }
+}
+
+/// Upgrades task JSON written at `version` to the current schema, one version at a time
+pub fn migrate_task(mut task: Value, version: u32) -> Result<Value, String> {
+    if version > TASK_SCHEMA_VERSION {
+        return Err(format!(
+            "Task schema version {} is newer than supported version {}",
+            version, TASK_SCHEMA_VERSION
+        ));
+    }
+    for from in version..TASK_SCHEMA_VERSION {
+        let fields = task.as_object_mut().ok_or("Task JSON is not an object")?;
+        match from {
+            1 => v1_to_v2(fields),
+            2 => v2_to_v3(fields),
+            3 => v3_to_v4(fields),
+            _ => return Err(format!("Unknown task schema version {}", from)),
+        }
+    }
+    Ok(task)
+}
+
+/// Version 2 added dependencies, attempt history, tags, idempotency keys and timeouts
+fn v1_to_v2(task: &mut Map<String, Value>) {
+    task.entry("dependencies").or_insert(json!([]));
+    task.entry("attempts").or_insert(json!([]));
+    task.entry("tag").or_insert(Value::Null);
+    task.entry("idempotency_key").or_insert(Value::Null);
+    task.entry("timeout_ms").or_insert(Value::Null);
+}
+
+/// Version 3 added the cluster tag. Older tasks stay untagged until a scheduler tags them.
+fn v2_to_v3(task: &mut Map<String, Value>) {
+    task.entry("cluster").or_insert(Value::Null);
+}
+
+/// Version 4 added the wallet name logged with the task. Older tasks log only its key.
+fn v3_to_v4(task: &mut Map<String, Value>) {
+    task.entry("wallet_name").or_insert(Value::Null);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::{TaskPriority, TaskStatus};
+    use engine::transaction::TransactionParams;
+    use engine::Cluster;
+    use solana_sdk::pubkey::Pubkey;
+    use std::time::Duration;
+
+    const TASK_V1: &str = include_str!("../fixtures/schema/task_v1.json");
+    const TASK_V2: &str = include_str!("../fixtures/schema/task_v2.json");
+    const TASK_V3: &str = include_str!("../fixtures/schema/task_v3.json");
+    const TASK_V4: &str = include_str!("../fixtures/schema/task_v4.json");
+
+    #[test]
+    fn test_v1_fixture_loads() {
+        let task = Task::from_versioned_json(TASK_V1).unwrap();
+
+        assert_eq!(task.id(), "4f2c7a9e-1b3d-4e8f-9a6b-2c5d7e9f1a3b");
+        assert_eq!(task.status(), TaskStatus::InProgress);
+        assert_eq!(task.priority(), TaskPriority::High);
+        assert_eq!(task.retry_count(), 1);
+        assert_eq!(task.last_attempt(), Some(1735689660000));
+        assert!(task.dependencies().is_empty());
+        assert!(task.attempts().is_empty());
+        assert_eq!(task.tag(), None);
+        assert_eq!(task.idempotency_key(), None);
+        assert_eq!(task.timeout(), None);
+        assert_eq!(task.cluster(), None);
+        assert!(matches!(
+            task.params().unwrap(),
+            TransactionParams::Transfer(_)
+        ));
+        assert_eq!(task.wallet(), Some(Pubkey::new_from_array([1; 32])));
+    }
+
+    #[test]
+    fn test_v2_fixture_loads() {
+        let task = Task::from_versioned_json(TASK_V2).unwrap();
+
+        assert_eq!(task.status(), TaskStatus::Failed);
+        assert_eq!(
+            task.dependencies(),
+            ["4f2c7a9e-1b3d-4e8f-9a6b-2c5d7e9f1a3b"]
+        );
+        assert_eq!(task.tag(), Some("kamino"));
+        assert_eq!(task.idempotency_key(), Some("campaign-7/fund-3"));
+        assert_eq!(task.timeout(), Some(Duration::from_secs(30)));
+        assert_eq!(task.errors(), vec!["Transaction simulation failed"]);
+        assert_eq!(task.params().unwrap().type_name(), "BatchTransfer");
+        assert_eq!(task.cluster(), None);
+    }
+
+    #[test]
+    fn test_v3_fixture_loads() {
+        let task = Task::from_versioned_json(TASK_V3).unwrap();
+
+        assert_eq!(task.status(), TaskStatus::Pending);
+        assert_eq!(task.tag(), Some("kamino"));
+        assert_eq!(task.cluster(), Some(Cluster::Devnet));
+        assert_eq!(task.wallet_name(), None);
+    }
+
+    #[test]
+    fn test_v4_fixture_loads() {
+        let task = Task::from_versioned_json(TASK_V4).unwrap();
+
+        assert_eq!(task.cluster(), Some(Cluster::Devnet));
+        assert_eq!(task.wallet_name(), Some("payroll"));
+    }
+
+    #[test]
+    fn test_round_trip_writes_current_version() {
+        let task = Task::from_versioned_json(TASK_V1).unwrap();
+
+        let json = task.to_versioned_json().unwrap();
+        let value: Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(value["schema_version"], TASK_SCHEMA_VERSION);
+        assert_eq!(value["params_version"], PARAMS_SCHEMA_VERSION);
+        assert_eq!(Task::from_versioned_json(&json).unwrap(), task);
+    }
+
+    #[test]
+    fn test_newer_schema_version_is_rejected() {
+        let mut value: Value = serde_json::from_str(TASK_V2).unwrap();
+        value["schema_version"] = json!(TASK_SCHEMA_VERSION + 1);
+
+        let error = Task::from_versioned_json(&value.to_string()).unwrap_err();
+
+        assert!(error.contains("newer than supported"));
+    }
+
+    #[test]
+    fn test_newer_params_version_is_rejected() {
+        let mut value: Value = serde_json::from_str(TASK_V2).unwrap();
+        value["params_version"] = json!(PARAMS_SCHEMA_VERSION + 1);
+
+        assert!(Task::from_versioned_json(&value.to_string()).is_err());
+    }
+
+    #[test]
+    fn test_malformed_json_is_rejected() {
+        assert!(Task::from_versioned_json("not json").is_err());
+        assert!(Task::from_versioned_json(r#"{"schema_version": 2}"#).is_err());
+    }
+}
@@ -0,0 +1,108 @@
+use crate::task::Task;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// Somewhere to keep unfinished tasks across restarts
+pub trait TaskStore: Send + Sync {
+    /// Replaces whatever was stored with `tasks`
+    fn save(&self, tasks: &[Task]) -> Result<(), String>;
+    /// Loads stored tasks, migrating any written by older versions
+    fn load(&self) -> Result<Vec<Task>, String>;
+}
+
+/// Stores tasks as JSON lines, one versioned envelope per task
+pub struct FileTaskStore {
+    path: PathBuf,
+}
+
+impl FileTaskStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl TaskStore for FileTaskStore {
+    fn save(&self, tasks: &[Task]) -> Result<(), String> {
+        let mut contents = String::new();
+        for task in tasks {
+            contents.push_str(&task.to_versioned_json()?);
+            contents.push('\n');
+        }
+        // Write beside the target and rename, so a crash never leaves a torn file
+        let staging = self.path.with_extension("tmp");
+        fs::write(&staging, contents).map_err(|e| format!("Failed to write tasks: {}", e))?;
+        fs::rename(&staging, &self.path).map_err(|e| format!("Failed to write tasks: {}", e))
+    }
+
+    fn load(&self) -> Result<Vec<Task>, String> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("Failed to read tasks: {}", e)),
+        };
+        contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(Task::from_versioned_json)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_store() -> FileTaskStore {
+        let path = std::env::temp_dir().join(format!("tasks-{}.jsonl", uuid::Uuid::new_v4()));
+        FileTaskStore::new(path)
+    }
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let store = temp_store();
+        let tasks = vec![
+            Task::new("{}".to_string()).with_tag("kamino"),
+            Task::new("{}".to_string()),
+        ];
+
+        store.save(&tasks).unwrap();
+
+        assert_eq!(store.load().unwrap(), tasks);
+        fs::remove_file(&store.path).unwrap();
+    }
+
+    #[test]
+    fn test_missing_file_loads_empty() {
+        assert!(temp_store().load().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_save_replaces_previous_contents() {
+        let store = temp_store();
+        store.save(&[Task::new("{}".to_string())]).unwrap();
+
+        store.save(&[]).unwrap();
+
+        assert!(store.load().unwrap().is_empty());
+        fs::remove_file(&store.path).unwrap();
+    }
+
+    #[test]
+    fn test_load_migrates_older_tasks() {
+        let store = temp_store();
+        let v1: serde_json::Value =
+            serde_json::from_str(include_str!("../fixtures/schema/task_v1.json")).unwrap();
+        fs::write(&store.path, format!("{}\n", v1)).unwrap();
+
+        let tasks = store.load().unwrap();
+
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].id(), "4f2c7a9e-1b3d-4e8f-9a6b-2c5d7e9f1a3b");
+        fs::remove_file(&store.path).unwrap();
+    }
+}
@@ -1,22 +1,65 @@
+use crate::executor::AttemptReport;
+use engine::transaction::TransactionParams;
+use engine::Cluster;
 use serde::{Deserialize, Serialize};
-use std::time::{SystemTime, UNIX_EPOCH};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{field, Span};
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
-enum TaskStatus {
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TaskStatus {
     Pending = 0,
     InProgress = 1,
     Completed = 2,
     Failed = 3,
+    Cancelled = 4,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
-enum TaskPriority {
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TaskPriority {
     Low = 0,
     Normal = 1,
     High = 2,
     Critical = 3,
 }
 
+/// One dispatch of a task, from the moment it left the queue until it reported back
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct TaskAttempt {
+    pub started_at: u64,
+    pub finished_at: Option<u64>,
+    pub blockhash: Option<String>,
+    pub signature: Option<String>,
+    pub simulation_logs: Vec<String>,
+    pub fee_lamports: Option<u64>,
+    pub error: Option<String>,
+}
+
+impl TaskAttempt {
+    fn start() -> Self {
+        Self {
+            started_at: now_millis(),
+            finished_at: None,
+            blockhash: None,
+            signature: None,
+            simulation_logs: Vec::new(),
+            fee_lamports: None,
+            error: None,
+        }
+    }
+
+    fn finish(&mut self, report: AttemptReport) {
+        self.finished_at = Some(now_millis());
+        // Keep what was recorded as sent unless the report says otherwise
+        self.blockhash = report.blockhash.or(self.blockhash.take());
+        self.signature = report.signature.or(self.signature.take());
+        self.simulation_logs = report.simulation_logs;
+        self.fee_lamports = report.fee_lamports;
+        self.error = report.error;
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct Task {
     id: String,
@@ -27,6 +70,13 @@ pub struct Task {
     priority: TaskPriority,
     last_attempt: Option<u64>,
     created_at: u64,
+    dependencies: Vec<String>,
+    attempts: Vec<TaskAttempt>,
+    tag: Option<String>,
+    idempotency_key: Option<String>,
+    timeout_ms: Option<u64>,
+    cluster: Option<Cluster>,
+    wallet_name: Option<String>,
 }
 
 impl Task {
@@ -39,12 +89,258 @@ impl Task {
             max_retries: 3,
             priority: TaskPriority::Normal,
             last_attempt: None,
-            created_at: SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_millis() as u64,
+            created_at: now_millis(),
+            dependencies: Vec::new(),
+            attempts: Vec::new(),
+            tag: None,
+            idempotency_key: None,
+            timeout_ms: None,
+            cluster: None,
+            wallet_name: None,
+        }
+    }
+
+    /// Declares the IDs of tasks that must complete before this one may run
+    pub fn with_dependencies(mut self, dependencies: Vec<String>) -> Self {
+        self.dependencies = dependencies;
+        self
+    }
+
+    /// Tags the task with the squad or campaign it belongs to
+    pub fn with_tag(mut self, tag: impl Into<String>) -> Self {
+        self.tag = Some(tag.into());
+        self
+    }
+
+    /// Caller-supplied key that makes scheduling the same intent twice a no-op
+    pub fn with_idempotency_key(mut self, key: impl Into<String>) -> Self {
+        self.idempotency_key = Some(key.into());
+        self
+    }
+
+    pub fn with_priority(mut self, priority: TaskPriority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Pins the task to a cluster; a scheduler for any other cluster refuses it
+    pub fn with_cluster(mut self, cluster: Cluster) -> Self {
+        self.cluster = Some(cluster);
+        self
+    }
+
+    /// Caps how long a single attempt may run, overriding the scheduler's default
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout_ms = Some(timeout.as_millis() as u64);
+        self
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn transaction_params(&self) -> &str {
+        &self.transaction_params
+    }
+
+    pub fn status(&self) -> TaskStatus {
+        self.status
+    }
+
+    pub fn max_retries(&self) -> u8 {
+        self.max_retries
+    }
+
+    /// When the task was last dispatched, in ms since the Unix epoch
+    pub fn last_attempt(&self) -> Option<u64> {
+        self.last_attempt
+    }
+
+    /// When the task was created, in ms since the Unix epoch
+    pub fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    pub fn dependencies(&self) -> &[String] {
+        &self.dependencies
+    }
+
+    pub fn priority(&self) -> TaskPriority {
+        self.priority
+    }
+
+    pub fn tag(&self) -> Option<&str> {
+        self.tag.as_deref()
+    }
+
+    pub fn idempotency_key(&self) -> Option<&str> {
+        self.idempotency_key.as_deref()
+    }
+
+    pub fn cluster(&self) -> Option<Cluster> {
+        self.cluster
+    }
+
+    pub fn retry_count(&self) -> u8 {
+        self.retry_count
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout_ms.map(Duration::from_millis)
+    }
+
+    /// Every dispatch of this task, oldest first
+    pub fn attempts(&self) -> &[TaskAttempt] {
+        &self.attempts
+    }
+
+    /// The errors of every failed attempt, oldest first
+    pub fn errors(&self) -> Vec<&str> {
+        self.attempts
+            .iter()
+            .filter_map(|attempt| attempt.error.as_deref())
+            .collect()
+    }
+
+    pub fn params(&self) -> Result<TransactionParams, serde_json::Error> {
+        serde_json::from_str(&self.transaction_params)
+    }
+
+    /// The signing/fee-paying wallet, if the params can be decoded
+    pub fn wallet(&self) -> Option<Pubkey> {
+        self.params().ok().map(|params| params.fee_payer())
+    }
+
+    /// Name of the wallet in `wallet()`, as known when the task was scheduled
+    pub fn wallet_name(&self) -> Option<&str> {
+        self.wallet_name.as_deref()
+    }
+
+    /// A span keyed by the task's ID, wallet and campaign tag, for everything done on its behalf
+    pub fn span(&self) -> Span {
+        let span = tracing::info_span!(
+            "task",
+            task_id = %self.id,
+            wallet = field::Empty,
+            pubkey = field::Empty,
+            campaign = field::Empty,
+        );
+        if let Some(name) = &self.wallet_name {
+            span.record("wallet", name.as_str());
+        }
+        if let Some(pubkey) = self.wallet() {
+            span.record("pubkey", field::display(pubkey));
+        }
+        if let Some(tag) = &self.tag {
+            span.record("campaign", tag.as_str());
+        }
+        span
+    }
+
+    /// The programs the task's transaction invokes, if the params can be decoded
+    pub fn program_ids(&self) -> Vec<Pubkey> {
+        self.params()
+            .map(|params| params.program_ids())
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn set_status(&mut self, status: TaskStatus) {
+        self.status = status;
+    }
+
+    pub(crate) fn mark_attempt(&mut self) {
+        let attempt = TaskAttempt::start();
+        self.status = TaskStatus::InProgress;
+        self.last_attempt = Some(attempt.started_at);
+        self.attempts.push(attempt);
+    }
+
+    /// Closes the attempt opened by `mark_attempt` with what the executor reported
+    pub(crate) fn finish_attempt(&mut self, report: AttemptReport) {
+        match self.attempts.last_mut() {
+            Some(attempt) if attempt.finished_at.is_none() => attempt.finish(report),
+            _ => {
+                let mut attempt = TaskAttempt::start();
+                attempt.finish(report);
+                self.attempts.push(attempt);
+            }
+        }
+    }
+
+    /// The attempt still waiting on its executor, if any
+    pub(crate) fn open_attempt(&self) -> Option<&TaskAttempt> {
+        self.attempts
+            .last()
+            .filter(|attempt| attempt.finished_at.is_none())
+    }
+
+    /// Opens the last attempt again so a late outcome can replace how it was closed
+    pub(crate) fn reopen_attempt(&mut self) {
+        if let Some(attempt) = self.attempts.last_mut() {
+            attempt.finished_at = None;
+            attempt.error = None;
+        }
+    }
+
+    /// Records what was sent by the still-open attempt, so it can be looked up on chain
+    /// if the attempt never reports back
+    pub(crate) fn record_sent(&mut self, signature: String, blockhash: Option<String>) {
+        if let Some(attempt) = self.attempts.last_mut() {
+            if attempt.finished_at.is_none() {
+                attempt.signature = Some(signature);
+                attempt.blockhash = blockhash;
+            }
         }
     }
+
+    pub(crate) fn set_wallet_name(&mut self, name: Option<String>) {
+        self.wallet_name = name;
+    }
+
+    pub(crate) fn set_max_retries(&mut self, max_retries: u8) {
+        self.max_retries = max_retries;
+    }
+
+    /// Bumps the retry counter, returning false once `max_retries` is exhausted
+    pub(crate) fn record_failure(&mut self) -> bool {
+        if self.retry_count >= self.max_retries {
+            self.status = TaskStatus::Failed;
+            return false;
+        }
+        self.retry_count += 1;
+        self.status = TaskStatus::Pending;
+        true
+    }
+
+    /// Points dependencies at the tasks they were deduplicated into
+    pub(crate) fn remap_dependencies(&mut self, aliases: &HashMap<String, String>) {
+        for dep in &mut self.dependencies {
+            if let Some(existing) = aliases.get(dep) {
+                *dep = existing.clone();
+            }
+        }
+    }
+
+    pub(crate) fn retain_dependencies(&mut self, keep: impl Fn(&str) -> bool) {
+        self.dependencies.retain(|dep| keep(dep));
+    }
+
+    pub(crate) fn set_transaction_params(&mut self, transaction_params: String) {
+        self.transaction_params = transaction_params;
+    }
+
+    /// Puts a failed task back to a fresh Pending state, keeping its error history
+    pub(crate) fn reset_for_replay(&mut self) {
+        self.retry_count = 0;
+        self.status = TaskStatus::Pending;
+    }
+}
+
+pub(crate) fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
 }
 
 #[cfg(test)]
@@ -211,4 +507,77 @@ mod tests {
 
         assert_eq!(cloned, restored);
     }
+
+    #[test]
+    fn test_task_with_dependencies() {
+        let params = TransactionParams::Transfer(TransferParams {
+            from: Pubkey::new_unique(),
+            to: Pubkey::new_unique(),
+            lamports: 1_000_000,
+        });
+        let parent = Task::new(serde_json::to_string(&params).unwrap());
+        let child = Task::new(serde_json::to_string(&params).unwrap())
+            .with_dependencies(vec![parent.id().to_string()]);
+
+        assert!(parent.dependencies().is_empty());
+        assert_eq!(child.dependencies(), &[parent.id().to_string()]);
+
+        let json = serde_json::to_string(&child).unwrap();
+        let restored: Task = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.dependencies(), child.dependencies());
+    }
+
+    #[test]
+    fn test_task_wallet_is_fee_payer() {
+        let from = Pubkey::new_unique();
+        let params = TransactionParams::Transfer(TransferParams {
+            from,
+            to: Pubkey::new_unique(),
+            lamports: 1_000_000,
+        });
+        let task = Task::new(serde_json::to_string(&params).unwrap());
+
+        assert_eq!(task.params().unwrap(), params);
+        assert_eq!(task.wallet(), Some(from));
+        assert_eq!(Task::new("not params".to_string()).wallet(), None);
+    }
+
+    #[test]
+    fn test_attempt_history_survives_serialization() {
+        let mut task = Task::new("{}".to_string());
+        task.mark_attempt();
+        task.finish_attempt(
+            AttemptReport::failure("Blockhash not found")
+                .with_blockhash("EkSnNWid2cvwEVnVx9aBqawnmiCNiDgp3gUdkDPTKN1N")
+                .with_simulation_logs(vec![
+                    "Program 11111111111111111111111111111111 failed".into()
+                ]),
+        );
+        task.mark_attempt();
+        task.finish_attempt(AttemptReport::success("5igna7ure").with_fee(5_000));
+
+        let json = serde_json::to_string(&task).unwrap();
+        let restored: Task = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(restored.attempts(), task.attempts());
+        let attempts = restored.attempts();
+        assert_eq!(attempts.len(), 2);
+        assert_eq!(attempts[0].error.as_deref(), Some("Blockhash not found"));
+        assert_eq!(attempts[0].simulation_logs.len(), 1);
+        assert!(attempts[0].signature.is_none());
+        assert_eq!(attempts[1].signature.as_deref(), Some("5igna7ure"));
+        assert_eq!(attempts[1].fee_lamports, Some(5_000));
+        assert!(attempts.iter().all(|a| a.finished_at >= Some(a.started_at)));
+        assert_eq!(restored.errors(), vec!["Blockhash not found"]);
+    }
+
+    #[test]
+    fn test_finish_without_open_attempt_still_records() {
+        let mut task = Task::new("{}".to_string());
+
+        task.finish_attempt(AttemptReport::failure("Cancelled before start"));
+
+        assert_eq!(task.attempts().len(), 1);
+        assert!(task.attempts()[0].finished_at.is_some());
+    }
 }
@@ -0,0 +1,1056 @@
+use crate::{
+    executor::{AttemptHandle, TaskExecutor},
+    metrics,
+    queue::{OverflowPolicy, QUEUE_FULL},
+    rate_limit::RateLimiter,
+    scheduler::TaskScheduler,
+    store::TaskStore,
+    task::Task,
+};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{watch, Notify, Semaphore};
+use tokio::task::JoinHandle;
+use tracing::{warn, Instrument};
+
+/// How often a graceful shutdown checks whether in-flight attempts have drained
+const DRAIN_POLL: Duration = Duration::from_millis(10);
+
+#[derive(Debug, Clone)]
+pub struct WorkerPoolConfig {
+    /// Number of workers pulling from the queue
+    pub concurrency: usize,
+    /// Shared cap on executions talking to the RPC endpoint at once.
+    /// Hand the same semaphore to every pool that hits the same endpoint.
+    pub rpc_limit: Arc<Semaphore>,
+    /// How long an idle worker sleeps before checking the queue again
+    pub idle_poll: Duration,
+    /// Request rate limits per endpoint and program. Share one limiter between
+    /// pools so they draw from the same buckets.
+    pub rate_limiter: Arc<RateLimiter>,
+    /// The RPC endpoint this pool's executor sends to, for endpoint rate limits
+    pub endpoint: Option<String>,
+    /// How long a wallet stays locked after one of its attempts times out, unless
+    /// the reaper resolves the attempt sooner. It should outlast blockhash expiry,
+    /// so the abandoned transaction can no longer land once the wallet is freed.
+    pub abandoned_hold: Duration,
+}
+
+impl Default for WorkerPoolConfig {
+    fn default() -> Self {
+        Self {
+            concurrency: 8,
+            rpc_limit: Arc::new(Semaphore::new(16)),
+            idle_poll: Duration::from_millis(100),
+            rate_limiter: Arc::new(RateLimiter::new()),
+            endpoint: None,
+            abandoned_hold: Duration::from_secs(90),
+        }
+    }
+}
+
+pub struct WorkerPool<E: TaskExecutor> {
+    scheduler: Arc<Mutex<TaskScheduler>>,
+    executor: Arc<E>,
+    config: WorkerPoolConfig,
+    wake: Arc<Notify>,
+    wallet_locks: Arc<Mutex<WalletLocks>>,
+}
+
+pub struct WorkerPoolHandle {
+    scheduler: Arc<Mutex<TaskScheduler>>,
+    stop: watch::Sender<bool>,
+    workers: Vec<JoinHandle<()>>,
+    wake: Arc<Notify>,
+}
+
+/// What a graceful shutdown left behind
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShutdownReport {
+    /// Attempts still running when the deadline passed
+    pub interrupted: usize,
+    /// Unfinished tasks written to the store as Pending
+    pub persisted: usize,
+}
+
+impl<E: TaskExecutor> WorkerPool<E> {
+    pub fn new(
+        scheduler: Arc<Mutex<TaskScheduler>>,
+        executor: E,
+        config: WorkerPoolConfig,
+    ) -> Self {
+        Self {
+            scheduler,
+            executor: Arc::new(executor),
+            config,
+            wake: Arc::new(Notify::new()),
+            wallet_locks: Arc::new(Mutex::new(WalletLocks::default())),
+        }
+    }
+
+    /// Schedules a task and wakes an idle worker to pick it up
+    pub fn submit(&self, task: Task) -> Result<Task, &'static str> {
+        let scheduled = self
+            .scheduler
+            .lock()
+            .expect("scheduler lock poisoned")
+            .schedule_task(task)?;
+        self.wake.notify_one();
+        Ok(scheduled)
+    }
+
+    /// Like `submit`, but waits for space when the scheduler's queue is
+    /// bounded with the `Block` overflow policy
+    pub async fn submit_async(&self, task: Task) -> Result<Task, &'static str> {
+        let queue = self
+            .scheduler
+            .lock()
+            .expect("scheduler lock poisoned")
+            .queue();
+        loop {
+            let space = queue.space_notified();
+            tokio::pin!(space);
+            space.as_mut().enable();
+
+            match self.submit(task.clone()) {
+                Err(QUEUE_FULL) if queue.policy() == OverflowPolicy::Block => space.await,
+                result => return result,
+            }
+        }
+    }
+
+    pub fn spawn(self) -> WorkerPoolHandle {
+        let (stop, stopped) = watch::channel(false);
+        let workers = (0..self.config.concurrency.max(1))
+            .map(|_| {
+                let worker = Worker {
+                    scheduler: Arc::clone(&self.scheduler),
+                    executor: Arc::clone(&self.executor),
+                    rpc_limit: Arc::clone(&self.config.rpc_limit),
+                    idle_poll: self.config.idle_poll,
+                    rate_limiter: Arc::clone(&self.config.rate_limiter),
+                    endpoint: self.config.endpoint.clone(),
+                    abandoned_hold: self.config.abandoned_hold,
+                    wake: Arc::clone(&self.wake),
+                    wallet_locks: Arc::clone(&self.wallet_locks),
+                    stopped: stopped.clone(),
+                };
+                tokio::spawn(worker.run())
+            })
+            .collect();
+
+        WorkerPoolHandle {
+            scheduler: self.scheduler,
+            stop,
+            workers,
+            wake: self.wake,
+        }
+    }
+}
+
+impl WorkerPoolHandle {
+    /// Wakes an idle worker, e.g. after scheduling tasks on the scheduler directly
+    pub fn notify(&self) {
+        self.wake.notify_one();
+    }
+
+    /// Stops dispatching new tasks and waits for in-flight executions to report back
+    pub async fn shutdown(self) {
+        let _ = self.stop.send(true);
+        for worker in self.workers {
+            let _ = worker.await;
+        }
+    }
+
+    /// Stops accepting and dispatching tasks, gives in-flight attempts until
+    /// `deadline` to report back, then abandons any still running and writes every
+    /// unfinished task to `store` as Pending. Finished tasks still retained are
+    /// written as they are, so dead letters can be replayed after a restart.
+    pub async fn shutdown_gracefully(
+        self,
+        deadline: Duration,
+        store: &impl TaskStore,
+    ) -> Result<ShutdownReport, String> {
+        self.scheduler
+            .lock()
+            .expect("scheduler lock poisoned")
+            .begin_shutdown();
+
+        let drained = tokio::time::timeout(deadline, async {
+            while self
+                .scheduler
+                .lock()
+                .expect("scheduler lock poisoned")
+                .in_flight_count()
+                > 0
+            {
+                tokio::time::sleep(DRAIN_POLL).await;
+            }
+        })
+        .await
+        .is_ok();
+
+        let _ = self.stop.send(true);
+        for worker in self.workers {
+            if !drained {
+                worker.abort();
+            }
+            let _ = worker.await;
+        }
+
+        let (interrupted, unfinished, mut tasks) = {
+            let mut scheduler = self.scheduler.lock().expect("scheduler lock poisoned");
+            let interrupted = scheduler.in_flight_count();
+            let unfinished = scheduler.take_unfinished();
+            (
+                interrupted,
+                unfinished.len(),
+                [scheduler.snapshot(), unfinished].concat(),
+            )
+        };
+        tasks.sort_by(|a, b| (a.created_at(), a.id()).cmp(&(b.created_at(), b.id())));
+        store.save(&tasks)?;
+        Ok(ShutdownReport {
+            interrupted,
+            persisted: unfinished,
+        })
+    }
+}
+
+/// Keeps at most one in-flight task per signing wallet. Tasks for a busy wallet
+/// are parked in dispatch order and handed to whichever worker releases the wallet.
+#[derive(Default)]
+struct WalletLocks {
+    busy: HashMap<Pubkey, VecDeque<Task>>,
+}
+
+impl WalletLocks {
+    /// Returns the task if it may run now, or parks it behind the wallet's in-flight task
+    fn acquire(&mut self, task: Task) -> Option<Task> {
+        let Some(wallet) = task.wallet() else {
+            return Some(task);
+        };
+        match self.busy.get_mut(&wallet) {
+            Some(parked) => {
+                parked.push_back(task);
+                None
+            }
+            None => {
+                self.busy.insert(wallet, VecDeque::new());
+                Some(task)
+            }
+        }
+    }
+
+    /// Frees the wallet, or keeps it locked and returns the next parked task for it
+    fn release(&mut self, wallet: &Pubkey) -> Option<Task> {
+        let next = self.busy.get_mut(wallet)?.pop_front();
+        if next.is_none() {
+            self.busy.remove(wallet);
+        }
+        next
+    }
+}
+
+struct Worker<E: TaskExecutor> {
+    scheduler: Arc<Mutex<TaskScheduler>>,
+    executor: Arc<E>,
+    rpc_limit: Arc<Semaphore>,
+    idle_poll: Duration,
+    rate_limiter: Arc<RateLimiter>,
+    endpoint: Option<String>,
+    abandoned_hold: Duration,
+    wake: Arc<Notify>,
+    wallet_locks: Arc<Mutex<WalletLocks>>,
+    stopped: watch::Receiver<bool>,
+}
+
+impl<E: TaskExecutor> Worker<E> {
+    async fn run(mut self) {
+        while !*self.stopped.borrow() {
+            let next = self
+                .scheduler
+                .lock()
+                .expect("scheduler lock poisoned")
+                .execute_next();
+
+            match next {
+                Some(task) => {
+                    let runnable = self
+                        .wallet_locks
+                        .lock()
+                        .expect("wallet locks poisoned")
+                        .acquire(task);
+                    if let Some(task) = runnable {
+                        self.run_wallet(task).await;
+                    }
+                }
+                None => {
+                    tokio::select! {
+                        _ = self.wake.notified() => {}
+                        _ = tokio::time::sleep(self.idle_poll) => {}
+                        _ = self.stopped.changed() => {}
+                    }
+                }
+            }
+        }
+    }
+
+    /// Runs a task, then any tasks parked behind it for the same wallet. Parked tasks
+    /// were already dispatched, so they are drained even if the pool is stopping.
+    async fn run_wallet(&self, task: Task) {
+        let mut current = Some(task);
+        while let Some(task) = current.take() {
+            let wallet = task.wallet();
+            let id = task.id().to_string();
+            let abandoned = self.execute(task).await;
+            // A timed-out transaction may still land, so its wallet stays locked until
+            // the attempt is resolved. Stopping leaves the parked tasks in flight.
+            if let (Some(attempt), Some(_)) = (abandoned, wallet) {
+                if !self.hold_until_resolved(&id, attempt).await {
+                    return;
+                }
+            }
+            if let Some(wallet) = wallet {
+                current = self
+                    .wallet_locks
+                    .lock()
+                    .expect("wallet locks poisoned")
+                    .release(&wallet);
+            }
+        }
+    }
+
+    /// Waits until the reaper resolves an abandoned attempt or `abandoned_hold`
+    /// passes. Returns false if the pool stopped first.
+    async fn hold_until_resolved(&self, id: &str, attempt: usize) -> bool {
+        let resolved = async {
+            while self
+                .scheduler
+                .lock()
+                .expect("scheduler lock poisoned")
+                .is_attempt_open(id, attempt)
+            {
+                tokio::time::sleep(self.idle_poll).await;
+            }
+        };
+        let mut stopped = self.stopped.clone();
+        tokio::select! {
+            _ = tokio::time::timeout(self.abandoned_hold, resolved) => true,
+            _ = stopped.wait_for(|stopped| *stopped) => false,
+        }
+    }
+
+    /// Runs one attempt, returning its number if it timed out and was abandoned
+    async fn execute(&self, task: Task) -> Option<usize> {
+        let id = task.id().to_string();
+        let (attempt, timeout) = {
+            let mut scheduler = self.scheduler.lock().expect("scheduler lock poisoned");
+            // Parked tasks may have been cancelled while waiting on their wallet
+            if scheduler.release_cancelled(&id) {
+                return None;
+            }
+            let tracked = scheduler.task(&id)?;
+            (tracked.attempts().len(), scheduler.timeout_for(tracked))
+        };
+        let handle = AttemptHandle::new(Arc::clone(&self.scheduler), id.clone(), attempt);
+        let endpoint = self.endpoint.as_deref();
+        let programs = task.program_ids();
+        let span = task.span();
+
+        let report = {
+            // Wait out any backoff before taking an RPC slot, so one throttled
+            // endpoint cannot hold every slot while it sleeps
+            self.rate_limiter.acquire(endpoint, &programs).await;
+            let _permit = self
+                .rpc_limit
+                .acquire()
+                .await
+                .expect("rpc limit semaphore closed");
+            let started = Instant::now();
+            let execution = self.executor.execute(task, handle).instrument(span.clone());
+            let report = tokio::time::timeout(timeout, execution).await;
+            metrics::record_execution(endpoint, report.as_ref().ok(), started.elapsed());
+            report
+        };
+        // A hung attempt is abandoned but left open; the reaper decides whether it landed
+        let Ok(report) = report else {
+            span.in_scope(|| warn!(attempt, ?timeout, "Attempt timed out"));
+            return Some(attempt);
+        };
+        if report.throttled {
+            span.in_scope(|| warn!(attempt, endpoint, "Throttled by RPC endpoint"));
+            self.rate_limiter.throttled(endpoint, &programs);
+        } else {
+            self.rate_limiter.succeeded(endpoint, &programs);
+        }
+
+        let reported = self
+            .scheduler
+            .lock()
+            .expect("scheduler lock poisoned")
+            .report_attempt_number(&id, attempt, report);
+
+        // Completing a parent may have released dependents, and a failure may have requeued
+        if reported.is_ok() {
+            self.wake.notify_one();
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::AttemptReport;
+    use crate::queue::TaskQueue;
+    use crate::rate_limit::{RateLimit, DEFAULT_INITIAL_BACKOFF};
+    use crate::store::FileTaskStore;
+    use crate::task::TaskStatus;
+    use engine::transaction::{TransactionParams, TransferParams};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct CountingExecutor {
+        running: AtomicUsize,
+        peak: AtomicUsize,
+        done: AtomicUsize,
+        fail_first: AtomicUsize,
+        per_wallet: Mutex<HashMap<Pubkey, (usize, usize)>>,
+    }
+
+    impl CountingExecutor {
+        fn new(fail_first: usize) -> Self {
+            Self {
+                running: AtomicUsize::new(0),
+                peak: AtomicUsize::new(0),
+                done: AtomicUsize::new(0),
+                fail_first: AtomicUsize::new(fail_first),
+                per_wallet: Mutex::new(HashMap::new()),
+            }
+        }
+    }
+
+    impl TaskExecutor for Arc<CountingExecutor> {
+        async fn execute(&self, task: Task, _attempt: AttemptHandle) -> AttemptReport {
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(running, Ordering::SeqCst);
+            if let Some(wallet) = task.wallet() {
+                let mut per_wallet = self.per_wallet.lock().unwrap();
+                let (running, peak) = per_wallet.entry(wallet).or_default();
+                *running += 1;
+                *peak = (*peak).max(*running);
+            }
+
+            tokio::time::sleep(Duration::from_millis(10)).await;
+
+            if let Some(wallet) = task.wallet() {
+                self.per_wallet.lock().unwrap().get_mut(&wallet).unwrap().0 -= 1;
+            }
+            self.running.fetch_sub(1, Ordering::SeqCst);
+
+            let should_fail = self
+                .fail_first
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok();
+            if should_fail {
+                return AttemptReport::failure("simulated failure");
+            }
+            self.done.fetch_add(1, Ordering::SeqCst);
+            AttemptReport::success("signature")
+        }
+    }
+
+    fn config(concurrency: usize, rpc_limit: usize) -> WorkerPoolConfig {
+        WorkerPoolConfig {
+            concurrency,
+            rpc_limit: Arc::new(Semaphore::new(rpc_limit)),
+            idle_poll: Duration::from_millis(5),
+            ..WorkerPoolConfig::default()
+        }
+    }
+
+    async fn wait_for(executor: &CountingExecutor, count: usize) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while executor.done.load(Ordering::SeqCst) < count {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("tasks did not complete in time");
+    }
+
+    #[tokio::test]
+    async fn test_pool_executes_all_tasks() {
+        let scheduler = Arc::new(Mutex::new(TaskScheduler::new()));
+        let executor = Arc::new(CountingExecutor::new(0));
+        let pool = WorkerPool::new(Arc::clone(&scheduler), Arc::clone(&executor), config(4, 4));
+
+        let tasks: Vec<Task> = (0..20).map(|_| Task::new("{}".to_string())).collect();
+        for task in &tasks {
+            pool.submit(task.clone()).unwrap();
+        }
+        let handle = pool.spawn();
+
+        wait_for(&executor, 20).await;
+        handle.shutdown().await;
+
+        let scheduler = scheduler.lock().unwrap();
+        assert!(scheduler.is_empty());
+        for task in &tasks {
+            assert_eq!(
+                scheduler.task(task.id()).unwrap().status(),
+                TaskStatus::Completed
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pool_respects_concurrency() {
+        let scheduler = Arc::new(Mutex::new(TaskScheduler::new()));
+        let executor = Arc::new(CountingExecutor::new(0));
+        let pool = WorkerPool::new(Arc::clone(&scheduler), Arc::clone(&executor), config(3, 16));
+
+        for _ in 0..12 {
+            pool.submit(Task::new("{}".to_string())).unwrap();
+        }
+        let handle = pool.spawn();
+
+        wait_for(&executor, 12).await;
+        handle.shutdown().await;
+
+        assert!(executor.peak.load(Ordering::SeqCst) <= 3);
+    }
+
+    #[tokio::test]
+    async fn test_pool_respects_rpc_limit() {
+        let scheduler = Arc::new(Mutex::new(TaskScheduler::new()));
+        let executor = Arc::new(CountingExecutor::new(0));
+        let pool = WorkerPool::new(Arc::clone(&scheduler), Arc::clone(&executor), config(8, 2));
+
+        for _ in 0..12 {
+            pool.submit(Task::new("{}".to_string())).unwrap();
+        }
+        let handle = pool.spawn();
+
+        wait_for(&executor, 12).await;
+        handle.shutdown().await;
+
+        assert!(executor.peak.load(Ordering::SeqCst) <= 2);
+    }
+
+    #[tokio::test]
+    async fn test_pool_retries_failed_tasks() {
+        let scheduler = Arc::new(Mutex::new(TaskScheduler::new()));
+        let executor = Arc::new(CountingExecutor::new(2));
+        let pool = WorkerPool::new(Arc::clone(&scheduler), Arc::clone(&executor), config(1, 1));
+
+        let task = Task::new("{}".to_string());
+        pool.submit(task.clone()).unwrap();
+        let handle = pool.spawn();
+
+        wait_for(&executor, 1).await;
+        handle.shutdown().await;
+
+        let scheduler = scheduler.lock().unwrap();
+        assert_eq!(
+            scheduler.task(task.id()).unwrap().status(),
+            TaskStatus::Completed
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pool_picks_up_released_dependents() {
+        let scheduler = Arc::new(Mutex::new(TaskScheduler::new()));
+        let executor = Arc::new(CountingExecutor::new(0));
+        let pool = WorkerPool::new(Arc::clone(&scheduler), Arc::clone(&executor), config(2, 2));
+
+        let fund = Task::new("{}".to_string());
+        let deposit = Task::new("{}".to_string()).with_dependencies(vec![fund.id().to_string()]);
+        scheduler
+            .lock()
+            .unwrap()
+            .schedule_batch(vec![fund, deposit])
+            .unwrap();
+        let handle = pool.spawn();
+        handle.notify();
+
+        wait_for(&executor, 2).await;
+        handle.shutdown().await;
+
+        assert!(scheduler.lock().unwrap().is_empty());
+    }
+
+    fn transfer_from(from: Pubkey) -> Task {
+        let params = TransactionParams::Transfer(TransferParams {
+            from,
+            to: Pubkey::new_unique(),
+            lamports: 1_000_000,
+        });
+        Task::new(serde_json::to_string(&params).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_pool_serializes_tasks_per_wallet() {
+        let scheduler = Arc::new(Mutex::new(TaskScheduler::new()));
+        let executor = Arc::new(CountingExecutor::new(0));
+        let pool = WorkerPool::new(Arc::clone(&scheduler), Arc::clone(&executor), config(4, 4));
+
+        let wallets = [Pubkey::new_unique(), Pubkey::new_unique()];
+        for _ in 0..5 {
+            for wallet in wallets {
+                pool.submit(transfer_from(wallet)).unwrap();
+            }
+        }
+        let handle = pool.spawn();
+
+        wait_for(&executor, 10).await;
+        handle.shutdown().await;
+
+        let per_wallet = executor.per_wallet.lock().unwrap();
+        for wallet in wallets {
+            assert_eq!(per_wallet[&wallet].1, 1, "wallet ran tasks concurrently");
+        }
+        // Different wallets still overlap
+        assert!(executor.peak.load(Ordering::SeqCst) >= 2);
+        assert!(scheduler.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_wallet_locks_park_and_hand_off() {
+        let mut locks = WalletLocks::default();
+        let wallet = Pubkey::new_unique();
+        let first = transfer_from(wallet);
+        let second = transfer_from(wallet);
+        let other = transfer_from(Pubkey::new_unique());
+
+        assert!(locks.acquire(first.clone()).is_some());
+        assert!(locks.acquire(second.clone()).is_none());
+        assert!(locks.acquire(other).is_some());
+
+        assert_eq!(locks.release(&wallet).unwrap().id(), second.id());
+        assert!(locks.release(&wallet).is_none());
+        assert!(locks.acquire(first).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_pool_skips_parked_task_cancelled_before_start() {
+        let scheduler = Arc::new(Mutex::new(TaskScheduler::new()));
+        let executor = Arc::new(CountingExecutor::new(0));
+        let wallet = Pubkey::new_unique();
+        let first = transfer_from(wallet);
+        let parked = transfer_from(wallet);
+
+        // Dispatch both by hand so the second is parked behind the first
+        let (first, parked) = {
+            let mut scheduler = scheduler.lock().unwrap();
+            scheduler.schedule_task(first).unwrap();
+            scheduler.schedule_task(parked).unwrap();
+            (
+                scheduler.execute_next().unwrap(),
+                scheduler.execute_next().unwrap(),
+            )
+        };
+        let pool = WorkerPool::new(Arc::clone(&scheduler), Arc::clone(&executor), config(1, 1));
+        let first = {
+            let mut locks = pool.wallet_locks.lock().unwrap();
+            let first = locks.acquire(first).unwrap();
+            assert!(locks.acquire(parked.clone()).is_none());
+            first
+        };
+
+        scheduler.lock().unwrap().cancel_task(parked.id()).unwrap();
+        let worker = Worker {
+            scheduler: Arc::clone(&scheduler),
+            executor: Arc::clone(&pool.executor),
+            rpc_limit: Arc::clone(&pool.config.rpc_limit),
+            idle_poll: pool.config.idle_poll,
+            rate_limiter: Arc::clone(&pool.config.rate_limiter),
+            endpoint: pool.config.endpoint.clone(),
+            abandoned_hold: pool.config.abandoned_hold,
+            wake: Arc::clone(&pool.wake),
+            wallet_locks: Arc::clone(&pool.wallet_locks),
+            stopped: watch::channel(false).1,
+        };
+        worker.run_wallet(first).await;
+
+        assert_eq!(executor.done.load(Ordering::SeqCst), 1);
+        let mut scheduler = scheduler.lock().unwrap();
+        assert_eq!(
+            scheduler.task(parked.id()).unwrap().status(),
+            TaskStatus::Cancelled
+        );
+        assert!(scheduler
+            .complete_task(parked.id(), "signature".to_string())
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_submit_async_waits_for_queue_space() {
+        let scheduler = Arc::new(Mutex::new(
+            TaskScheduler::new().with_queue(TaskQueue::with_capacity(1, OverflowPolicy::Block)),
+        ));
+        let executor = Arc::new(CountingExecutor::new(0));
+        let pool = Arc::new(WorkerPool::new(
+            Arc::clone(&scheduler),
+            Arc::clone(&executor),
+            config(1, 1),
+        ));
+
+        pool.submit(Task::new("{}".to_string())).unwrap();
+        assert_eq!(pool.submit(Task::new("{}".to_string())), Err(QUEUE_FULL));
+
+        let producer = {
+            let pool = Arc::clone(&pool);
+            tokio::spawn(async move { pool.submit_async(Task::new("{}".to_string())).await })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!producer.is_finished());
+
+        // Draining the queue frees the slot
+        scheduler.lock().unwrap().execute_next().unwrap();
+        tokio::time::timeout(Duration::from_secs(1), producer)
+            .await
+            .expect("producer stayed blocked")
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(scheduler.lock().unwrap().queue_depth().queued, 1);
+    }
+
+    const ENDPOINT: &str = "https://rpc.example.com";
+
+    fn limited_config(limiter: RateLimiter) -> WorkerPoolConfig {
+        WorkerPoolConfig {
+            rate_limiter: Arc::new(limiter),
+            endpoint: Some(ENDPOINT.to_string()),
+            ..config(4, 4)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pool_waits_on_endpoint_rate_limit() {
+        let scheduler = Arc::new(Mutex::new(TaskScheduler::new()));
+        let executor = Arc::new(CountingExecutor::new(0));
+        let limiter = RateLimiter::new()
+            .with_endpoint_limit(ENDPOINT, RateLimit::per_second(50).with_burst(1));
+        let pool = WorkerPool::new(
+            Arc::clone(&scheduler),
+            Arc::clone(&executor),
+            limited_config(limiter),
+        );
+
+        for _ in 0..6 {
+            pool.submit(Task::new("{}".to_string())).unwrap();
+        }
+        let start = tokio::time::Instant::now();
+        let handle = pool.spawn();
+
+        wait_for(&executor, 6).await;
+        handle.shutdown().await;
+
+        // One token up front, then one every 20ms
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+
+    /// Answers 429 on its first call, then succeeds
+    struct ThrottledOnceExecutor {
+        calls: AtomicUsize,
+    }
+
+    impl TaskExecutor for Arc<ThrottledOnceExecutor> {
+        async fn execute(&self, _task: Task, _attempt: AttemptHandle) -> AttemptReport {
+            if self.calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                return AttemptReport::throttled("429 Too Many Requests");
+            }
+            AttemptReport::success("signature")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_throttled_attempt_backs_off_endpoint() {
+        let scheduler = Arc::new(Mutex::new(TaskScheduler::new()));
+        let executor = Arc::new(ThrottledOnceExecutor {
+            calls: AtomicUsize::new(0),
+        });
+        let config = limited_config(
+            RateLimiter::new().with_endpoint_limit(ENDPOINT, RateLimit::per_second(1000)),
+        );
+        let limiter = Arc::clone(&config.rate_limiter);
+        let pool = WorkerPool::new(Arc::clone(&scheduler), Arc::clone(&executor), config);
+
+        let task = pool.submit(Task::new("{}".to_string())).unwrap();
+        let start = tokio::time::Instant::now();
+        let handle = pool.spawn();
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while executor.calls.load(Ordering::SeqCst) < 2 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("throttled task was not retried");
+        handle.shutdown().await;
+
+        assert!(start.elapsed() >= DEFAULT_INITIAL_BACKOFF);
+        let scheduler = scheduler.lock().unwrap();
+        let task = scheduler.task(task.id()).unwrap();
+        assert_eq!(task.status(), TaskStatus::Completed);
+        assert_eq!(task.errors(), vec!["429 Too Many Requests"]);
+        assert_eq!(task.retry_count(), 0);
+        assert_eq!(
+            limiter.endpoint(ENDPOINT).unwrap().backoff(),
+            Duration::ZERO
+        );
+    }
+
+    #[tokio::test]
+    async fn test_backoff_does_not_hold_an_rpc_slot() {
+        let rpc_limit = Arc::new(Semaphore::new(1));
+        let throttled =
+            RateLimiter::new().with_endpoint_limit(ENDPOINT, RateLimit::per_second(1000));
+        throttled.throttled(Some(ENDPOINT), &[]);
+        let backing_off = Arc::new(CountingExecutor::new(0));
+        let healthy = Arc::new(CountingExecutor::new(0));
+        let pools = [
+            (
+                Arc::clone(&backing_off),
+                Some(ENDPOINT.to_string()),
+                throttled,
+            ),
+            (Arc::clone(&healthy), None, RateLimiter::new()),
+        ]
+        .map(|(executor, endpoint, limiter)| {
+            let pool = WorkerPool::new(
+                Arc::new(Mutex::new(TaskScheduler::new())),
+                executor,
+                WorkerPoolConfig {
+                    rpc_limit: Arc::clone(&rpc_limit),
+                    rate_limiter: Arc::new(limiter),
+                    endpoint,
+                    ..config(1, 1)
+                },
+            );
+            pool.submit(Task::new("{}".to_string())).unwrap();
+            pool
+        });
+        let start = tokio::time::Instant::now();
+        let handles = pools.map(WorkerPool::spawn);
+
+        wait_for(&healthy, 1).await;
+        assert!(start.elapsed() < DEFAULT_INITIAL_BACKOFF);
+        assert_eq!(backing_off.done.load(Ordering::SeqCst), 0);
+        wait_for(&backing_off, 1).await;
+        for handle in handles {
+            handle.shutdown().await;
+        }
+    }
+
+    /// Sends and then hangs on the first attempt of `hung`; finishes anything else at once
+    struct HangOnceExecutor {
+        hung: String,
+        started: Mutex<Vec<String>>,
+    }
+
+    impl TaskExecutor for Arc<HangOnceExecutor> {
+        async fn execute(&self, task: Task, attempt: AttemptHandle) -> AttemptReport {
+            self.started.lock().unwrap().push(task.id().to_string());
+            if task.id() == self.hung && attempt.attempt() == 1 {
+                attempt.sent("lost", None);
+                std::future::pending::<()>().await;
+            }
+            AttemptReport::success("signature")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_timed_out_attempt_keeps_its_wallet_locked_until_resolved() {
+        let scheduler = Arc::new(Mutex::new(TaskScheduler::new()));
+        let wallet = Pubkey::new_unique();
+        let hung = transfer_from(wallet).with_timeout(Duration::from_millis(20));
+        let next = transfer_from(wallet);
+        let executor = Arc::new(HangOnceExecutor {
+            hung: hung.id().to_string(),
+            started: Mutex::new(Vec::new()),
+        });
+        let pool = WorkerPool::new(Arc::clone(&scheduler), Arc::clone(&executor), config(2, 2));
+        pool.submit(hung.clone()).unwrap();
+        pool.submit(next.clone()).unwrap();
+        let handle = pool.spawn();
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(*executor.started.lock().unwrap(), vec![hung.id()]);
+
+        // What the reaper does once the lease runs out and the signature is found
+        scheduler
+            .lock()
+            .unwrap()
+            .expire_attempt(hung.id(), 1, AttemptReport::success("lost"))
+            .unwrap();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while scheduler.lock().unwrap().task(next.id()).unwrap().status()
+                != TaskStatus::Completed
+            {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("parked task never ran");
+        handle.shutdown().await;
+
+        assert_eq!(
+            scheduler.lock().unwrap().task(hung.id()).unwrap().status(),
+            TaskStatus::Completed
+        );
+    }
+
+    /// Runs each attempt for `delay`, or forever if `delay` is `None`
+    struct SlowExecutor {
+        delay: Option<Duration>,
+    }
+
+    impl TaskExecutor for SlowExecutor {
+        async fn execute(&self, _task: Task, attempt: AttemptHandle) -> AttemptReport {
+            attempt.sent("sent", None);
+            match self.delay {
+                Some(delay) => tokio::time::sleep(delay).await,
+                None => std::future::pending().await,
+            }
+            AttemptReport::success("signature")
+        }
+    }
+
+    fn temp_store() -> FileTaskStore {
+        let path = std::env::temp_dir().join(format!("tasks-{}.jsonl", uuid::Uuid::new_v4()));
+        FileTaskStore::new(path)
+    }
+
+    async fn wait_until_in_flight(scheduler: &Mutex<TaskScheduler>, count: usize) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while scheduler.lock().unwrap().in_flight_count() < count {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("tasks were not dispatched");
+    }
+
+    #[tokio::test]
+    async fn test_graceful_shutdown_drains_in_flight() {
+        let scheduler = Arc::new(Mutex::new(TaskScheduler::new()));
+        let executor = SlowExecutor {
+            delay: Some(Duration::from_millis(50)),
+        };
+        let pool = WorkerPool::new(Arc::clone(&scheduler), executor, config(2, 2));
+        let running: Vec<Task> = (0..2)
+            .map(|_| pool.submit(Task::new("{}".to_string())).unwrap())
+            .collect();
+        let handle = pool.spawn();
+        wait_until_in_flight(&scheduler, 2).await;
+        let queued = scheduler
+            .lock()
+            .unwrap()
+            .schedule_task(Task::new("{}".to_string()))
+            .unwrap();
+        let store = temp_store();
+
+        let report = handle
+            .shutdown_gracefully(Duration::from_secs(5), &store)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            report,
+            ShutdownReport {
+                interrupted: 0,
+                persisted: 1
+            }
+        );
+        let persisted = store.load().unwrap();
+        assert_eq!(persisted.len(), 3);
+        let saved = |id: &str| persisted.iter().find(|task| task.id() == id).unwrap();
+        assert_eq!(saved(queued.id()).status(), TaskStatus::Pending);
+        let scheduler = scheduler.lock().unwrap();
+        for task in &running {
+            assert_eq!(
+                scheduler.task(task.id()).unwrap().status(),
+                TaskStatus::Completed
+            );
+            assert_eq!(saved(task.id()).status(), TaskStatus::Completed);
+        }
+        assert!(scheduler.is_shutting_down());
+        std::fs::remove_file(store.path()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_graceful_shutdown_persists_after_deadline() {
+        let scheduler = Arc::new(Mutex::new(TaskScheduler::new()));
+        let pool = WorkerPool::new(
+            Arc::clone(&scheduler),
+            SlowExecutor { delay: None },
+            config(1, 1),
+        );
+        let stuck = pool.submit(Task::new("{}".to_string())).unwrap();
+        let handle = pool.spawn();
+        wait_until_in_flight(&scheduler, 1).await;
+        let store = temp_store();
+
+        let report = handle
+            .shutdown_gracefully(Duration::from_millis(50), &store)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            report,
+            ShutdownReport {
+                interrupted: 1,
+                persisted: 1
+            }
+        );
+        let persisted = store.load().unwrap();
+        assert_eq!(persisted[0].id(), stuck.id());
+        assert_eq!(persisted[0].status(), TaskStatus::Pending);
+        assert_eq!(
+            persisted[0].attempts()[0].signature.as_deref(),
+            Some("sent")
+        );
+        std::fs::remove_file(store.path()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_dead_letters_survive_a_restart() {
+        let scheduler = Arc::new(Mutex::new(TaskScheduler::new().with_max_retries(0)));
+        let dead = {
+            let mut scheduler = scheduler.lock().unwrap();
+            let task = scheduler
+                .schedule_task(Task::new("{}".to_string()))
+                .unwrap();
+            scheduler.execute_next().unwrap();
+            scheduler
+                .fail_task(task.id(), "rpc down".to_string())
+                .unwrap();
+            task
+        };
+        let handle = WorkerPool::new(
+            Arc::clone(&scheduler),
+            SlowExecutor { delay: None },
+            config(1, 1),
+        )
+        .spawn();
+        let store = temp_store();
+
+        let report = handle
+            .shutdown_gracefully(Duration::from_secs(5), &store)
+            .await
+            .unwrap();
+
+        assert_eq!(report.persisted, 0);
+        let mut restarted = TaskScheduler::new();
+        restarted.load(store.load().unwrap()).unwrap();
+        assert_eq!(restarted.dead_letters()[0].id(), dead.id());
+        restarted.replay_dead_letter(dead.id()).unwrap();
+        assert_eq!(restarted.execute_next().unwrap().id(), dead.id());
+        std::fs::remove_file(store.path()).unwrap();
+    }
+}
@@ -0,0 +1,688 @@
+mod common;
+
+use common::{transfer, TestServer};
+use engine::wallet::Squad;
+use engine::Keystore;
+use reqwest::StatusCode;
+use serde_json::json;
+use server::api::{
+    BalanceReport, CreateSquadRequest, ErrorBody, Health, ImportWalletRequest, TreasuryInfo,
+    UpdateSquadRequest, WalletInfo,
+};
+use server::balances::CachedBalance;
+use server::{AppState, TopUpReport};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use std::time::Duration;
+use task_scheduler::{Task, TaskPage, TaskPriority, TaskStatus};
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+
+async fn error_of(response: reqwest::Response) -> String {
+    response.json::<ErrorBody>().await.unwrap().error
+}
+
+// Health
+
+#[tokio::test]
+async fn test_health_reports_counts() {
+    let server = TestServer::start().await;
+    server.generate_wallet("alpha").await;
+    server.schedule(&transfer(Pubkey::new_unique())).await;
+
+    let health: Health = server
+        .client
+        .get(server.url("/health"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(health.status, "ok");
+    assert_eq!(health.wallets, 1);
+    assert_eq!(health.pending, 1);
+    assert_eq!(health.in_flight, 0);
+    assert!(!health.shutting_down);
+}
+
+#[tokio::test]
+async fn test_serve_until_returns_once_signalled() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let (stop, stopped) = oneshot::channel::<()>();
+    let served = tokio::spawn(server::serve_until(listener, AppState::default(), async {
+        stopped.await.ok();
+    }));
+
+    stop.send(()).unwrap();
+
+    tokio::time::timeout(Duration::from_secs(5), served)
+        .await
+        .expect("server did not stop")
+        .unwrap()
+        .unwrap();
+}
+
+// Wallets
+
+#[tokio::test]
+async fn test_generate_and_list_wallets() {
+    let server = TestServer::start().await;
+    let bravo = server.generate_wallet("bravo").await;
+    let alpha = server.generate_wallet("alpha").await;
+
+    let wallets: Vec<WalletInfo> = server
+        .client
+        .get(server.url("/wallets"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(wallets, vec![alpha, bravo]);
+}
+
+#[tokio::test]
+async fn test_duplicate_wallet_conflicts() {
+    let server = TestServer::start().await;
+    server.generate_wallet("alpha").await;
+
+    let response = server
+        .client
+        .post(server.url("/wallets"))
+        .json(&json!({ "name": "alpha" }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    assert!(error_of(response).await.contains("already exists"));
+}
+
+#[tokio::test]
+async fn test_import_wallet() {
+    let server = TestServer::start().await;
+    let keypair = Keypair::new();
+
+    let response = server
+        .client
+        .post(server.url("/wallets/import"))
+        .json(&ImportWalletRequest {
+            name: "imported".to_string(),
+            keypair: keypair.to_bytes().to_vec(),
+        })
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let wallet: WalletInfo = response.json().await.unwrap();
+    assert_eq!(wallet.pubkey, keypair.pubkey());
+    let state = server.state.wallets.read().unwrap();
+    assert_eq!(state.get_pubkey("imported").unwrap(), keypair.pubkey());
+}
+
+#[tokio::test]
+async fn test_import_rejects_malformed_keypair() {
+    let server = TestServer::start().await;
+
+    let response = server
+        .client
+        .post(server.url("/wallets/import"))
+        .json(&ImportWalletRequest {
+            name: "broken".to_string(),
+            keypair: vec![1, 2, 3],
+        })
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_remove_wallet() {
+    let server = TestServer::start().await;
+    server.generate_wallet("alpha").await;
+
+    let removed = server
+        .client
+        .delete(server.url("/wallets/alpha"))
+        .send()
+        .await
+        .unwrap();
+    let missing = server
+        .client
+        .delete(server.url("/wallets/alpha"))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(removed.status(), StatusCode::NO_CONTENT);
+    assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_wallet_changes_are_saved_to_the_keystore() {
+    let path = std::env::temp_dir().join(format!("keystore-{}.json", uuid::Uuid::new_v4()));
+    let state = AppState::default().with_keystore(Keystore::new(&path));
+    let server = TestServer::start_with(state).await;
+    server.generate_wallet("alpha").await;
+    let beta = server.generate_wallet("beta").await;
+    server.generate_wallet("gamma").await;
+    server
+        .client
+        .post(server.url("/squads"))
+        .json(&json!({ "name": "red", "wallets": ["alpha"] }))
+        .send()
+        .await
+        .unwrap();
+    server
+        .client
+        .put(server.url("/treasury"))
+        .json(&json!({ "wallet": "beta" }))
+        .send()
+        .await
+        .unwrap();
+    server
+        .client
+        .delete(server.url("/wallets/gamma"))
+        .send()
+        .await
+        .unwrap();
+
+    let saved = Keystore::new(&path).load().unwrap();
+
+    assert_eq!(saved.wallet_count(), 2);
+    assert_eq!(saved.get_pubkey("beta").unwrap(), beta.pubkey);
+    assert!(saved.get_pubkey("gamma").is_err());
+    assert_eq!(saved.get_squad("red").unwrap().wallets, vec!["alpha"]);
+    assert_eq!(saved.treasury(), Some("beta"));
+    let _ = std::fs::remove_file(&path);
+}
+
+// Squads
+
+#[tokio::test]
+async fn test_squad_crud() {
+    let server = TestServer::start().await;
+    server.generate_wallet("alpha").await;
+    server.generate_wallet("bravo").await;
+
+    let created = server
+        .client
+        .post(server.url("/squads"))
+        .json(&CreateSquadRequest {
+            name: "red".to_string(),
+            campaign: Some("kamino".to_string()),
+            wallets: vec!["alpha".to_string()],
+        })
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(created.status(), StatusCode::CREATED);
+
+    let updated: Squad = server
+        .client
+        .put(server.url("/squads/red"))
+        .json(&UpdateSquadRequest {
+            campaign: None,
+            wallets: vec!["bravo".to_string(), "alpha".to_string()],
+        })
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(updated.campaign, None);
+    assert_eq!(updated.wallets, ["bravo", "alpha"]);
+
+    let fetched: Squad = server
+        .client
+        .get(server.url("/squads/red"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(fetched, updated);
+
+    let removed = server
+        .client
+        .delete(server.url("/squads/red"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(removed.status(), StatusCode::NO_CONTENT);
+
+    let squads: Vec<Squad> = server
+        .client
+        .get(server.url("/squads"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(squads.is_empty());
+}
+
+#[tokio::test]
+async fn test_create_squad_with_unknown_wallet_is_rolled_back() {
+    let server = TestServer::start().await;
+
+    let response = server
+        .client
+        .post(server.url("/squads"))
+        .json(&CreateSquadRequest {
+            name: "red".to_string(),
+            campaign: None,
+            wallets: vec!["ghost".to_string()],
+        })
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let fetched = server
+        .client
+        .get(server.url("/squads/red"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(fetched.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_update_missing_squad_is_not_found() {
+    let server = TestServer::start().await;
+
+    let response = server
+        .client
+        .put(server.url("/squads/red"))
+        .json(&UpdateSquadRequest {
+            campaign: None,
+            wallets: Vec::new(),
+        })
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+// Tasks
+
+#[tokio::test]
+async fn test_schedule_and_get_task() {
+    let server = TestServer::start().await;
+    let wallet = server.generate_wallet("alpha").await;
+    let mut request = transfer(wallet.pubkey);
+    request.priority = Some(TaskPriority::High);
+    request.tag = Some("kamino".to_string());
+
+    let (status, task) = server.schedule(&request).await;
+
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(task.priority(), TaskPriority::High);
+    assert_eq!(task.tag(), Some("kamino"));
+    assert_eq!(task.wallet(), Some(wallet.pubkey));
+    let fetched: Task = server
+        .client
+        .get(server.url(&format!("/tasks/{}", task.id())))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(fetched, task);
+}
+
+#[tokio::test]
+async fn test_idempotent_schedule_returns_existing_task() {
+    let server = TestServer::start().await;
+    let mut request = transfer(Pubkey::new_unique());
+    request.idempotency_key = Some("campaign-7/fund-3".to_string());
+
+    let (_, first) = server.schedule(&request).await;
+    let (status, second) = server.schedule(&request).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(second.id(), first.id());
+}
+
+#[tokio::test]
+async fn test_schedule_with_unknown_dependency_is_rejected() {
+    let server = TestServer::start().await;
+    let mut request = transfer(Pubkey::new_unique());
+    request.dependencies = vec!["missing".to_string()];
+
+    let response = server
+        .client
+        .post(server.url("/tasks"))
+        .json(&request)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(error_of(response).await, "Task depends on an unknown task");
+}
+
+#[tokio::test]
+async fn test_query_tasks_filters_and_pages() {
+    let server = TestServer::start().await;
+    let alpha = Pubkey::new_unique();
+    let bravo = Pubkey::new_unique();
+    for _ in 0..3 {
+        server.schedule(&transfer(alpha)).await;
+    }
+    server.schedule(&transfer(bravo)).await;
+
+    let page: TaskPage = server
+        .client
+        .get(server.url(&format!(
+            "/tasks?wallet={}&status=Pending,InProgress&limit=2",
+            alpha
+        )))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(page.total, 3);
+    assert_eq!(page.tasks.len(), 2);
+    assert!(page.tasks.iter().all(|task| task.wallet() == Some(alpha)));
+}
+
+#[tokio::test]
+async fn test_query_rejects_unknown_status() {
+    let server = TestServer::start().await;
+
+    let response = server
+        .client
+        .get(server.url("/tasks?status=Sleeping"))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_cancel_task() {
+    let server = TestServer::start().await;
+    let (_, task) = server.schedule(&transfer(Pubkey::new_unique())).await;
+    let cancel = server.url(&format!("/tasks/{}/cancel", task.id()));
+
+    let cancelled = server.client.post(&cancel).send().await.unwrap();
+    let again = server.client.post(&cancel).send().await.unwrap();
+
+    assert_eq!(cancelled.status(), StatusCode::NO_CONTENT);
+    assert_eq!(again.status(), StatusCode::CONFLICT);
+    let scheduler = server.state.scheduler.lock().unwrap();
+    assert_eq!(
+        scheduler.task(task.id()).unwrap().status(),
+        TaskStatus::Cancelled
+    );
+}
+
+#[tokio::test]
+async fn test_unknown_task_is_not_found() {
+    let server = TestServer::start().await;
+
+    let get = server
+        .client
+        .get(server.url("/tasks/missing"))
+        .send()
+        .await
+        .unwrap();
+    let cancel = server
+        .client
+        .post(server.url("/tasks/missing/cancel"))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(get.status(), StatusCode::NOT_FOUND);
+    assert_eq!(cancel.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_schedule_rejected_while_shutting_down() {
+    let server = TestServer::start().await;
+    server.state.scheduler.lock().unwrap().begin_shutdown();
+
+    let response = server
+        .client
+        .post(server.url("/tasks"))
+        .json(&transfer(Pubkey::new_unique()))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+}
+
+// Balances
+
+#[tokio::test]
+async fn test_balances_report_cache_lowest_first() {
+    let server = TestServer::start().await;
+    let before: BalanceReport = server
+        .client
+        .get(server.url("/balances"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(before.refreshed_at, None);
+    assert!(before.balances.is_empty());
+    {
+        let mut cache = server.state.balances.write().unwrap();
+        cache.refreshed_at = Some(1_000);
+        for (wallet, lamports) in [("alpha", 9_000_000), ("bravo", 2_000_000)] {
+            cache.balances.insert(
+                wallet.to_string(),
+                CachedBalance {
+                    wallet: wallet.to_string(),
+                    pubkey: Pubkey::new_unique(),
+                    lamports,
+                    fetched_at: 1_000,
+                },
+            );
+        }
+    }
+
+    let all: BalanceReport = server
+        .client
+        .get(server.url("/balances"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let low: BalanceReport = server
+        .client
+        .get(server.url("/balances?below=5000000"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(all.refreshed_at, Some(1_000));
+    let wallets: Vec<&str> = all.balances.iter().map(|b| b.wallet.as_str()).collect();
+    assert_eq!(wallets, ["bravo", "alpha"]);
+    assert_eq!(low.balances.len(), 1);
+    assert_eq!(low.balances[0].wallet, "bravo");
+}
+
+// Treasury
+
+#[tokio::test]
+async fn test_top_up_schedules_batch_transfers_once() {
+    let server = TestServer::start().await;
+    let bank = server.generate_wallet("bank").await;
+    let low = server.generate_wallet("low").await;
+    let full = server.generate_wallet("full").await;
+    server
+        .client
+        .post(server.url("/squads"))
+        .json(&json!({ "name": "red", "wallets": ["low", "full"] }))
+        .send()
+        .await
+        .unwrap();
+    let top_up_error = || async {
+        let response = server
+            .client
+            .post(server.url("/treasury/top-up"))
+            .send()
+            .await
+            .unwrap();
+        (response.status(), error_of(response).await)
+    };
+    assert_eq!(
+        top_up_error().await,
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Balances have not been fetched yet".to_string()
+        )
+    );
+    {
+        let mut cache = server.state.balances.write().unwrap();
+        cache.refreshed_at = Some(1_000);
+        cache.rent_exempt_minimum = Some(890_880);
+        for (wallet, lamports) in [(&bank, 1_000_000_000), (&low, 200_000), (&full, 4_000_000)] {
+            cache.balances.insert(
+                wallet.name.clone(),
+                CachedBalance {
+                    wallet: wallet.name.clone(),
+                    pubkey: wallet.pubkey,
+                    lamports,
+                    fetched_at: 1_000,
+                },
+            );
+        }
+    }
+    assert_eq!(
+        top_up_error().await,
+        (
+            StatusCode::BAD_REQUEST,
+            "No treasury wallet set".to_string()
+        )
+    );
+
+    server
+        .client
+        .put(server.url("/treasury"))
+        .json(&json!({ "wallet": "bank" }))
+        .send()
+        .await
+        .unwrap();
+    let response = server
+        .client
+        .put(server.url("/squads/red/top-up"))
+        .json(&json!({ "target_lamports": 500_000, "min_lamports": 100_000 }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        error_of(response).await,
+        "Top-up target 500000 is below the rent-exempt minimum 890880"
+    );
+    server
+        .client
+        .put(server.url("/squads/red/top-up"))
+        .json(&json!({ "target_lamports": 5_000_000, "min_lamports": 1_000_000 }))
+        .send()
+        .await
+        .unwrap();
+
+    let info: TreasuryInfo = server
+        .client
+        .get(server.url("/treasury"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(info.wallet.as_deref(), Some("bank"));
+    assert_eq!(info.pubkey, Some(bank.pubkey));
+    assert_eq!(info.policies["red"].target_lamports, 5_000_000);
+
+    let top_up = || async {
+        let response = server
+            .client
+            .post(server.url("/treasury/top-up"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        response.json::<TopUpReport>().await.unwrap()
+    };
+    let first = top_up().await;
+    let again = top_up().await;
+
+    assert_eq!(first.treasury, bank.pubkey);
+    assert_eq!(first.transfers, 1);
+    assert_eq!(first.total_lamports, 4_800_000);
+    assert_eq!(first.tasks.len(), 1);
+    let task = &first.tasks[0];
+    assert_eq!(task.tag(), Some("red"));
+    assert_eq!(task.wallet(), Some(bank.pubkey));
+    assert_eq!(task.params().unwrap().type_name(), "BatchTransfer");
+    // The first transfer is still in flight, so nothing is funded twice
+    assert_eq!(again.transfers, 0);
+    assert!(again.tasks.is_empty());
+    assert_eq!(
+        server.state.scheduler.lock().unwrap().get_pending_count(),
+        1
+    );
+}
+
+// Metrics
+
+#[tokio::test]
+async fn test_metrics_export_scheduler_and_wallet_gauges() {
+    let server = TestServer::start().await;
+    server.generate_wallet("alpha").await;
+    let mut request = transfer(Pubkey::new_unique());
+    request.priority = Some(TaskPriority::High);
+    server.schedule(&request).await;
+
+    let response = server
+        .client
+        .get(server.url("/metrics"))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let content_type = response.headers()["content-type"].to_str().unwrap();
+    assert!(content_type.starts_with("text/plain"));
+    let body = response.text().await.unwrap();
+    for metric in [
+        "sols_wallets ",
+        "sols_queue_depth{priority=\"high\"} ",
+        "sols_tasks{status=\"pending\",priority=\"high\"} ",
+        "sols_tasks_scheduled_total{priority=\"high\"} ",
+        "sols_dead_letters ",
+    ] {
+        assert!(body.contains(metric), "{metric} missing from:\n{body}");
+    }
+}
@@ -0,0 +1,228 @@
+mod common;
+
+use common::{transfer, TestServer};
+use reqwest::{Method, StatusCode};
+use serde_json::{json, Value};
+use server::api::{ExportedWallet, IssuedToken};
+use server::{ApiToken, Role};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+struct Endpoint {
+    method: Method,
+    path: &'static str,
+    body: Option<Value>,
+    role: Role,
+}
+
+fn endpoint(method: Method, path: &'static str, role: Role) -> Endpoint {
+    Endpoint {
+        method,
+        path,
+        body: None,
+        role,
+    }
+}
+
+impl Endpoint {
+    fn with_body(mut self, body: Value) -> Self {
+        self.body = Some(body);
+        self
+    }
+}
+
+fn endpoints() -> Vec<Endpoint> {
+    vec![
+        // Read-only
+        endpoint(Method::GET, "/wallets", Role::Viewer),
+        endpoint(Method::GET, "/squads", Role::Viewer),
+        endpoint(Method::GET, "/squads/red", Role::Viewer),
+        endpoint(Method::GET, "/tasks", Role::Viewer),
+        endpoint(Method::GET, "/tasks/missing", Role::Viewer),
+        endpoint(Method::GET, "/balances", Role::Viewer),
+        endpoint(Method::GET, "/events", Role::Viewer),
+        endpoint(Method::GET, "/metrics", Role::Viewer),
+        endpoint(Method::GET, "/treasury", Role::Viewer),
+        // Tasks and squads
+        endpoint(Method::POST, "/tasks", Role::Operator)
+            .with_body(serde_json::to_value(transfer(Pubkey::new_unique())).unwrap()),
+        endpoint(Method::POST, "/tasks/missing/cancel", Role::Operator),
+        endpoint(Method::POST, "/squads", Role::Operator).with_body(json!({ "name": "blue" })),
+        endpoint(Method::PUT, "/squads/red", Role::Operator)
+            .with_body(json!({ "campaign": null, "wallets": [] })),
+        endpoint(Method::DELETE, "/squads/missing", Role::Operator),
+        endpoint(Method::PUT, "/squads/red/top-up", Role::Operator)
+            .with_body(json!({ "target_lamports": 10_000_000, "min_lamports": 1_000_000 })),
+        endpoint(Method::DELETE, "/squads/missing/top-up", Role::Operator),
+        endpoint(Method::POST, "/treasury/top-up", Role::Operator),
+        // Wallets, keys and tokens
+        endpoint(Method::POST, "/wallets", Role::Admin).with_body(json!({ "name": "fresh" })),
+        endpoint(Method::POST, "/wallets/import", Role::Admin).with_body(json!({
+            "name": "imported",
+            "keypair": Keypair::new().to_bytes().to_vec(),
+        })),
+        endpoint(Method::DELETE, "/wallets/missing", Role::Admin),
+        endpoint(Method::POST, "/wallets/alpha/export", Role::Admin),
+        endpoint(Method::GET, "/tokens", Role::Admin),
+        endpoint(Method::POST, "/tokens", Role::Admin)
+            .with_body(json!({ "name": "bot", "role": "viewer" })),
+        endpoint(Method::POST, "/tokens/missing/revoke", Role::Admin),
+        endpoint(Method::PUT, "/treasury", Role::Admin).with_body(json!({ "wallet": "alpha" })),
+    ]
+}
+
+async fn status_as(server: &TestServer, endpoint: &Endpoint, role: Option<Role>) -> StatusCode {
+    let mut request = server.request_as(role, endpoint.method.clone(), endpoint.path);
+    if let Some(body) = &endpoint.body {
+        request = request.json(body);
+    }
+    request.send().await.unwrap().status()
+}
+
+#[tokio::test]
+async fn test_each_endpoint_enforces_its_role() {
+    let server = TestServer::start().await;
+    server.generate_wallet("alpha").await;
+    server
+        .client
+        .post(server.url("/squads"))
+        .json(&json!({ "name": "red" }))
+        .send()
+        .await
+        .unwrap();
+
+    for endpoint in endpoints() {
+        let label = format!("{} {}", endpoint.method, endpoint.path);
+        assert_eq!(
+            status_as(&server, &endpoint, None).await,
+            StatusCode::UNAUTHORIZED,
+            "{label} without a token"
+        );
+        for role in [Role::Viewer, Role::Operator, Role::Admin] {
+            let status = status_as(&server, &endpoint, Some(role)).await;
+            if role < endpoint.role {
+                assert_eq!(status, StatusCode::FORBIDDEN, "{label} as {:?}", role);
+            } else {
+                assert_ne!(status, StatusCode::UNAUTHORIZED, "{label} as {:?}", role);
+                assert_ne!(status, StatusCode::FORBIDDEN, "{label} as {:?}", role);
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_health_is_public() {
+    let server = TestServer::start().await;
+
+    let status = server
+        .request_as(None, Method::GET, "/health")
+        .send()
+        .await
+        .unwrap()
+        .status();
+
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_unknown_and_malformed_tokens_are_rejected() {
+    let server = TestServer::start().await;
+    let url = server.url("/wallets");
+    let client = reqwest::Client::new();
+
+    let unknown = client.get(&url).bearer_auth("nope").send().await.unwrap();
+    let basic = client
+        .get(&url)
+        .basic_auth("admin", Some(&server.secrets[&Role::Admin]))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(unknown.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(basic.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_issued_token_works_until_revoked() {
+    let server = TestServer::start().await;
+    let issued: IssuedToken = server
+        .client
+        .post(server.url("/tokens"))
+        .json(&json!({ "name": "dashboard", "role": "viewer" }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let list_tasks = || {
+        reqwest::Client::new()
+            .get(server.url("/tasks"))
+            .bearer_auth(&issued.secret)
+            .send()
+    };
+    assert_eq!(issued.token.role, Role::Viewer);
+    assert_eq!(list_tasks().await.unwrap().status(), StatusCode::OK);
+
+    let revoked = server
+        .client
+        .post(server.url(&format!("/tokens/{}/revoke", issued.token.id)))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(revoked.status(), StatusCode::NO_CONTENT);
+    assert_eq!(
+        list_tasks().await.unwrap().status(),
+        StatusCode::UNAUTHORIZED
+    );
+    let tokens: Vec<ApiToken> = server
+        .client
+        .get(server.url("/tokens"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let listed = tokens.iter().find(|token| token.id == issued.token.id);
+    assert!(listed.unwrap().is_revoked());
+}
+
+#[tokio::test]
+async fn test_token_listing_never_contains_secrets() {
+    let server = TestServer::start().await;
+
+    let body = server
+        .client
+        .get(server.url("/tokens"))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+
+    assert_eq!(serde_json::from_str::<Vec<Value>>(&body).unwrap().len(), 3);
+    assert!(server.secrets.values().all(|secret| !body.contains(secret)));
+}
+
+#[tokio::test]
+async fn test_admin_exports_wallet_keypair() {
+    let server = TestServer::start().await;
+    let wallet = server.generate_wallet("alpha").await;
+
+    let exported: ExportedWallet = server
+        .client
+        .post(server.url("/wallets/alpha/export"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(exported.pubkey, wallet.pubkey);
+    let keypair = Keypair::try_from(exported.keypair.as_slice()).unwrap();
+    assert_eq!(keypair.pubkey(), wallet.pubkey);
+}
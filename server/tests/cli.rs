@@ -0,0 +1,473 @@
+use clap::Parser;
+use engine::transaction::{TransactionParams, TransferParams};
+use engine::{Keystore, WalletManager};
+use serde_json::Value;
+use server::cli::{self, Cli};
+use server::lock::lock_store;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use task_scheduler::{FileTaskStore, TaskScheduler, TaskStatus, TaskStore};
+use uuid::Uuid;
+
+/// A keystore and task store in a scratch directory, removed on drop
+struct Workspace {
+    dir: PathBuf,
+}
+
+impl Workspace {
+    fn new() -> Self {
+        let dir = std::env::temp_dir().join(format!("sols-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        Self { dir }
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+
+    fn tasks(&self) -> FileTaskStore {
+        FileTaskStore::new(self.path("tasks.jsonl"))
+    }
+
+    async fn try_run(&self, args: &[&str]) -> Result<Value, String> {
+        let keystore = self.path("keystore.json");
+        let tasks = self.path("tasks.jsonl");
+        let mut argv = vec![
+            "sols",
+            "--json",
+            "--keystore",
+            keystore.to_str().unwrap(),
+            "--tasks",
+            tasks.to_str().unwrap(),
+        ];
+        argv.extend_from_slice(args);
+        cli::run(&Cli::try_parse_from(argv).unwrap())
+            .await
+            .map(|output| output.json)
+    }
+
+    async fn run(&self, args: &[&str]) -> Value {
+        self.try_run(args).await.unwrap()
+    }
+}
+
+impl Drop for Workspace {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn transfer_params(from: Pubkey) -> String {
+    serde_json::to_string(&TransactionParams::Transfer(TransferParams {
+        from,
+        to: Pubkey::new_unique(),
+        lamports: 1_000,
+    }))
+    .unwrap()
+}
+
+fn write_keypair(path: &Path, keypair: &Keypair) {
+    fs::write(
+        path,
+        serde_json::to_string(&keypair.to_bytes().to_vec()).unwrap(),
+    )
+    .unwrap();
+}
+
+// Wallets
+
+#[tokio::test]
+async fn test_wallets_persist_between_runs() {
+    let workspace = Workspace::new();
+
+    let generated = workspace.run(&["wallet", "generate", "bravo"]).await;
+    workspace.run(&["wallet", "generate", "alpha"]).await;
+    let listed = workspace.run(&["wallet", "list"]).await;
+
+    let names: Vec<&str> = listed
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|wallet| wallet["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["alpha", "bravo"]);
+    assert_eq!(listed[1]["pubkey"], generated["pubkey"]);
+}
+
+#[tokio::test]
+async fn test_import_then_export_round_trips_the_keypair() {
+    let workspace = Workspace::new();
+    let keypair = Keypair::new();
+    let keypair_file = workspace.path("imported.json");
+    write_keypair(&keypair_file, &keypair);
+
+    let imported = workspace
+        .run(&["wallet", "import", "cold", keypair_file.to_str().unwrap()])
+        .await;
+    let exported = workspace.run(&["wallet", "export", "cold"]).await;
+
+    assert_eq!(
+        imported["pubkey"],
+        serde_json::to_value(keypair.pubkey()).unwrap()
+    );
+    let bytes: Vec<u8> = serde_json::from_value(exported["keypair"].clone()).unwrap();
+    assert_eq!(bytes, keypair.to_bytes().to_vec());
+}
+
+#[tokio::test]
+async fn test_remove_wallet() {
+    let workspace = Workspace::new();
+    workspace.run(&["wallet", "generate", "alpha"]).await;
+
+    workspace.run(&["wallet", "remove", "alpha"]).await;
+
+    assert_eq!(
+        workspace.run(&["wallet", "list"]).await,
+        Value::Array(vec![])
+    );
+    let missing = workspace.try_run(&["wallet", "remove", "alpha"]).await;
+    assert!(missing.unwrap_err().contains("not found"));
+}
+
+#[tokio::test]
+async fn test_writes_are_refused_while_the_server_holds_the_stores() {
+    let workspace = Workspace::new();
+    let keystore = Keystore::new(workspace.path("keystore.json"));
+    // As the running server does, from startup until its last save
+    let locks = [
+        workspace.path("keystore.json"),
+        workspace.path("tasks.jsonl"),
+    ]
+    .map(|path| lock_store(&path, Duration::ZERO).unwrap());
+    let mut wallets = WalletManager::new();
+    wallets.generate_wallet("server".to_string()).unwrap();
+    keystore.save(&wallets).unwrap();
+
+    let generate = workspace.try_run(&["wallet", "generate", "cli"]).await;
+    let params = transfer_params(Pubkey::new_unique());
+    let schedule = workspace
+        .try_run(&["task", "schedule", "--params", &params])
+        .await;
+
+    for refused in [generate, schedule] {
+        let error = refused.unwrap_err();
+        assert!(error.contains("locked by another process"), "{}", error);
+    }
+    assert_eq!(keystore.load().unwrap().list_wallets().len(), 1);
+    assert!(!workspace.path("tasks.jsonl").exists());
+
+    drop(locks);
+    workspace.run(&["wallet", "generate", "cli"]).await;
+    let wallets = keystore.load().unwrap();
+    assert!(wallets.get_wallet("server").is_ok());
+    assert!(wallets.get_wallet("cli").is_ok());
+}
+
+// Squads
+
+#[tokio::test]
+async fn test_create_squad_and_add_wallets() {
+    let workspace = Workspace::new();
+    workspace.run(&["wallet", "generate", "alpha"]).await;
+    workspace.run(&["wallet", "generate", "bravo"]).await;
+
+    workspace
+        .run(&["squad", "create", "red", "--campaign", "kamino"])
+        .await;
+    workspace
+        .run(&["squad", "add", "red", "alpha", "bravo"])
+        .await;
+    let squads = workspace.run(&["squad", "list"]).await;
+
+    assert_eq!(squads[0]["name"], "red");
+    assert_eq!(squads[0]["campaign"], "kamino");
+    assert_eq!(squads[0]["wallets"], serde_json::json!(["alpha", "bravo"]));
+}
+
+#[tokio::test]
+async fn test_adding_unknown_wallet_fails() {
+    let workspace = Workspace::new();
+    workspace.run(&["squad", "create", "red"]).await;
+
+    let result = workspace.try_run(&["squad", "add", "red", "ghost"]).await;
+
+    assert!(result.is_err());
+}
+
+// Treasury
+
+#[tokio::test]
+async fn test_treasury_and_top_up_policy_persist() {
+    let workspace = Workspace::new();
+    workspace.run(&["wallet", "generate", "bank"]).await;
+    workspace.run(&["squad", "create", "red"]).await;
+
+    workspace.run(&["treasury", "set", "bank"]).await;
+    workspace
+        .run(&[
+            "treasury",
+            "policy",
+            "red",
+            "--target-sol",
+            "0.05",
+            "--min-sol",
+            "0.01",
+        ])
+        .await;
+    let treasury = workspace.run(&["treasury", "show"]).await;
+
+    assert_eq!(treasury["wallet"], "bank");
+    assert_eq!(treasury["policies"]["red"]["target_lamports"], 50_000_000);
+    assert_eq!(treasury["policies"]["red"]["min_lamports"], 10_000_000);
+
+    workspace.run(&["treasury", "clear-policy", "red"]).await;
+    let treasury = workspace.run(&["treasury", "show"]).await;
+    assert_eq!(treasury["policies"], serde_json::json!({}));
+}
+
+#[tokio::test]
+async fn test_top_up_policy_rejects_minimum_above_target() {
+    let workspace = Workspace::new();
+    workspace.run(&["squad", "create", "red"]).await;
+
+    let result = workspace
+        .try_run(&[
+            "treasury",
+            "policy",
+            "red",
+            "--target-sol",
+            "0.01",
+            "--min-sol",
+            "0.05",
+        ])
+        .await;
+
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_top_up_policy_rejects_target_below_rent_exemption() {
+    let workspace = Workspace::new();
+    workspace.run(&["squad", "create", "red"]).await;
+
+    let result = workspace
+        .try_run(&[
+            "treasury",
+            "policy",
+            "red",
+            "--target-sol",
+            "0.0005",
+            "--min-sol",
+            "0.0001",
+        ])
+        .await;
+
+    assert_eq!(
+        result.unwrap_err(),
+        "Top-up target 500000 is below the rent-exempt minimum 890880"
+    );
+}
+
+// Tasks
+
+#[tokio::test]
+async fn test_schedule_and_list_tasks() {
+    let workspace = Workspace::new();
+    let alpha = workspace.run(&["wallet", "generate", "alpha"]).await;
+    let alpha: Pubkey = serde_json::from_value(alpha["pubkey"].clone()).unwrap();
+    let params = transfer_params(alpha);
+
+    let scheduled = workspace
+        .run(&[
+            "task",
+            "schedule",
+            "--params",
+            &params,
+            "--priority",
+            "High",
+            "--tag",
+            "kamino",
+        ])
+        .await;
+    workspace
+        .run(&[
+            "task",
+            "schedule",
+            "--params",
+            &transfer_params(Pubkey::new_unique()),
+        ])
+        .await;
+    let page = workspace
+        .run(&["task", "list", "--wallet", "alpha", "--status", "Pending"])
+        .await;
+
+    assert_eq!(scheduled["priority"], "High");
+    assert_eq!(scheduled["tag"], "kamino");
+    assert_eq!(page["total"], 1);
+    assert_eq!(page["tasks"][0]["id"], scheduled["id"]);
+}
+
+#[tokio::test]
+async fn test_schedule_rejects_invalid_params() {
+    let workspace = Workspace::new();
+
+    let result = workspace
+        .try_run(&["task", "schedule", "--params", "{\"type\":\"Nope\"}"])
+        .await;
+
+    assert!(result.unwrap_err().starts_with("Invalid params"));
+    assert!(!workspace.path("tasks.jsonl").exists());
+}
+
+#[tokio::test]
+async fn test_cancel_task() {
+    let workspace = Workspace::new();
+    let params = transfer_params(Pubkey::new_unique());
+    let scheduled = workspace
+        .run(&["task", "schedule", "--params", &params])
+        .await;
+    let id = scheduled["id"].as_str().unwrap();
+
+    workspace.run(&["task", "cancel", id]).await;
+
+    let page = workspace
+        .run(&["task", "list", "--status", "Cancelled"])
+        .await;
+    assert_eq!(page["tasks"][0]["id"], id);
+    let again = workspace.try_run(&["task", "cancel", id]).await;
+    assert_eq!(again.unwrap_err(), "Task already finished");
+}
+
+#[tokio::test]
+async fn test_retry_requeues_dead_lettered_task() {
+    let workspace = Workspace::new();
+    let params = transfer_params(Pubkey::new_unique());
+    let scheduled = workspace
+        .run(&["task", "schedule", "--params", &params])
+        .await;
+    let id = scheduled["id"].as_str().unwrap();
+    // Exhaust its retries the way the worker pool would
+    let mut scheduler = TaskScheduler::new();
+    scheduler.load(workspace.tasks().load().unwrap()).unwrap();
+    while scheduler.dead_letter(id).is_none() {
+        scheduler.execute_next().unwrap();
+        scheduler.fail_task(id, "rpc down".to_string()).unwrap();
+    }
+    workspace.tasks().save(&scheduler.snapshot()).unwrap();
+
+    workspace.run(&["task", "retry", id]).await;
+
+    let tasks = workspace.tasks().load().unwrap();
+    let task = tasks.iter().find(|task| task.id() == id).unwrap();
+    assert_eq!(task.status(), TaskStatus::Pending);
+    assert_eq!(task.retry_count(), 0);
+    let again = workspace.try_run(&["task", "retry", id]).await;
+    assert!(again.is_err());
+}
+
+// Config
+
+#[tokio::test]
+async fn test_config_profile_sets_keystore_and_retry_policy() {
+    let workspace = Workspace::new();
+    let keystore = workspace.path("local-keys.json");
+    let config = workspace.path("sols.toml");
+    fs::write(
+        &config,
+        format!(
+            "[profiles.localnet]\n\
+             cluster = \"localnet\"\n\
+             rpc_url = \"http://127.0.0.1:8899\"\n\
+             ws_url = \"ws://127.0.0.1:8900\"\n\
+             keystore = {:?}\n\
+             [profiles.localnet.retry]\n\
+             max_retries = 0\n",
+            keystore.to_str().unwrap()
+        ),
+    )
+    .unwrap();
+    let tasks = workspace.path("tasks.jsonl");
+    let run = |args: &[&str]| {
+        let mut argv = vec![
+            "sols",
+            "--json",
+            "--config",
+            config.to_str().unwrap(),
+            "--profile",
+            "localnet",
+            "--tasks",
+            tasks.to_str().unwrap(),
+        ];
+        argv.extend_from_slice(args);
+        Cli::try_parse_from(argv).unwrap()
+    };
+
+    cli::run(&run(&["wallet", "generate", "alpha"]))
+        .await
+        .unwrap();
+    let params = transfer_params(Pubkey::new_unique());
+    let scheduled = cli::run(&run(&["task", "schedule", "--params", &params]))
+        .await
+        .unwrap();
+
+    assert!(keystore.exists());
+    assert_eq!(scheduled.json["max_retries"], 0);
+}
+
+#[tokio::test]
+async fn test_keystore_of_another_cluster_is_refused() {
+    let workspace = Workspace::new();
+    workspace
+        .run(&["--profile", "mainnet", "wallet", "generate", "whale"])
+        .await;
+
+    let result = workspace
+        .try_run(&["--profile", "devnet", "wallet", "list"])
+        .await;
+
+    assert_eq!(
+        result.unwrap_err(),
+        "Keystore belongs to mainnet, not devnet"
+    );
+    let listed = workspace
+        .run(&["--profile", "mainnet", "wallet", "list"])
+        .await;
+    assert_eq!(listed[0]["name"], "whale");
+}
+
+#[tokio::test]
+async fn test_unknown_profile_is_rejected() {
+    let workspace = Workspace::new();
+
+    let result = workspace
+        .try_run(&["--profile", "testnet", "wallet", "list"])
+        .await;
+
+    assert!(result.unwrap_err().contains("Unknown profile 'testnet'"));
+}
+
+// Output
+
+#[tokio::test]
+async fn test_text_output_is_human_readable() {
+    let workspace = Workspace::new();
+    let keystore = workspace.path("keystore.json");
+    let cli = Cli::parse_from([
+        "sols",
+        "--keystore",
+        keystore.to_str().unwrap(),
+        "wallet",
+        "generate",
+        "alpha",
+    ]);
+
+    let output = cli::run(&cli).await.unwrap();
+
+    assert!(!cli.json);
+    assert!(output.render(false).contains("alpha"));
+    let json: Value = serde_json::from_str(&output.render(true)).unwrap();
+    assert_eq!(json["name"], "alpha");
+}
@@ -0,0 +1,143 @@
+// Each test binary uses its own subset of these helpers
+#![allow(dead_code)]
+
+use engine::transaction::{TransactionParams, TransferParams};
+use reqwest::header::{HeaderMap, AUTHORIZATION};
+use reqwest::{Client, Method, RequestBuilder, StatusCode};
+use server::api::{GenerateWalletRequest, ScheduleTaskRequest, WalletInfo};
+use server::{AppState, Role, TokenStore};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::time::Duration;
+use task_scheduler::Task;
+use tokio::net::TcpListener;
+
+pub struct TestServer {
+    pub base: String,
+    /// Sends an admin token with every request
+    pub client: Client,
+    pub state: AppState,
+    /// Secrets of a token per role
+    pub secrets: HashMap<Role, String>,
+}
+
+impl TestServer {
+    pub async fn start() -> Self {
+        Self::start_with(AppState::default()).await
+    }
+
+    /// Serves `state`, adding a token per role
+    pub async fn start_with(state: AppState) -> Self {
+        let mut tokens = TokenStore::new();
+        let secrets: HashMap<Role, String> = [Role::Viewer, Role::Operator, Role::Admin]
+            .into_iter()
+            .map(|role| (role, tokens.issue(role.as_str(), role).unwrap().1))
+            .collect();
+        let state = state.with_tokens(tokens);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(server::serve(listener, state.clone()));
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            AUTHORIZATION,
+            format!("Bearer {}", secrets[&Role::Admin]).parse().unwrap(),
+        );
+        Self {
+            base,
+            client: Client::builder().default_headers(headers).build().unwrap(),
+            state,
+            secrets,
+        }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base, path)
+    }
+
+    /// A request authenticated as `role`, or not at all
+    pub fn request_as(&self, role: Option<Role>, method: Method, path: &str) -> RequestBuilder {
+        let request = Client::new().request(method, self.url(path));
+        match role {
+            Some(role) => request.bearer_auth(&self.secrets[&role]),
+            None => request,
+        }
+    }
+
+    pub async fn generate_wallet(&self, name: &str) -> WalletInfo {
+        let response = self
+            .client
+            .post(self.url("/wallets"))
+            .json(&GenerateWalletRequest {
+                name: name.to_string(),
+            })
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        response.json().await.unwrap()
+    }
+
+    pub async fn schedule(&self, request: &ScheduleTaskRequest) -> (StatusCode, Task) {
+        let response = self
+            .client
+            .post(self.url("/tasks"))
+            .json(request)
+            .send()
+            .await
+            .unwrap();
+        let status = response.status();
+        (status, response.json().await.unwrap())
+    }
+}
+
+pub fn transfer(from: Pubkey) -> ScheduleTaskRequest {
+    ScheduleTaskRequest::new(TransactionParams::Transfer(TransferParams {
+        from,
+        to: Pubkey::new_unique(),
+        lamports: 1_000,
+    }))
+}
+
+/// Reads Server-Sent Events off a streaming response
+pub struct EventStream {
+    response: reqwest::Response,
+    buffer: String,
+}
+
+impl EventStream {
+    pub async fn open(server: &TestServer, path: &str) -> Self {
+        let response = server.client.get(server.url(path)).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        Self {
+            response,
+            buffer: String::new(),
+        }
+    }
+
+    /// The next event's name and JSON data, skipping keep-alives
+    pub async fn next(&mut self) -> (String, serde_json::Value) {
+        loop {
+            while let Some(end) = self.buffer.find("\n\n") {
+                let frame: String = self.buffer.drain(..end + 2).collect();
+                let mut name = None;
+                let mut data = None;
+                for line in frame.lines() {
+                    if let Some(value) = line.strip_prefix("event:") {
+                        name = Some(value.trim().to_string());
+                    } else if let Some(value) = line.strip_prefix("data:") {
+                        data = Some(serde_json::from_str(value.trim()).unwrap());
+                    }
+                }
+                if let (Some(name), Some(data)) = (name, data) {
+                    return (name, data);
+                }
+            }
+            let chunk = tokio::time::timeout(Duration::from_secs(5), self.response.chunk())
+                .await
+                .expect("no event within 5s")
+                .unwrap()
+                .expect("event stream ended");
+            self.buffer.push_str(std::str::from_utf8(&chunk).unwrap());
+        }
+    }
+}
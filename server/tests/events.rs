@@ -0,0 +1,117 @@
+mod common;
+
+use common::{transfer, EventStream, TestServer};
+use reqwest::StatusCode;
+use server::api::CreateSquadRequest;
+use server::{BalanceChange, LiveEvent};
+use solana_sdk::pubkey::Pubkey;
+
+fn balance(pubkey: Pubkey, lamports: u64) -> LiveEvent {
+    LiveEvent::Balance(BalanceChange::new("wallet", pubkey, None, lamports))
+}
+
+#[tokio::test]
+async fn test_stream_sends_task_events_and_campaign_progress() {
+    let server = TestServer::start().await;
+    let mut events = EventStream::open(&server, "/events").await;
+    let mut request = transfer(Pubkey::new_unique());
+    request.tag = Some("kamino".to_string());
+
+    let (_, task) = server.schedule(&request).await;
+
+    let (name, event) = events.next().await;
+    assert_eq!(name, "task");
+    assert_eq!(event["type"], "task");
+    assert_eq!(event["task_id"], task.id());
+    assert_eq!(event["kind"], "Scheduled");
+    let (name, progress) = events.next().await;
+    assert_eq!(name, "campaign_progress");
+    assert_eq!(progress["tag"], "kamino");
+    assert_eq!(progress["total"], 1);
+    assert_eq!(progress["pending"], 1);
+}
+
+#[tokio::test]
+async fn test_stream_filters_by_event_type() {
+    let server = TestServer::start().await;
+    let mut events = EventStream::open(&server, "/events?types=balance").await;
+    let wallet = Pubkey::new_unique();
+
+    server.schedule(&transfer(wallet)).await;
+    server.state.feed.publish(balance(wallet, 5_000));
+
+    let (name, event) = events.next().await;
+    assert_eq!(name, "balance");
+    assert_eq!(event["lamports"], 5_000);
+}
+
+#[tokio::test]
+async fn test_stream_filters_by_squad() {
+    let server = TestServer::start().await;
+    let member = server.generate_wallet("alpha").await;
+    let outsider = server.generate_wallet("bravo").await;
+    let created = server
+        .client
+        .post(server.url("/squads"))
+        .json(&CreateSquadRequest {
+            name: "red".to_string(),
+            campaign: Some("kamino".to_string()),
+            wallets: vec!["alpha".to_string()],
+        })
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(created.status(), StatusCode::CREATED);
+    let mut events = EventStream::open(&server, "/events?squad=red").await;
+
+    // Neither the outsider's untagged task nor its balance concern the squad
+    server.schedule(&transfer(outsider.pubkey)).await;
+    server.state.feed.publish(balance(outsider.pubkey, 1));
+    let mut campaign_task = transfer(outsider.pubkey);
+    campaign_task.tag = Some("kamino".to_string());
+    let (_, task) = server.schedule(&campaign_task).await;
+
+    let (name, event) = events.next().await;
+    assert_eq!(
+        (name.as_str(), &event["task_id"]),
+        ("task", &task.id().into())
+    );
+    let (name, progress) = events.next().await;
+    assert_eq!(
+        (name.as_str(), &progress["tag"]),
+        ("campaign_progress", &"kamino".into())
+    );
+
+    server.state.feed.publish(balance(member.pubkey, 2));
+    let (name, event) = events.next().await;
+    assert_eq!(name, "balance");
+    assert_eq!(event["lamports"], 2);
+
+    let (_, task) = server.schedule(&transfer(member.pubkey)).await;
+    let (name, event) = events.next().await;
+    assert_eq!(
+        (name.as_str(), &event["task_id"]),
+        ("task", &task.id().into())
+    );
+}
+
+#[tokio::test]
+async fn test_stream_rejects_bad_filters() {
+    let server = TestServer::start().await;
+
+    let unknown_squad = server
+        .client
+        .get(server.url("/events?squad=ghost"))
+        .send()
+        .await
+        .unwrap();
+    let unknown_type = server
+        .client
+        .get(server.url("/events?types=task,weather"))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(unknown_squad.status(), StatusCode::NOT_FOUND);
+    assert_eq!(unknown_type.status(), StatusCode::BAD_REQUEST);
+}